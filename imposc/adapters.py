@@ -1,5 +1,5 @@
-from imposclib.imposclib import ParameterProperties, symbol_properties, group_properties, app_info, iterate, IterationInputs, IterationOutputs, validate
-from typing import Optional, Dict, Iterable
+from imposclib.imposclib import ParameterProperties, symbol_properties, group_properties, app_info, iterate, iterate_batch, iterate_from_state, pre_images, manifold, find_orbit, continue_orbit, grazing, orbit_chart, hysteresis_sweep, iterate_with_schedule, circle_map, invariant_density, fractal_dimension, recurrence, symbolic_dynamics, rotation_staircase, rotation_tongues, transient_lengths, basin_stability, IterationInputs, StateIterationInputs, StateIterationOutputs, IterationOutputs, BatchIterationInputs, BatchIterationOutputs, CancellationToken, PreImageOutputs, ManifoldOutputs, PeriodicOrbitOutputs, BranchOutputs, GrazingOutputs, OrbitChartOutputs, HysteresisOutputs, ScheduledOutputs, CircleMapOutputs, DensityOutputs, DimensionOutputs, RecurrenceOutputs, SymbolicOutputs, RotationOutputs, RotationChartOutputs, TransientOutputs, BasinStabilityOutputs, validate
+from typing import Callable, Optional, Dict, Iterable, List, Tuple
 
 def from_properties(properties: ParameterProperties) -> Iterable:
     return [dict([field for field in record]) for record in properties]
@@ -18,8 +18,65 @@ def parameter_info(category: str) -> Optional[Dict]:
     else:
         return None
 
-def iterate_impacts(inputs: IterationInputs) -> IterationOutputs:
-    return iterate(inputs)
+def iterate_impacts(inputs: IterationInputs, progress: Optional[Callable[[int], None]] = None, progress_interval: int = 1000, cancellation: Optional[CancellationToken] = None) -> IterationOutputs:
+    return iterate(inputs, progress, progress_interval, cancellation)
+
+def iterate_impacts_batch(inputs: BatchIterationInputs, keep_trajectories: bool = False) -> BatchIterationOutputs:
+    return iterate_batch(inputs, keep_trajectories)
+
+def iterate_impacts_from_state(inputs: StateIterationInputs) -> StateIterationOutputs:
+    return iterate_from_state(inputs)
+
+def impact_pre_images(inputs: IterationInputs) -> List[PreImageOutputs]:
+    return pre_images(inputs)
+
+def orbit_manifold(inputs: IterationInputs, impacts_per_cycle: int, stable: bool = False, negative_branch: bool = False, max_points: int = 2000, max_arc_length: float = 5.0) -> ManifoldOutputs:
+    return manifold(inputs, impacts_per_cycle, stable, negative_branch, max_points, max_arc_length)
+
+def find_periodic_orbit(inputs: IterationInputs, impacts: int, periods: int, from_trajectory: bool = False) -> PeriodicOrbitOutputs:
+    return find_orbit(inputs, impacts, periods, from_trajectory)
+
+def continue_periodic_orbit(inputs: IterationInputs, impacts: int, periods: int, parameter: str, end: float, max_points: int = 1000) -> BranchOutputs:
+    return continue_orbit(inputs, impacts, periods, parameter, end, max_points)
+
+def locate_grazing(inputs: IterationInputs, impacts: int, periods: int, parameter: str, towards: float) -> GrazingOutputs:
+    return grazing(inputs, impacts, periods, parameter, towards)
+
+def orbit_type_chart(inputs: IterationInputs, parameter: str, frequency_range: Tuple[float, float, int], parameter_range: Tuple[float, float, int], cancellation: Optional[CancellationToken] = None) -> Optional[OrbitChartOutputs]:
+    return orbit_chart(inputs, parameter, frequency_range, parameter_range, cancellation)
+
+def sweep_with_hysteresis(inputs: IterationInputs, parameter: str, parameter_range: Tuple[float, float, int], transient: int = 1000, recorded: int = 100, cancellation: Optional[CancellationToken] = None) -> Optional[HysteresisOutputs]:
+    return hysteresis_sweep(inputs, parameter, parameter_range, transient, recorded, cancellation)
+
+def iterate_impacts_with_schedule(inputs: IterationInputs, parameter: str, schedule: List[Tuple[float, float]]) -> ScheduledOutputs:
+    return iterate_with_schedule(inputs, parameter, schedule)
+
+def plastic_circle_map(inputs: IterationInputs, num_points: int = 200) -> CircleMapOutputs:
+    return circle_map(inputs, num_points)
+
+def impact_density(inputs: IterationInputs, phase_bins: int = 100, velocity_bins: int = 100, cancellation: Optional[CancellationToken] = None) -> DensityOutputs:
+    return invariant_density(inputs, phase_bins, velocity_bins, cancellation)
+
+def attractor_dimension(inputs: IterationInputs, method: str = "correlation", num_scales: int = 20) -> DimensionOutputs:
+    return fractal_dimension(inputs, method, num_scales)
+
+def impact_recurrence(inputs: IterationInputs, phase_tolerance: float = 1e-3, velocity_tolerance: float = 1e-3, cancellation: Optional[CancellationToken] = None) -> Optional[RecurrenceOutputs]:
+    return recurrence(inputs, phase_tolerance, velocity_tolerance, cancellation)
+
+def impact_symbols(inputs: IterationInputs, max_word_length: int = 6, partition: Optional[List[float]] = None) -> SymbolicOutputs:
+    return symbolic_dynamics(inputs, max_word_length, partition)
+
+def devils_staircase(inputs: IterationInputs, parameter: str, parameter_range: Tuple[float, float, int], transient: int = 1000, recorded: int = 200, max_impacts: int = 20, tolerance: float = 1e-3, cancellation: Optional[CancellationToken] = None) -> Optional[RotationOutputs]:
+    return rotation_staircase(inputs, parameter, parameter_range, transient, recorded, max_impacts, tolerance, cancellation)
+
+def arnold_tongues(inputs: IterationInputs, parameter: str, frequency_range: Tuple[float, float, int], parameter_range: Tuple[float, float, int], transient: int = 1000, recorded: int = 200, max_impacts: int = 20, tolerance: float = 1e-3, cancellation: Optional[CancellationToken] = None) -> Optional[RotationChartOutputs]:
+    return rotation_tongues(inputs, parameter, frequency_range, parameter_range, transient, recorded, max_impacts, tolerance, cancellation)
+
+def transient_length_grid(inputs: IterationInputs, phase_range: Tuple[float, float, int], velocity_range: Tuple[float, float, int], phase_tolerance: float = 1e-3, velocity_tolerance: float = 1e-3, cancellation: Optional[CancellationToken] = None) -> Optional[TransientOutputs]:
+    return transient_lengths(inputs, phase_range, velocity_range, phase_tolerance, velocity_tolerance, cancellation)
+
+def basin_fractions(inputs: IterationInputs, velocity_range: Tuple[float, float], phase_range: Optional[Tuple[float, float]] = None, displacement_range: Optional[Tuple[float, float]] = None, seed: int = 0, precision: float = 0.01, batch_size: int = 100, max_samples: int = 10000, cancellation: Optional[CancellationToken] = None) -> BasinStabilityOutputs:
+    return basin_stability(inputs, velocity_range, phase_range, displacement_range, seed, precision, batch_size, max_samples, cancellation)
 
 def validate_iter_inputs(inputs: IterationInputs) -> IterationInputs:
     return validate(inputs)
@@ -12,10 +12,126 @@ def files_size(files: List[Tuple[Union[str, Path], int]]) -> int:
     return sum([x[1] for x in files])
 
 def scatter_plot(data):
-    x, y = zip(*map(lambda impact: (impact.phase(), impact.velocity()), data))
+    x, y = data.phases(), data.velocities()
 
     file_name = unique_file_name("png")
     plt.plot(x, y, linestyle='', marker='.', markersize=1, mec='black', mfc='black')
+
+    amplitude = data.non_impacting_amplitude()
+    if amplitude is not None:
+        plt.title(f"Settles on non-impacting motion (amplitude {amplitude:.4g})")
+    plt.savefig(file_name)
+    plt.close()
+
+    return file_name
+
+def orbit_chart_plot(chart):
+    """ Colours each cell of a two-parameter grid by the type of orbit found there """
+    legend = chart.legend()
+    colours = plt.get_cmap('tab20', max(len(legend), 1))
+
+    file_name = unique_file_name("png")
+    plt.pcolormesh(chart.frequencies(), chart.values(), chart.cells(), cmap=colours, vmin=-0.5, vmax=len(legend) - 0.5, shading='nearest')
+
+    handles = [plt.Rectangle((0, 0), 1, 1, color=colours(i)) for i in range(len(legend))]
+    plt.legend(handles, legend, loc='center left', bbox_to_anchor=(1.0, 0.5), fontsize='small')
+    plt.xlabel("frequency")
+    plt.ylabel(chart.parameter())
+    plt.savefig(file_name, bbox_inches='tight')
+    plt.close()
+
+    return file_name
+
+def hysteresis_plot(result, coordinate: str = "velocity"):
+    """ Overlays the up- and down-sweeps of a bifurcation diagram, so that coexisting branches can be told apart """
+    file_name = unique_file_name("png")
+
+    for sweep, colour in ((result.up(), 'tab:blue'), (result.down(), 'tab:orange')):
+        y = sweep.velocities() if coordinate == "velocity" else sweep.phases()
+        plt.plot(sweep.parameters(), y, linestyle='', marker='.', markersize=1, mec=colour, mfc=colour, label=f"{sweep.direction()}-sweep")
+
+    plt.xlabel(result.parameter())
+    plt.ylabel(coordinate)
+    plt.legend(markerscale=10)
+    plt.savefig(file_name)
+    plt.close()
+
+    return file_name
+
+def density_plot(result):
+    """ Heat map of how often each region of the impact surface is visited """
+    file_name = unique_file_name("png")
+
+    plt.pcolormesh(result.phase_edges(), result.velocity_edges(), result.density(), cmap='viridis', shading='flat')
+    plt.colorbar(label="density")
+    plt.xlabel("phase")
+    plt.ylabel("velocity")
+    plt.savefig(file_name)
+    plt.close()
+
+    return file_name
+
+def recurrence_plot(result):
+    """ Marks each pair of impacts which recur, with the recurrence quantification measures in the title """
+    file_name = unique_file_name("png")
+
+    # The matrix is symmetric, and only the pairs above the diagonal are returned
+    plt.plot(result.rows(), result.columns(), linestyle='', marker=',', color='black')
+    plt.plot(result.columns(), result.rows(), linestyle='', marker=',', color='black')
+    plt.xlim(0, result.size())
+    plt.ylim(0, result.size())
+    plt.gca().set_aspect('equal')
+    plt.xlabel("impact")
+    plt.ylabel("impact")
+    plt.title(f"RR = {result.recurrence_rate():.3f}, DET = {result.determinism():.3f}, LAM = {result.laminarity():.3f}", fontsize='small')
+    plt.savefig(file_name)
+    plt.close()
+
+    return file_name
+
+def staircase_plot(result):
+    """ Rotation number against the swept parameter, with locked values in a separate colour """
+    file_name = unique_file_name("png")
+
+    locked = [locking is not None for locking in result.lockings()]
+    values, rotation_numbers = result.values(), result.rotation_numbers()
+
+    plt.plot(values[[not flag for flag in locked]], rotation_numbers[[not flag for flag in locked]], linestyle='', marker='.', markersize=2, color='tab:grey', label="unlocked")
+    plt.plot(values[locked], rotation_numbers[locked], linestyle='', marker='.', markersize=2, color='tab:blue', label="locked")
+    plt.xlabel(result.parameter())
+    plt.ylabel("forcing periods per impact")
+    plt.legend(markerscale=5)
+    plt.savefig(file_name)
+    plt.close()
+
+    return file_name
+
+def transient_plot(result):
+    """ Colours each initial impact by the number of impacts it takes to settle, leaving blank those which never do """
+    file_name = unique_file_name("png")
+
+    lengths = [[float('nan') if length is None else length for length in row] for row in result.lengths()]
+
+    plt.pcolormesh(result.phases(), result.velocities(), lengths, cmap='magma', shading='nearest')
+    plt.colorbar(label="transient length (impacts)")
+    plt.xlabel("initial phase")
+    plt.ylabel("initial velocity")
+    plt.savefig(file_name)
+    plt.close()
+
+    return file_name
+
+def basin_stability_plot(result):
+    """ Bar chart of the fraction of initial conditions ending on each attractor, with 95% confidence intervals """
+    file_name = unique_file_name("png")
+
+    fractions = result.fractions()
+    errors = [fractions - result.lower(), result.upper() - fractions]
+
+    plt.bar(result.labels(), fractions, yerr=errors, capsize=4, color='tab:blue')
+    plt.ylim(0, 1)
+    plt.ylabel("basin fraction")
+    plt.title(f"{result.samples()} samples" + ("" if result.converged() else " (precision not reached)"), fontsize='small')
     plt.savefig(file_name)
     plt.close()
 
@@ -0,0 +1,71 @@
+import json
+import sys
+from argparse import ArgumentParser, Namespace
+from typing import List, Optional
+
+from adapters import orbit_type_chart, IterationInputs
+from charts import orbit_chart_plot
+
+def orbit_chart_command(args: Namespace) -> None:
+    inputs = IterationInputs(
+        frequency = args.min_frequency,
+        offset = args.offset,
+        r = args.r,
+        max_periods = args.max_periods,
+        phi = args.phi,
+        v = args.v,
+        num_iterations = args.num_iterations)
+
+    chart = orbit_type_chart(
+        inputs,
+        args.parameter,
+        (args.min_frequency, args.max_frequency, args.frequency_steps),
+        (args.min_value, args.max_value, args.value_steps))
+
+    if args.image:
+        print(orbit_chart_plot(chart))
+    else:
+        print(json.dumps({
+            "parameter": chart.parameter(),
+            "frequencies": chart.frequencies().tolist(),
+            "values": chart.values().tolist(),
+            "cells": chart.cells(),
+            "legend": chart.legend()}))
+
+def parser() -> ArgumentParser:
+    result = ArgumentParser(prog="imposc", description="Impact oscillator analyses")
+
+    commands = result.add_subparsers(dest="command", required=True)
+
+    orbit_chart = commands.add_parser("orbit-chart", help="Orbit types over a grid of forcing frequency and either obstacle offset or coefficient of restitution")
+    orbit_chart.add_argument("--parameter", choices=("offset", "r"), default="offset", help="Parameter varied against forcing frequency")
+    orbit_chart.add_argument("--min-frequency", type=float, default=2.0, help="Lowest forcing frequency")
+    orbit_chart.add_argument("--max-frequency", type=float, default=5.0, help="Highest forcing frequency")
+    orbit_chart.add_argument("--frequency-steps", type=int, default=50, help="Number of forcing frequencies")
+    orbit_chart.add_argument("--min-value", type=float, default=-0.2, help="Lowest value of the other parameter")
+    orbit_chart.add_argument("--max-value", type=float, default=0.2, help="Highest value of the other parameter")
+    orbit_chart.add_argument("--value-steps", type=int, default=50, help="Number of values of the other parameter")
+    orbit_chart.add_argument("--offset", type=float, default=0.0, help="Obstacle offset, if not varied")
+    orbit_chart.add_argument("--r", type=float, default=0.8, help="Coefficient of restitution, if not varied")
+    orbit_chart.add_argument("--max-periods", type=int, default=100, help="Number of periods without an impact after which the algorithm will report 'long excursions'")
+    orbit_chart.add_argument("--phi", type=float, default=0.0, help="Phase at initial impact")
+    orbit_chart.add_argument("--v", type=float, default=0.0, help="Velocity at initial impact")
+    orbit_chart.add_argument("--num-iterations", type=int, default=1000, help="Number of iterations of impact map for each cell")
+    orbit_chart.add_argument("--image", action="store_true", help="Plot the chart to a PNG file and print its name, instead of printing the chart as JSON")
+    orbit_chart.set_defaults(run=orbit_chart_command)
+
+    return result
+
+def main(argv: Optional[List[str]] = None) -> int:
+    args = parser().parse_args(argv)
+
+    try:
+        args.run(args)
+    except ValueError as error:
+        print(f"{args.command}: {error}", file=sys.stderr)
+        return 1
+
+    return 0
+
+if __name__ == "__main__":
+    sys.exit(main())
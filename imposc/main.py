@@ -1,14 +1,18 @@
 from pathlib import Path
 from dataclasses import dataclass
+from typing import Optional
+import asyncio
 import io
-from fastapi import FastAPI, HTTPException, Depends, Query
-from adapters import iterate_impacts, parameter_info, get_app_info
+import math
+from fastapi import FastAPI, HTTPException, Depends, Query, Request
+from adapters import iterate_impacts, orbit_type_chart, sweep_with_hysteresis, iterate_impacts_with_schedule, impact_density, impact_recurrence, devils_staircase, transient_length_grid, basin_fractions, parameter_info, get_app_info
 from fastapi.staticfiles import StaticFiles
 from fastapi.responses import RedirectResponse
+from starlette.concurrency import run_in_threadpool
 from starlette.responses import StreamingResponse
 from logging import warning
-from imposclib.imposclib import IterationInputs
-from charts import scatter_plot
+from imposclib.imposclib import IterationInputs, IterationOutputs, CancellationToken
+from charts import scatter_plot, orbit_chart_plot, hysteresis_plot, density_plot, recurrence_plot, staircase_plot, transient_plot, basin_stability_plot
 
 app = FastAPI(**(get_app_info())) # TODO: configure info and info endpoint from file
 
@@ -40,31 +44,85 @@ async def read_parameter_info(category):
         return result
 
 @dataclass
-class IterationQueryData:
+class SystemQueryData:
+    """ The system parameters shared by every analysis. Any of them which an analysis varies is ignored. """
     frequency: float = Query(2.8, title="Forcing frequency", gt=0)
     offset: float = Query(0.0, title="Obstacle offset")
     r: float = Query(0.8, title="Coefficient of restitution", ge=0, le=1)
     max_periods: int = Query(100, title="Number of periods without an impact after which the algorithm will report 'long excursions'", gt=0)
-    phi: float = Query(0.5, title="Phase at initial impact")
-    v: float = Query(0.0, title="Velocity at initial impact")
-    num_iterations: int = Query(5000, title="Number of iterations of impact map")
 
-    def __call__(self) -> IterationInputs:
+    def inputs(self, phi: float, v: float, num_iterations: int, **kwargs) -> IterationInputs:
         return IterationInputs(
             frequency = self.frequency,
             offset = self.offset,
             r = self.r,
             max_periods = self.max_periods,
-            phi = self.phi,
-            v = self.v,
-            num_iterations = self.num_iterations)
+            phi = phi,
+            v = v,
+            num_iterations = num_iterations,
+            **kwargs)
+
+@dataclass
+class InitialImpactQueryData(SystemQueryData):
+    """ The system parameters together with the impact from which the map is iterated """
+    phi: float = Query(0.0, title="Phase at initial impact")
+    v: float = Query(0.0, title="Velocity at initial impact")
+
+    def inputs(self, num_iterations: int, **kwargs) -> IterationInputs:
+        return super().inputs(self.phi, self.v, num_iterations, **kwargs)
+
+async def run_while_connected(request: Request, function, *args):
+    """ Runs `function` off the event loop, cancelling the run through its `cancellation` argument if the client disconnects """
+    cancellation = CancellationToken()
+
+    task = asyncio.ensure_future(run_in_threadpool(function, *args, cancellation=cancellation))
+
+    while not task.done():
+        if await request.is_disconnected():
+            cancellation.cancel()
+
+        await asyncio.wait({task}, timeout=0.1)
+
+    if cancellation.is_cancelled():
+        # Nobody is waiting for what was computed before the run stopped
+        respond_with_error(status_code=499, detail="Client closed request")
+
+    return task.result()
+
+async def run_analysis(data, request: Optional[Request] = None):
+    """
+    Runs an analysis off the event loop, responding with an error if its inputs are invalid. Given the request, the
+    analysis is cancelled if the client disconnects.
+    """
+    try:
+        if request is None:
+            return await run_in_threadpool(data)
+
+        return await run_while_connected(request, data)
+    except ValueError as error:
+        respond_with_error(status_code=400, detail=str(error))
+
+@dataclass
+class IterationQueryData(InitialImpactQueryData):
+    phi: float = Query(0.5, title="Phase at initial impact")
+    num_iterations: int = Query(5000, title="Number of iterations of impact map")
+    skip_impacts: int = Query(0, title="Number of initial (transient) impacts to discard", ge=0)
+    keep_every: int = Query(1, title="Keep only every k-th impact after the transient", gt=0)
+    max_points: int = Query(0, title="Maximum number of impacts to return (0 for no limit)", ge=0)
+
+    def __call__(self) -> IterationInputs:
+        return self.inputs(self.num_iterations, skip_impacts = self.skip_impacts, keep_every = self.keep_every, max_points = self.max_points)
         
+async def iterate_while_connected(request: Request, inputs: IterationInputs) -> IterationOutputs:
+    """ Iterates the impact map off the event loop, cancelling the run if the client disconnects """
+    return await run_while_connected(request, iterate_impacts, inputs)
+
 @app.get("/api/iteration/data")
-async def read_iteration_data(data: IterationQueryData=Depends()):
+async def read_iteration_data(request: Request, data: IterationQueryData=Depends()):
     if data is None:
         respond_with_error(status_code=400, detail="Form inputs not found")
 
-    result = iterate_impacts(data())
+    result = await iterate_while_connected(request, data())
 
     if result is None:
         respond_with_error(status_code=404, detail="Parameter info category not found")
@@ -97,14 +155,299 @@ def image_response(filename: str) -> StreamingResponse:
             )
 
 @app.get("/api/iteration/image", summary="Scatter Plot")
-async def read_iteration_plot(data: IterationQueryData=Depends()):
+async def read_iteration_plot(request: Request, data: IterationQueryData=Depends()):
     """ Scatter plot from iterating the impact map for a specified set of parameters """
     if data is None:
         respond_with_error(status_code=400, detail="Form inputs not found")
 
-    result = iterate_impacts(data())
+    result = await iterate_while_connected(request, data())
 
     if result is None:
         respond_with_error(status_code=404, detail="Parameter info category not found")
     else:
         return image_response(scatter_plot(result))
+
+@dataclass
+class OrbitChartQueryData(InitialImpactQueryData):
+    parameter: str = Query("offset", title="Parameter varied against forcing frequency (offset or r)", regex="^(offset|r)$")
+    min_frequency: float = Query(2.0, title="Lowest forcing frequency", gt=0)
+    max_frequency: float = Query(5.0, title="Highest forcing frequency", gt=0)
+    frequency_steps: int = Query(50, title="Number of forcing frequencies", gt=0, le=500)
+    min_value: float = Query(-0.2, title="Lowest value of the other parameter")
+    max_value: float = Query(0.2, title="Highest value of the other parameter")
+    value_steps: int = Query(50, title="Number of values of the other parameter", gt=0, le=500)
+    num_iterations: int = Query(1000, title="Number of iterations of impact map for each cell", gt=0, le=100000)
+
+    def __call__(self, cancellation: Optional[CancellationToken] = None):
+        inputs = self.inputs(self.num_iterations)
+
+        return orbit_type_chart(
+            inputs,
+            self.parameter,
+            (self.min_frequency, self.max_frequency, self.frequency_steps),
+            (self.min_value, self.max_value, self.value_steps),
+            cancellation)
+
+@app.get("/api/orbit-chart/data")
+async def read_orbit_chart_data(request: Request, data: OrbitChartQueryData=Depends()):
+    """ Orbit types over a grid of forcing frequency and either obstacle offset or coefficient of restitution """
+    chart = await run_analysis(data, request)
+
+    return {
+        "parameter": chart.parameter(),
+        "frequencies": chart.frequencies().tolist(),
+        "values": chart.values().tolist(),
+        "cells": chart.cells(),
+        "legend": chart.legend()}
+
+@app.get("/api/orbit-chart/image", summary="Orbit Chart")
+async def read_orbit_chart_plot(request: Request, data: OrbitChartQueryData=Depends()):
+    """ Chart of orbit types over a grid of forcing frequency and either obstacle offset or coefficient of restitution """
+    chart = await run_analysis(data, request)
+
+    return image_response(orbit_chart_plot(chart))
+
+@dataclass
+class HysteresisQueryData(InitialImpactQueryData):
+    parameter: str = Query("frequency", title="Parameter to sweep (frequency, offset or r)", regex="^(frequency|offset|r)$")
+    min_value: float = Query(2.0, title="Lowest value of the swept parameter")
+    max_value: float = Query(5.0, title="Highest value of the swept parameter")
+    steps: int = Query(100, title="Number of values of the swept parameter", gt=0, le=1000)
+    transient: int = Query(1000, title="Number of impacts to discard at each parameter value", ge=0, le=10000)
+    recorded: int = Query(100, title="Number of impacts to record at each parameter value", gt=0, le=1000)
+
+    def __call__(self, cancellation: Optional[CancellationToken] = None):
+        inputs = self.inputs(self.transient + self.recorded)
+
+        return sweep_with_hysteresis(inputs, self.parameter, (self.min_value, self.max_value, self.steps), self.transient, self.recorded, cancellation)
+
+def sweep_to_json(sweep):
+    return {
+        "direction": sweep.direction(),
+        "parameters": sweep.parameters().tolist(),
+        "phases": sweep.phases().tolist(),
+        "velocities": sweep.velocities().tolist(),
+        "orbit_types": sweep.orbit_types(),
+        "jumps": sweep.jumps()}
+
+@app.get("/api/hysteresis/data")
+async def read_hysteresis_data(request: Request, data: HysteresisQueryData=Depends()):
+    """ Up- and down-sweeps of one parameter, carrying the state of the system from each value to the next """
+    result = await run_analysis(data, request)
+
+    return {
+        "parameter": result.parameter(),
+        "up": sweep_to_json(result.up()),
+        "down": sweep_to_json(result.down()),
+        "coexisting": result.coexisting()}
+
+@app.get("/api/hysteresis/image", summary="Hysteresis Bifurcation Diagram")
+async def read_hysteresis_plot(request: Request, data: HysteresisQueryData=Depends(), coordinate: str = Query("velocity", regex="^(phase|velocity)$")):
+    """ Bifurcation diagram overlaying the up- and down-sweeps of one parameter """
+    result = await run_analysis(data, request)
+
+    return image_response(hysteresis_plot(result, coordinate))
+
+@dataclass
+class RampQueryData(InitialImpactQueryData):
+    parameter: str = Query("frequency", title="Parameter to ramp (frequency, offset or r)", regex="^(frequency|offset|r)$")
+    start_value: float = Query(2.0, title="Value of the ramped parameter at the start of the run")
+    end_value: float = Query(3.0, title="Value of the ramped parameter at the end of the ramp")
+    duration: float = Query(1000.0, title="Duration of the ramp, after which the parameter is held at its end value", gt=0)
+    num_iterations: int = Query(1000, title="Number of iterations of impact map", gt=0)
+
+    def __call__(self):
+        inputs = self.inputs(self.num_iterations)
+
+        return iterate_impacts_with_schedule(inputs, self.parameter, [(0.0, self.start_value), (self.duration, self.end_value)])
+
+@app.get("/api/ramp/data")
+async def read_ramp_data(data: RampQueryData=Depends()):
+    """ Impacts from a run in which one parameter is ramped linearly, each paired with the parameter value at that time """
+    result = await run_analysis(data)
+
+    return {
+        "parameter": result.parameter(),
+        "times": result.times().tolist(),
+        "phases": result.phases().tolist(),
+        "velocities": result.velocities().tolist(),
+        "values": result.values().tolist(),
+        "termination": result.termination()}
+
+@dataclass
+class DensityQueryData(InitialImpactQueryData):
+    phi: float = Query(0.5, title="Phase at initial impact")
+    num_iterations: int = Query(100000, title="Number of iterations of impact map", gt=0, le=10000000)
+    skip_impacts: int = Query(1000, title="Number of initial (transient) impacts to leave out of the density", ge=0)
+    phase_bins: int = Query(100, title="Number of phase bins", gt=0, le=1000)
+    velocity_bins: int = Query(100, title="Number of velocity bins", gt=1, le=1000)
+
+    def __call__(self, cancellation: Optional[CancellationToken] = None):
+        inputs = self.inputs(self.num_iterations, skip_impacts = self.skip_impacts)
+
+        return impact_density(inputs, self.phase_bins, self.velocity_bins, cancellation)
+
+@app.get("/api/density/data")
+async def read_density_data(request: Request, data: DensityQueryData=Depends()):
+    """ Estimated invariant density of impacts over the impact surface """
+    result = await run_analysis(data, request)
+
+    return {
+        "phase_edges": result.phase_edges().tolist(),
+        "velocity_edges": result.velocity_edges().tolist(),
+        "density": result.density(),
+        "total": result.total()}
+
+@app.get("/api/density/image", summary="Invariant Density")
+async def read_density_plot(request: Request, data: DensityQueryData=Depends()):
+    """ Heat map of the estimated invariant density of impacts over the impact surface """
+    result = await run_analysis(data, request)
+
+    return image_response(density_plot(result))
+
+@dataclass
+class RecurrenceQueryData(InitialImpactQueryData):
+    phi: float = Query(0.5, title="Phase at initial impact")
+    # The plot can be nearly dense, with a bit for every pair of impacts and an entry in the response for every
+    # recurrent pair
+    num_iterations: int = Query(2000, title="Number of iterations of impact map", gt=0, le=3000)
+    skip_impacts: int = Query(1000, title="Number of initial (transient) impacts to leave out", ge=0)
+    phase_tolerance: float = Query(1e-3, title="Largest difference in phase for two impacts to recur", gt=0)
+    velocity_tolerance: float = Query(1e-3, title="Largest relative difference in velocity for two impacts to recur", gt=0)
+
+    def __call__(self, cancellation: Optional[CancellationToken] = None):
+        inputs = self.inputs(self.num_iterations, skip_impacts = self.skip_impacts)
+
+        return impact_recurrence(inputs, self.phase_tolerance, self.velocity_tolerance, cancellation)
+
+@app.get("/api/recurrence/data")
+async def read_recurrence_data(request: Request, data: RecurrenceQueryData=Depends()):
+    """ Recurrent pairs of impacts, with recurrence rate, determinism and laminarity """
+    result = await run_analysis(data, request)
+
+    return {
+        "size": result.size(),
+        "rows": result.rows().tolist(),
+        "columns": result.columns().tolist(),
+        "recurrence_rate": result.recurrence_rate(),
+        "determinism": result.determinism(),
+        "laminarity": result.laminarity(),
+        "longest_diagonal_line": result.longest_diagonal_line()}
+
+@app.get("/api/recurrence/image", summary="Recurrence Plot")
+async def read_recurrence_plot(request: Request, data: RecurrenceQueryData=Depends()):
+    """ Recurrence plot of a sequence of impacts """
+    result = await run_analysis(data, request)
+
+    return image_response(recurrence_plot(result))
+
+@dataclass
+class StaircaseQueryData(InitialImpactQueryData):
+    parameter: str = Query("frequency", title="Parameter to sweep (frequency, offset or r)", regex="^(frequency|offset|r)$")
+    min_value: float = Query(2.0, title="Lowest value of the swept parameter")
+    max_value: float = Query(5.0, title="Highest value of the swept parameter")
+    steps: int = Query(200, title="Number of values of the swept parameter", gt=0, le=2000)
+    transient: int = Query(1000, title="Number of impacts to discard at each parameter value", ge=0, le=100000)
+    recorded: int = Query(200, title="Number of impacts over which the rotation number is taken", gt=0, le=10000)
+    max_impacts: int = Query(20, title="Largest number of impacts in a locked cycle", gt=0, le=100)
+    tolerance: float = Query(1e-3, title="Largest difference in phase allowed for locking", gt=0)
+
+    def __call__(self, cancellation: Optional[CancellationToken] = None):
+        inputs = self.inputs(self.transient + self.recorded)
+
+        return devils_staircase(inputs, self.parameter, (self.min_value, self.max_value, self.steps), self.transient, self.recorded, self.max_impacts, self.tolerance, cancellation)
+
+@app.get("/api/staircase/data")
+async def read_staircase_data(request: Request, data: StaircaseQueryData=Depends()):
+    """ Rotation number (forcing periods per impact) and locking at each value of the swept parameter """
+    result = await run_analysis(data, request)
+
+    return {
+        "parameter": result.parameter(),
+        "values": result.values().tolist(),
+        # NaN is not valid JSON
+        "rotation_numbers": [None if math.isnan(x) else x for x in result.rotation_numbers().tolist()],
+        "lockings": result.lockings()}
+
+@app.get("/api/staircase/image", summary="Devil's Staircase")
+async def read_staircase_plot(request: Request, data: StaircaseQueryData=Depends()):
+    """ Rotation number against the swept parameter, showing where impacts lock to the forcing """
+    result = await run_analysis(data, request)
+
+    return image_response(staircase_plot(result))
+
+@dataclass
+class TransientQueryData(SystemQueryData):
+    min_phase: float = Query(0.0, title="Lowest initial phase")
+    max_phase: float = Query(1.0, title="Highest initial phase")
+    phase_steps: int = Query(50, title="Number of initial phases", gt=0, le=500)
+    min_velocity: float = Query(0.0, title="Lowest initial velocity", ge=0)
+    max_velocity: float = Query(2.0, title="Highest initial velocity", ge=0)
+    velocity_steps: int = Query(50, title="Number of initial velocities", gt=0, le=500)
+    num_iterations: int = Query(1000, title="Number of iterations of impact map from each initial impact", gt=0, le=100000)
+    phase_tolerance: float = Query(1e-3, title="Largest difference in phase for an impact to be on the final orbit", gt=0)
+    velocity_tolerance: float = Query(1e-3, title="Largest relative difference in velocity for an impact to be on the final orbit", gt=0)
+
+    def __call__(self, cancellation: Optional[CancellationToken] = None):
+        inputs = self.inputs(self.min_phase, self.min_velocity, self.num_iterations)
+
+        return transient_length_grid(inputs, (self.min_phase, self.max_phase, self.phase_steps), (self.min_velocity, self.max_velocity, self.velocity_steps), self.phase_tolerance, self.velocity_tolerance, cancellation)
+
+@app.get("/api/transient/data")
+async def read_transient_data(request: Request, data: TransientQueryData=Depends()):
+    """ Number of impacts taken to settle from each of a grid of initial impacts, with the attractor reached """
+    result = await run_analysis(data, request)
+
+    return {
+        "phases": result.phases().tolist(),
+        "velocities": result.velocities().tolist(),
+        "lengths": result.lengths(),
+        "cells": result.cells(),
+        "legend": result.legend()}
+
+@app.get("/api/transient/image", summary="Transient Lengths")
+async def read_transient_plot(request: Request, data: TransientQueryData=Depends()):
+    """ Heat map of the number of impacts taken to settle from each of a grid of initial impacts """
+    result = await run_analysis(data, request)
+
+    return image_response(transient_plot(result))
+
+@dataclass
+class BasinStabilityQueryData(SystemQueryData):
+    frequency: float = Query(3.0, title="Forcing frequency", gt=0)
+    offset: float = Query(0.11, title="Obstacle offset")
+    min_phase: float = Query(0.0, title="Lowest initial phase")
+    max_phase: float = Query(1.0, title="Highest initial phase")
+    min_velocity: float = Query(0.0, title="Lowest initial velocity", ge=0)
+    max_velocity: float = Query(2.0, title="Highest initial velocity", ge=0)
+    num_iterations: int = Query(1000, title="Number of iterations of impact map from each initial impact", gt=0, le=100000)
+    seed: int = Query(0, title="Seed for drawing initial impacts", ge=0)
+    precision: float = Query(0.01, title="Largest half-width of the 95% confidence interval of each fraction", ge=0)
+    batch_size: int = Query(100, title="Number of initial impacts drawn between checks of precision", gt=0, le=10000)
+    max_samples: int = Query(10000, title="Largest number of initial impacts to draw", gt=0, le=1000000)
+
+    def __call__(self, cancellation: Optional[CancellationToken] = None):
+        inputs = self.inputs(self.min_phase, self.min_velocity, self.num_iterations)
+
+        return basin_fractions(inputs, (self.min_velocity, self.max_velocity), (self.min_phase, self.max_phase), None, self.seed, self.precision, self.batch_size, self.max_samples, cancellation)
+
+@app.get("/api/basin-stability/data")
+async def read_basin_stability_data(request: Request, data: BasinStabilityQueryData=Depends()):
+    """ Fraction of random initial impacts ending on each attractor, with 95% confidence intervals """
+    result = await run_analysis(data, request)
+
+    return {
+        "labels": result.labels(),
+        "counts": result.counts(),
+        "fractions": result.fractions().tolist(),
+        "lower": result.lower().tolist(),
+        "upper": result.upper().tolist(),
+        "samples": result.samples(),
+        "converged": result.converged()}
+
+@app.get("/api/basin-stability/image", summary="Basin Stability")
+async def read_basin_stability_plot(request: Request, data: BasinStabilityQueryData=Depends()):
+    """ Bar chart of the fraction of random initial impacts ending on each attractor """
+    result = await run_analysis(data, request)
+
+    return image_response(basin_stability_plot(result))
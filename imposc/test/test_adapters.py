@@ -1,5 +1,7 @@
+import math
 import pytest
-from adapters import parameter_info, validate_iter_inputs, IterationInputs
+import numpy as np
+from adapters import parameter_info, validate_iter_inputs, iterate_impacts, iterate_impacts_batch, iterate_impacts_from_state, impact_pre_images, orbit_manifold, find_periodic_orbit, continue_periodic_orbit, locate_grazing, orbit_type_chart, sweep_with_hysteresis, iterate_impacts_with_schedule, plastic_circle_map, impact_density, attractor_dimension, impact_recurrence, impact_symbols, devils_staircase, arnold_tongues, transient_length_grid, basin_fractions, IterationInputs, BatchIterationInputs, StateIterationInputs, CancellationToken
 
 @pytest.mark.parametrize("inputs", [
     {
@@ -10,6 +12,17 @@ from adapters import parameter_info, validate_iter_inputs, IterationInputs
         'phi': 0.1,
         'v': 1.1,
         'num_iterations': 35},
+    {
+        'frequency': 1.2,
+        'offset': -0.7,
+        'r': 0.1,
+        'max_periods': 58,
+        'phi': 0.1,
+        'v': 1.1,
+        'num_iterations': 35,
+        'skip_impacts': 10,
+        'keep_every': 2,
+        'max_points': 5},
 ])
 def test_validate_iter_inputs(inputs):
     iteration_inputs = IterationInputs(**inputs)
@@ -36,7 +49,10 @@ def test_parameter_info_not_valid(input):
     {"Parameter":"v","Property":"Initial impact"},
     {"Parameter":"max_periods","Property":"Control parameters"},
     {"Parameter":"num_iterations","Property":"Control parameters"},
-    {"Parameter":"num_points","Property":"Control parameters"}]),
+    {"Parameter":"num_points","Property":"Control parameters"},
+    {"Parameter":"skip_impacts","Property":"Control parameters"},
+    {"Parameter":"keep_every","Property":"Control parameters"},
+    {"Parameter":"max_points","Property":"Control parameters"}]),
     ("garbage", None)
 ])
 def test_parameter_info_valid(category, expected):
@@ -52,4 +68,438 @@ def test_parameter_info_valid(category, expected):
             assert element in properties
 
         for element in properties:
-            assert element in expected
\ No newline at end of file
+            assert element in expected
+
+def test_iteration_outputs_as_arrays():
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=50)
+    result = iterate_impacts(inputs)
+
+    phases, velocities, times = result.phases(), result.velocities(), result.times()
+
+    assert len(phases) == len(velocities) == len(times) == len(result)
+
+    for i, impact in enumerate(result):
+        assert phases[i] == impact.phase()
+        assert velocities[i] == impact.velocity()
+
+    # Later calls borrow the same arrays rather than copying the trajectory again
+    assert result.phases() is phases
+    assert result.times() is times
+
+
+def test_iteration_with_invalid_parameters():
+    inputs = IterationInputs(frequency=-2.8, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=50)
+
+    with pytest.raises(ValueError):
+        iterate_impacts(inputs)
+
+def test_failing_progress_callback_leaves_token_alone():
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=1000)
+
+    def progress(iterations_done):
+        raise RuntimeError("stop")
+
+    token = CancellationToken()
+
+    with pytest.raises(RuntimeError):
+        iterate_impacts(inputs, progress, 10, token)
+
+    assert not token.is_cancelled()
+
+
+def test_iteration_at_resonance():
+    inputs = IterationInputs(frequency=1.0, offset=0.5, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=1000)
+
+    result = iterate_impacts(inputs)
+
+    assert math.isclose(result.velocities()[-1], math.pi / 1.8, rel_tol=1e-6)
+    assert result.symplectic_error() is None
+
+def test_symplectic_error_is_tracked_when_conservative():
+    inputs = IterationInputs(frequency=4.85, offset=-0.1, r=1.0, max_periods=100, phi=0.3, v=0.5, num_iterations=200)
+
+    result = iterate_impacts(inputs)
+
+    assert result.symplectic_error() < 1e-4
+
+def test_batch_iteration_matches_single_runs():
+    phi = np.array([0.0, 0.25, 0.5])
+    v = np.array([0.0, 0.5, 1.0])
+    batch = BatchIterationInputs(frequency=4.85, offset=-0.1, r=0.8, max_periods=100, phi=phi, v=v, num_iterations=200)
+
+    result = iterate_impacts_batch(batch, keep_trajectories=True)
+
+    assert len(result) == len(phi)
+    assert len(result.orbit_types()) == len(phi)
+
+    for i in range(len(phi)):
+        single = iterate_impacts(batch[i])
+        assert result.final_phases()[i] == single.phases()[-1]
+        assert len(result.trajectory(i)) == len(single)
+
+    assert batch.phi() is batch.phi()
+    assert np.array_equal(batch.v(), v)
+
+def test_batch_indices_out_of_range():
+    batch = BatchIterationInputs(frequency=4.85, offset=-0.1, r=0.8, max_periods=100, phi=np.array([0.0, 0.25]), v=np.array([0.0, 0.5]), num_iterations=10)
+
+    assert batch[-1].phi() == 0.25
+
+    with pytest.raises(IndexError):
+        batch[2]
+
+    with pytest.raises(IndexError):
+        batch[-3]
+
+    empty = BatchIterationInputs(frequency=4.85, offset=-0.1, r=0.8, max_periods=100, phi=np.array([]), v=np.array([]), num_iterations=10)
+
+    with pytest.raises(IndexError):
+        empty[-1]
+
+
+@pytest.mark.parametrize(('offset', 'outcome'), [(0.05, "impacting"), (0.1, "non-impacting")])
+def test_iterate_from_rest(offset, outcome):
+    inputs = StateIterationInputs(frequency=4.85, offset=offset, r=0.8, max_periods=100, t=0.0, x=0.0, v=0.0, num_iterations=10)
+
+    result = iterate_impacts_from_state(inputs)
+
+    assert result.outcome() == outcome
+
+    if outcome == "impacting":
+        assert len(result.impacts()) == 11
+        assert result.amplitude() is None
+    else:
+        assert result.impacts() is None
+
+def test_iterate_from_beyond_obstacle():
+    inputs = StateIterationInputs(frequency=4.85, offset=0.1, r=0.8, max_periods=100, t=0.0, x=0.2, v=0.0, num_iterations=10)
+
+    with pytest.raises(ValueError, match="beyond the obstacle"):
+        iterate_impacts_from_state(inputs)
+        assert result.amplitude() < offset
+
+def test_pre_images_map_to_impact():
+    inputs = IterationInputs(frequency=2.8, offset=0.1, r=0.8, max_periods=100, phi=0.3, v=0.5, num_iterations=1)
+
+    image = iterate_impacts(inputs)[1]
+
+    pre_image_inputs = IterationInputs(frequency=2.8, offset=0.1, r=0.8, max_periods=100, phi=image.phase(), v=image.velocity(), num_iterations=1)
+
+    results = impact_pre_images(pre_image_inputs)
+
+    assert any(result.kind() == "impact" and result.phase() == pytest.approx(0.3, abs=1e-3) for result in results)
+
+def test_manifold_of_saddle():
+    # Near the (1, 1) orbit, which is a saddle for these parameters
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.48694, v=0.33621, num_iterations=1)
+
+    result = orbit_manifold(inputs, 1, max_arc_length=0.5)
+
+    assert result.eigenvalue() < -1.0
+    assert len(result.phases()) == len(result.velocities())
+    assert len(result.phases()) > 10
+
+def test_stable_orbit_has_no_manifold():
+    inputs = IterationInputs(frequency=2.0, offset=0.0, r=0.8, max_periods=100, phi=0.25, v=6.6667, num_iterations=1)
+
+    with pytest.raises(ValueError):
+        orbit_manifold(inputs, 1)
+
+def test_find_unstable_orbit_from_trajectory():
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=2000)
+
+    orbit = find_periodic_orbit(inputs, 1, 1, from_trajectory=True)
+
+    assert not orbit.stable()
+    assert len(orbit.phases()) == 1
+    assert orbit.residual() < 1e-10
+
+def test_continue_orbit_to_grazing():
+    inputs = IterationInputs(frequency=2.6, offset=0.0, r=0.8, max_periods=100, phi=0.4821, v=0.5054, num_iterations=1)
+
+    branch = continue_periodic_orbit(inputs, 1, 1, "offset", 1.0)
+
+    assert branch.parameter() == "offset"
+    assert branch.termination() == "grazing"
+    assert len(branch.parameters()) == len(branch.phases()) == len(branch.stable())
+    assert branch.events()[-1][0] == "grazing"
+
+def test_continue_orbit_rejects_unknown_parameter():
+    inputs = IterationInputs(frequency=2.6, offset=0.0, r=0.8, max_periods=100, phi=0.4821, v=0.5054, num_iterations=1)
+
+    with pytest.raises(ValueError):
+        continue_periodic_orbit(inputs, 1, 1, "phi", 1.0)
+
+def test_grazing_with_zero_velocity_impact():
+    inputs = IterationInputs(frequency=2.6, offset=0.0, r=0.8, max_periods=100, phi=0.4821, v=0.5054, num_iterations=1)
+
+    result = locate_grazing(inputs, 1, 1, "offset", 1.0)
+
+    assert result.kind() == "impact velocity"
+    assert 0.17 < result.parameter() < 0.18
+
+    normal_form = result.normal_form()
+
+    assert normal_form.scenario() == "unclassified"
+    assert normal_form.penetrates_for_increasing_parameter() is False
+
+def test_grazing_normal_form():
+    inputs = IterationInputs(frequency=4.85, offset=-0.12, r=0.8, max_periods=100, phi=0.0219, v=0.295, num_iterations=1)
+
+    result = locate_grazing(inputs, 1, 2, "offset", 0.0)
+
+    assert result.kind() == "clearance"
+
+    normal_form = result.normal_form()
+
+    assert normal_form.scenario() == "unstable orbit"
+    assert len(normal_form.smooth()) == 2
+
+def test_orbit_type_chart():
+    inputs = IterationInputs(frequency=2.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=2000)
+
+    chart = orbit_type_chart(inputs, "offset", (2.0, 4.85, 2), (-0.1, 0.0, 2))
+
+    assert chart.parameter() == "offset"
+    assert len(chart.frequencies()) == 2
+    assert len(chart.cells()) == len(chart.values()) == 2
+    assert chart.legend()[chart.cells()[1][0]] == "(1, 1)"
+    assert chart.legend()[chart.cells()[0][1]] == "(1, 2)"
+
+def test_orbit_type_chart_rejects_frequency():
+    inputs = IterationInputs(frequency=2.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=100)
+
+    with pytest.raises(ValueError):
+        orbit_type_chart(inputs, "frequency", (2.0, 3.0, 2), (2.0, 3.0, 2))
+
+def test_sweep_with_hysteresis():
+    inputs = IterationInputs(frequency=3.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=1)
+
+    result = sweep_with_hysteresis(inputs, "offset", (0.08, 0.14, 10), 1000, 40)
+
+    assert result.parameter() == "offset"
+    assert result.up().direction() == "up"
+    assert result.down().direction() == "down"
+    assert len(result.up().parameters()) == len(result.up().phases()) == len(result.up().velocities())
+    assert [value for value, _ in result.up().orbit_types()] == [value for value, _ in reversed(result.down().orbit_types())]
+    assert any(up == "(3, 4)" and down == "(2, 4)" for _, up, down in result.coexisting())
+
+def test_sweep_with_nothing_recorded():
+    inputs = IterationInputs(frequency=3.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=1)
+
+    with pytest.raises(ValueError, match="At least one impact"):
+        sweep_with_hysteresis(inputs, "offset", (0.08, 0.14, 10), 1000, 0)
+
+def test_iterate_impacts_with_schedule():
+    inputs = IterationInputs(frequency=2.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=400)
+
+    result = iterate_impacts_with_schedule(inputs, "frequency", [(0.0, 2.0), (500.0, 3.0)])
+
+    assert result.parameter() == "frequency"
+    assert result.termination() == "completed"
+    assert len(result.times()) == len(result.phases()) == len(result.velocities()) == len(result.values())
+    assert result.values()[0] == 2.0
+    assert result.values()[-1] == 3.0
+
+def test_iterate_impacts_with_empty_schedule():
+    inputs = IterationInputs(frequency=2.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=10)
+
+    with pytest.raises(ValueError):
+        iterate_impacts_with_schedule(inputs, "frequency", [])
+
+def test_plastic_circle_map():
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.0, max_periods=100, phi=0.3, v=0.0, num_iterations=50)
+
+    result = plastic_circle_map(inputs, 100)
+
+    assert len(result.phases()) == len(result.images()) == 100
+    assert len(result.orbit()) == 51
+    assert np.array_equal(result.orbit()[1:], iterate_impacts(inputs).phases()[1:])
+
+def test_circle_map_needs_plastic_impacts():
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.3, v=0.0, num_iterations=50)
+
+    with pytest.raises(ValueError):
+        plastic_circle_map(inputs)
+
+def test_impact_density():
+    inputs = IterationInputs(frequency=4.85, offset=-0.1, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=2000, skip_impacts=100)
+
+    result = impact_density(inputs, 50, 40)
+
+    density = np.array(result.density())
+    cell_areas = np.outer(np.diff(result.velocity_edges()), np.diff(result.phase_edges()))
+
+    assert density.shape == (40, 50)
+    assert result.total() >= 1900
+    assert math.isclose(np.sum(density * cell_areas), 1.0, rel_tol=1e-9)
+
+@pytest.mark.parametrize('method', ["correlation", "box-counting"])
+def test_attractor_dimension(method):
+    # A chaotic attractor
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=3000, skip_impacts=1000)
+
+    result = attractor_dimension(inputs, method, 12)
+
+    first, last = result.scaling_region()
+
+    assert result.method() == method
+    assert 1.0 < result.dimension() < 2.0
+    assert result.error() >= 0.0
+    assert 0 <= first < last < len(result.log_scales())
+    assert len(result.log_scales()) == len(result.log_measures())
+
+def test_attractor_dimension_unknown_method():
+    inputs = IterationInputs(frequency=4.85, offset=-0.1, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=100)
+
+    with pytest.raises(ValueError):
+        attractor_dimension(inputs, "information")
+
+def test_attractor_dimension_too_many_boxes():
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=3000, skip_impacts=1000)
+
+    # Scales beyond the resolution of the phase are ignored
+    finest = attractor_dimension(inputs, "box-counting", 64)
+
+    assert np.array_equal(finest.log_scales(), attractor_dimension(inputs, "box-counting", 52).log_scales())
+
+def test_impact_recurrence():
+    # A (2, 4) orbit, on which every other impact recurs
+    inputs = IterationInputs(frequency=3.0, offset=0.11, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=1500, skip_impacts=1000)
+
+    result = impact_recurrence(inputs, 1e-2, 1e-2)
+
+    assert result.size() == 501
+    assert len(result.rows()) == len(result.columns())
+    assert np.all(result.rows() < result.columns())
+    assert np.all((result.columns() - result.rows()) % 2 == 0)
+    assert result.determinism() > 0.99
+    assert result.laminarity() == 0.0
+
+def test_impact_symbols_of_periodic_orbit():
+    # A (1, 2) orbit, with two forcing periods between impacts
+    inputs = IterationInputs(frequency=3.5, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=2000, skip_impacts=1000)
+
+    result = impact_symbols(inputs, 4)
+
+    assert np.all(result.symbols() == 2)
+    assert result.words() == [([2, 2, 2, 2], len(result.symbols()) - 3)]
+    assert result.word_counts() == [1, 1, 1, 1]
+    assert result.topological_entropy() == 0.0
+
+def test_impact_symbols_of_chaotic_orbit():
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=3000, skip_impacts=1000)
+
+    result = impact_symbols(inputs, 6, [0.0, 0.5])
+
+    assert set(result.symbols()) == {0, 1}
+    assert len(result.block_entropies()) == 6
+    assert result.topological_entropy() > 0.1
+
+def test_impact_symbols_are_between_consecutive_impacts():
+    # Keeping every other impact of the (1, 2) orbit would otherwise give four periods between impacts
+    inputs = IterationInputs(frequency=3.5, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=2000, skip_impacts=1000, keep_every=2)
+
+    result = impact_symbols(inputs, 4)
+
+    assert np.all(result.symbols() == 2)
+
+    consecutive = IterationInputs(frequency=3.5, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=2000, skip_impacts=1000)
+
+    assert len(result.symbols()) == len(impact_symbols(consecutive, 4).symbols())
+
+def test_impact_symbols_bad_partition():
+    inputs = IterationInputs(frequency=2.8, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=100)
+
+    with pytest.raises(ValueError):
+        impact_symbols(inputs, 2, [0.5])
+
+def test_devils_staircase():
+    inputs = IterationInputs(frequency=2.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=1)
+
+    result = devils_staircase(inputs, "frequency", (2.0, 3.5, 2), 1000, 200)
+
+    assert result.parameter() == "frequency"
+    assert list(result.values()) == [2.0, 3.5]
+    assert np.allclose(result.rotation_numbers(), [1.0, 2.0])
+    assert result.lockings() == [(1, 1), (2, 1)]
+
+def test_arnold_tongues():
+    # A (2, 4) orbit has the same rotation number as a (1, 2) orbit but locks differently
+    inputs = IterationInputs(frequency=3.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=1)
+
+    result = arnold_tongues(inputs, "offset", (3.0, 3.5, 2), (0.0, 0.11, 2), 1000, 200)
+
+    assert len(result.rotation_numbers()) == len(result.values()) == 2
+    assert result.lockings()[0][1] == (2, 1)
+    assert result.lockings()[1][0] == (4, 2)
+
+def test_arnold_tongues_rejects_frequency():
+    inputs = IterationInputs(frequency=2.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=1)
+
+    with pytest.raises(ValueError):
+        arnold_tongues(inputs, "frequency", (2.0, 3.0, 2), (2.0, 3.0, 2))
+
+def test_transient_length_grid():
+    inputs = IterationInputs(frequency=2.0, offset=0.0, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=2000)
+
+    result = transient_length_grid(inputs, (0.0, 0.5, 2), (0.0, 2.0, 3))
+
+    assert len(result.phases()) == 2
+    assert len(result.velocities()) == 3
+    assert len(result.lengths()) == len(result.cells()) == 3
+    assert all(len(row) == 2 for row in result.lengths())
+
+    for lengths, cells in zip(result.lengths(), result.cells()):
+        for length, cell in zip(lengths, cells):
+            if result.legend()[cell] == "(1, 1)":
+                assert 0 <= length < 2000
+
+def test_cancelled_analyses():
+    inputs = IterationInputs(frequency=3.0, offset=0.11, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=2000, skip_impacts=1000)
+
+    token = CancellationToken()
+    token.cancel()
+
+    assert transient_length_grid(inputs, (0.0, 0.5, 2), (0.0, 2.0, 3), cancellation=token) is None
+    assert orbit_type_chart(inputs, "offset", (2.0, 4.85, 2), (-0.1, 0.0, 2), cancellation=token) is None
+    assert sweep_with_hysteresis(inputs, "offset", (0.08, 0.14, 3), 100, 10, cancellation=token) is None
+    assert devils_staircase(inputs, "frequency", (2.0, 3.5, 2), cancellation=token) is None
+    assert arnold_tongues(inputs, "r", (2.0, 3.5, 2), (0.8, 1.0, 2), cancellation=token) is None
+    assert basin_fractions(inputs, (0.0, 2.0), phase_range=(0.0, 1.0), cancellation=token).samples() == 0
+    assert impact_density(inputs, cancellation=token).total() == 0
+    assert impact_recurrence(inputs, cancellation=token) is None
+
+def test_basin_fractions_on_impact_surface():
+    # (3, 4) and (2, 4) orbits coexist
+    inputs = IterationInputs(frequency=3.0, offset=0.11, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=500)
+
+    result = basin_fractions(inputs, (0.0, 2.0), phase_range=(0.0, 1.0), seed=1, precision=0.1, batch_size=50)
+
+    assert result.converged()
+    assert set(result.labels()) == {"(3, 4)", "(2, 4)"}
+    assert sum(result.counts()) == result.samples()
+    assert math.isclose(np.sum(result.fractions()), 1.0)
+    assert np.all(result.lower() <= result.fractions())
+    assert np.all(result.fractions() <= result.upper())
+
+    repeat = basin_fractions(inputs, (0.0, 2.0), phase_range=(0.0, 1.0), seed=1, precision=0.1, batch_size=50)
+
+    assert repeat.counts() == result.counts()
+
+def test_basin_fractions_from_states_of_motion():
+    inputs = IterationInputs(frequency=3.0, offset=0.11, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=500)
+
+    result = basin_fractions(inputs, (-0.5, 0.5), displacement_range=(-0.5, 0.0), precision=0.0, max_samples=40)
+
+    assert not result.converged()
+    assert result.samples() == 40
+
+def test_basin_fractions_needs_one_region():
+    inputs = IterationInputs(frequency=3.0, offset=0.11, r=0.8, max_periods=100, phi=0.0, v=0.0, num_iterations=500)
+
+    with pytest.raises(ValueError):
+        basin_fractions(inputs, (0.0, 1.0))
+
+    with pytest.raises(ValueError):
+        basin_fractions(inputs, (0.0, 1.0), phase_range=(0.0, 1.0), displacement_range=(-0.5, 0.0))
@@ -0,0 +1,20 @@
+import json
+from cli import main
+
+def test_orbit_chart(capsys):
+    status = main(["orbit-chart", "--min-frequency", "2.0", "--max-frequency", "4.85", "--frequency-steps", "2", "--min-value", "-0.1", "--max-value", "0.0", "--value-steps", "2", "--num-iterations", "2000"])
+
+    assert status == 0
+
+    chart = json.loads(capsys.readouterr().out)
+
+    assert chart["parameter"] == "offset"
+    assert len(chart["frequencies"]) == len(chart["values"]) == 2
+    assert chart["legend"][chart["cells"][1][0]] == "(1, 1)"
+    assert chart["legend"][chart["cells"][0][1]] == "(1, 2)"
+
+def test_orbit_chart_with_invalid_parameters(capsys):
+    status = main(["orbit-chart", "--min-frequency", "0.0", "--frequency-steps", "2", "--value-steps", "2"])
+
+    assert status == 1
+    assert capsys.readouterr().err.startswith("orbit-chart: ")
@@ -29,7 +29,10 @@ def test_read_main():
     {"Parameter":"v","Property":"Initial impact"},
     {"Parameter":"max_periods","Property":"Control parameters"},
     {"Parameter":"num_iterations","Property":"Control parameters"},
-    {"Parameter":"num_points","Property":"Control parameters"}]}),
+    {"Parameter":"num_points","Property":"Control parameters"},
+    {"Parameter":"skip_impacts","Property":"Control parameters"},
+    {"Parameter":"keep_every","Property":"Control parameters"},
+    {"Parameter":"max_points","Property":"Control parameters"}]}),
 ])
 def test_read_parameter_info(category, status, response_json):
 
@@ -66,4 +69,90 @@ def test_get_impact_iteration():
     json, actual_status = post_response_for_test(f"/api/iteration/data", input_json)
     assert actual_status == 200, f"{json}"
     assert json
-    # assert len(json) == 2
\ No newline at end of file
+    # assert len(json) == 2
+def test_get_orbit_chart():
+    json, actual_status = get_response_for_test("/api/orbit-chart/data?parameter=offset&min_frequency=2.0&max_frequency=4.85&frequency_steps=2&min_value=-0.1&max_value=0.0&value_steps=2&num_iterations=2000")
+
+    assert actual_status == 200, f"{json}"
+    assert json["parameter"] == "offset"
+    assert len(json["cells"]) == 2
+    assert json["legend"][json["cells"][1][0]] == "(1, 1)"
+
+def test_get_orbit_chart_bad_parameter():
+    _, actual_status = get_response_for_test("/api/orbit-chart/data?parameter=frequency")
+
+    assert actual_status == 422
+
+def test_get_orbit_chart_too_large():
+    _, actual_status = get_response_for_test("/api/orbit-chart/data?frequency_steps=1000")
+
+    assert actual_status == 422
+
+def test_get_hysteresis():
+    json, actual_status = get_response_for_test("/api/hysteresis/data?parameter=offset&min_value=0.08&max_value=0.14&steps=10&frequency=3.0&recorded=40")
+
+    assert actual_status == 200, f"{json}"
+    assert json["up"]["direction"] == "up"
+    assert len(json["down"]["orbit_types"]) == 10
+    assert json["coexisting"]
+
+def test_get_hysteresis_too_long():
+    _, actual_status = get_response_for_test("/api/hysteresis/data?transient=1000000")
+
+    assert actual_status == 422
+
+def test_get_ramp():
+    json, actual_status = get_response_for_test("/api/ramp/data?parameter=frequency&start_value=2.0&end_value=3.0&duration=500&num_iterations=400")
+
+    assert actual_status == 200, f"{json}"
+    assert json["termination"] == "completed"
+    assert json["values"][0] == 2.0
+    assert len(json["times"]) == len(json["values"])
+
+def test_get_density():
+    json, actual_status = get_response_for_test("/api/density/data?frequency=4.85&offset=-0.1&num_iterations=2000&skip_impacts=100&phase_bins=20&velocity_bins=10")
+
+    assert actual_status == 200, f"{json}"
+    assert len(json["density"]) == 10
+    assert len(json["density"][0]) == 20
+    assert len(json["phase_edges"]) == 21
+
+def test_get_recurrence():
+    json, actual_status = get_response_for_test("/api/recurrence/data?frequency=3.0&offset=0.11&num_iterations=1500&skip_impacts=1000&phase_tolerance=0.01&velocity_tolerance=0.01")
+
+    assert actual_status == 200, f"{json}"
+    assert json["size"] == 501
+    assert len(json["rows"]) == len(json["columns"])
+    assert json["determinism"] > 0.99
+
+def test_get_recurrence_too_long():
+    _, actual_status = get_response_for_test("/api/recurrence/data?num_iterations=5000")
+
+    assert actual_status == 422
+
+def test_get_staircase():
+    json, actual_status = get_response_for_test("/api/staircase/data?min_value=2.0&max_value=3.5&steps=2&recorded=100")
+
+    assert actual_status == 200, f"{json}"
+    assert json["values"] == [2.0, 3.5]
+    assert json["lockings"] == [[1, 1], [2, 1]]
+
+def test_get_staircase_too_long():
+    _, actual_status = get_response_for_test("/api/staircase/data?steps=100000")
+
+    assert actual_status == 422
+
+def test_get_transient():
+    json, actual_status = get_response_for_test("/api/transient/data?frequency=2.0&phase_steps=2&velocity_steps=3&num_iterations=2000")
+
+    assert actual_status == 200, f"{json}"
+    assert len(json["lengths"]) == len(json["cells"]) == 3
+    assert len(json["lengths"][0]) == 2
+
+def test_get_basin_stability():
+    json, actual_status = get_response_for_test("/api/basin-stability/data?num_iterations=500&seed=1&precision=0.1&batch_size=50")
+
+    assert actual_status == 200, f"{json}"
+    assert json["converged"]
+    assert sum(json["counts"]) == json["samples"]
+    assert len(json["labels"]) == len(json["fractions"]) == len(json["lower"]) == len(json["upper"])
@@ -0,0 +1,280 @@
+//
+// Monte Carlo estimates of basin stability, i.e. the fraction of initial conditions which end on each attractor
+//
+use super::classification::OrbitClassifier as OrbitClassifier;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::impact_map::StateIterationResult as StateIterationResult;
+use super::model_types::Distance as Distance;
+use super::model_types::Phase as Phase;
+use super::model_types::Velocity as Velocity;
+use super::motion::StateOfMotion as StateOfMotion;
+use super::orbit_chart::ChartLabel as ChartLabel;
+use super::parameters::Parameters as Parameters;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The region from which initial conditions are drawn, uniformly
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SamplingRegion {
+    /// Impacts with phase and velocity in the given ranges
+    ImpactSurface {phases: (Phase, Phase), velocities: (Velocity, Velocity)},
+
+    /// States of motion with displacement and velocity in the given ranges, at a time anywhere in the first
+    /// forcing cycle. States beyond the obstacle are labelled invalid.
+    StatesOfMotion {displacements: (Distance, Distance), velocities: (Velocity, Velocity)}
+}
+
+impl SamplingRegion {
+    /// Impact velocities cannot be negative
+    pub fn impact_surface(phases: (Phase, Phase), velocities: (Velocity, Velocity)) -> Option<SamplingRegion> {
+        if phases.0 > phases.1 || velocities.0 > velocities.1 || velocities.0 < 0.0 {
+            return None;
+        }
+
+        Some(SamplingRegion::ImpactSurface{phases, velocities})
+    }
+
+    pub fn states_of_motion(displacements: (Distance, Distance), velocities: (Velocity, Velocity)) -> Option<SamplingRegion> {
+        if displacements.0 > displacements.1 || velocities.0 > velocities.1 {
+            return None;
+        }
+
+        Some(SamplingRegion::StatesOfMotion{displacements, velocities})
+    }
+}
+
+// SplitMix64, evaluated at a position in the stream rather than stepped, so that each sample gets the same numbers
+// whichever thread draws it
+fn uniform(seed: u64, position: u64) -> f64 {
+    let mut z = seed.wrapping_add(position.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    // The top 53 bits, as a fraction in [0, 1)
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn within(range: (f64, f64), fraction: f64) -> f64 {
+    range.0 + (range.1 - range.0) * fraction
+}
+
+/// The share of samples which end on one attractor, with a Wilson score confidence interval
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct BasinFraction {
+    pub label: ChartLabel,
+    pub count: u64,
+    pub fraction: f64,
+    pub lower: f64,
+    pub upper: f64
+}
+
+impl BasinFraction {
+    fn new(label: ChartLabel, count: u64, samples: u64) -> BasinFraction {
+        let n = samples as f64;
+        let z = BasinStability::CONFIDENCE_Z;
+
+        let fraction = count as f64 / n;
+
+        let denominator = 1.0 + z * z / n;
+        let centre = (fraction + z * z / (2.0 * n)) / denominator;
+        let half_width = z * (fraction * (1.0 - fraction) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+
+        BasinFraction{label, count, fraction, lower: (centre - half_width).max(0.0), upper: (centre + half_width).min(1.0)}
+    }
+
+    pub fn half_width(&self) -> f64 {
+        0.5 * (self.upper - self.lower)
+    }
+}
+
+/// Basin fractions of every attractor found, in the order in which they were first reached
+#[derive(Debug, Clone)]
+pub struct BasinStabilityEstimate {
+    fractions: Vec<BasinFraction>,
+    samples: u64,
+    converged: bool
+}
+
+impl BasinStabilityEstimate {
+    pub fn fractions(&self) -> &[BasinFraction] {
+        &self.fractions
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Whether every confidence interval was within the target precision before the sample limit was reached
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+
+    #[cfg(test)]
+    pub fn fraction(&self, label: ChartLabel) -> Option<BasinFraction> {
+        self.fractions.iter().find(|fraction| fraction.label == label).copied()
+    }
+}
+
+/// Estimates basin stability by iterating the map from random initial conditions, drawn from a seeded stream so
+/// that an estimate can be repeated exactly. Attractors are told apart by their orbit type, as in an `OrbitChart`,
+/// so coexisting attractors of the same type are counted together.
+pub struct BasinStability {
+    mapper: ImpactMap,
+    classifier: OrbitClassifier,
+    region: SamplingRegion,
+    num_iterations: u32,
+    seed: u64
+}
+
+impl BasinStability {
+    // Confidence intervals are at 95%
+    const CONFIDENCE_Z: f64 = 1.959_963_984_540_054;
+
+    pub fn new(parameters: Parameters, region: SamplingRegion, num_iterations: u32, seed: u64) -> BasinStability {
+        BasinStability{mapper: ImpactMap::new(parameters), classifier: OrbitClassifier::default(parameters), region, num_iterations, seed}
+    }
+
+    /// Draws samples in parallel batches of `batch_size` until the confidence interval of every basin fraction is
+    /// no wider than `precision` either side, or `max_samples` have been drawn. The result only depends on the seed,
+    /// not on how the batches are shared between threads. Setting `cancellation` stops the estimate after the
+    /// current batch, as if the sample limit had been reached.
+    pub fn estimate(&self, precision: f64, batch_size: u64, max_samples: u64, cancellation: &AtomicBool) -> BasinStabilityEstimate {
+        let batch_size = std::cmp::max(1, batch_size);
+
+        let mut counts: Vec<(ChartLabel, u64)> = vec![];
+
+        let mut samples = 0;
+
+        while samples < max_samples && !cancellation.load(Ordering::Relaxed) {
+            let batch_end = std::cmp::min(samples + batch_size, max_samples);
+
+            let labels: Vec<ChartLabel> = (samples..batch_end).into_par_iter().map(|index| self.sample(index)).collect();
+
+            for label in labels {
+                match counts.iter_mut().find(|(known, _)| *known == label) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((label, 1))
+                }
+            }
+
+            samples = batch_end;
+
+            if BasinStability::fractions(&counts, samples).iter().all(|fraction| fraction.half_width() <= precision) {
+                return BasinStabilityEstimate{fractions: BasinStability::fractions(&counts, samples), samples, converged: true};
+            }
+        }
+
+        BasinStabilityEstimate{fractions: BasinStability::fractions(&counts, samples), samples, converged: false}
+    }
+
+    /// Where the trajectory from the `index`-th initial condition of the stream ends up
+    pub fn sample(&self, index: u64) -> ChartLabel {
+        // Three numbers are drawn for every sample, whether or not all are needed
+        let draw = |k: u64| uniform(self.seed, 3 * index + k);
+
+        match self.region {
+            SamplingRegion::ImpactSurface{phases, velocities} => {
+                let result = self.mapper.iterate_from_point(within(phases, draw(0)), within(velocities, draw(1)), self.num_iterations);
+
+                ChartLabel::from(self.classifier.classify(&result))
+            },
+            SamplingRegion::StatesOfMotion{displacements, velocities} => {
+                let time = self.mapper.converter().time_into_cycle(draw(2));
+
+                let state = StateOfMotion::new(time, within(displacements, draw(0)), within(velocities, draw(1)));
+
+                match self.mapper.iterate_from_state(state, self.num_iterations) {
+                    Ok(StateIterationResult::Impacting(result)) => ChartLabel::from(self.classifier.classify(&result)),
+                    Ok(StateIterationResult::NonImpacting{..}) => ChartLabel::NonImpacting,
+                    Ok(StateIterationResult::NoImpact) => ChartLabel::LongExcursions,
+                    Err(_) => ChartLabel::Invalid
+                }
+            }
+        }
+    }
+
+    fn fractions(counts: &[(ChartLabel, u64)], samples: u64) -> Vec<BasinFraction> {
+        counts.iter().map(|&(label, count)| BasinFraction::new(label, count, samples)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coexisting(region: SamplingRegion, seed: u64) -> BasinStability {
+        // Both (3, 4) and (2, 4) orbits are stable here
+        BasinStability::new(Parameters::new(3.0, 0.11, 0.8, 100).unwrap(), region, 500, seed)
+    }
+
+    #[test]
+    fn test_uniform_stream() {
+        let numbers: Vec<f64> = (0..1000).map(|position| uniform(7, position)).collect();
+
+        assert!(numbers.iter().all(|&x| (0.0..1.0).contains(&x)));
+        assert!((numbers.iter().sum::<f64>() / 1000.0 - 0.5).abs() < 0.05);
+        assert_eq!(uniform(7, 3), numbers[3]);
+        assert_ne!(uniform(8, 3), numbers[3]);
+    }
+
+    #[test]
+    fn test_wilson_interval() {
+        let fraction = BasinFraction::new(ChartLabel::Chaotic, 50, 100);
+
+        assert_eq!(fraction.fraction, 0.5);
+        assert!((fraction.half_width() - 0.0962).abs() < 1e-3);
+
+        // The interval does not collapse when every sample ends on the same attractor
+        let all = BasinFraction::new(ChartLabel::Chaotic, 100, 100);
+
+        assert_eq!(all.upper, 1.0);
+        assert!(all.lower < 1.0);
+    }
+
+    #[test]
+    fn test_estimate_is_repeatable_and_stops_early() {
+        let region = SamplingRegion::impact_surface((0.0, 1.0), (0.0, 2.0)).unwrap();
+
+        let running = AtomicBool::new(false);
+
+        let estimate = coexisting(region, 1).estimate(0.1, 50, 1000, &running);
+
+        assert!(estimate.converged());
+        assert!(estimate.fraction(ChartLabel::Periodic{impacts: 3, periods: 4}).is_some());
+        assert!(estimate.fraction(ChartLabel::Periodic{impacts: 2, periods: 4}).is_some());
+        assert!(estimate.samples() < 1000);
+        assert_eq!(estimate.samples() % 50, 0);
+        assert_eq!(estimate.fractions().iter().map(|fraction| fraction.count).sum::<u64>(), estimate.samples());
+
+        for fraction in estimate.fractions() {
+            assert!(fraction.lower <= fraction.fraction && fraction.fraction <= fraction.upper);
+            assert!(fraction.half_width() <= 0.1);
+        }
+
+        assert_eq!(coexisting(region, 1).estimate(0.1, 50, 1000, &running).fractions(), estimate.fractions());
+
+        let capped = coexisting(region, 1).estimate(0.0, 50, 120, &running);
+
+        assert!(!capped.converged());
+        assert_eq!(capped.samples(), 120);
+
+        let cancelled = coexisting(region, 1).estimate(0.1, 50, 1000, &AtomicBool::new(true));
+
+        assert!(!cancelled.converged());
+        assert_eq!(cancelled.samples(), 0);
+    }
+
+    #[test]
+    fn test_states_of_motion() {
+        assert!(SamplingRegion::impact_surface((0.0, 1.0), (-1.0, 1.0)).is_none());
+
+        let region = SamplingRegion::states_of_motion((-0.5, 0.0), (-0.5, 0.5)).unwrap();
+
+        let estimate = coexisting(region, 2).estimate(0.2, 20, 200, &AtomicBool::new(false));
+
+        assert_eq!(estimate.fractions().iter().map(|fraction| fraction.count).sum::<u64>(), estimate.samples());
+        assert!(estimate.fraction(ChartLabel::Invalid).is_none());
+    }
+}
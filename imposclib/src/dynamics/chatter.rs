@@ -21,6 +21,14 @@ impl ChatterResult {
     }
 }
 
+/// Running count of low-velocity impacts along a single trajectory, which the `ChatterChecker` uses to decide
+/// when to extrapolate to an accumulation point. It is kept separate from the checker so that the checker itself
+/// holds no per-trajectory state.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ChatterCount {
+	impact_count: u32
+}
+
 pub struct ChatterChecker {
 	
 	// Detects and numerically approximates 'Chatter', which is when an infinite sequence of impact.Impacts accumulates 
@@ -32,44 +40,45 @@ pub struct ChatterChecker {
 		count_threshold: u32,
 		sticking: Sticking,
         parameters: Parameters,
-		can_chatter: bool,
-		impact_count: u32
+		can_chatter: bool
 }
 
 impl ChatterChecker {
     pub fn new(parameters: Parameters, velocity_threshold: Velocity, count_threshold: u32) -> ChatterChecker {
 
-        let can_chatter = parameters.coefficient_of_restitution() < 1.0 && parameters.coefficient_of_restitution() >=0.0;
+        // With r = 0 every impact brings the mass to rest at once, so there is no sequence of impacts to accumulate
+        let can_chatter = parameters.coefficient_of_restitution() < 1.0 && parameters.coefficient_of_restitution() > 0.0;
 
         ChatterChecker {
                 velocity_threshold: velocity_threshold,
                 count_threshold: count_threshold,
-                impact_count: 0,
                 can_chatter: can_chatter,
                 sticking: Sticking::new(parameters),
                 parameters: parameters
             }
     }
 
+    // Measured from the start of the forcing period in which `impact` occurs
     fn accumulation_time(&self, impact: Impact) -> Time {
+        let time = impact.time_in_period();
 
         if self.can_chatter { 
-            return impact.time() - 2.0*impact.velocity() / (1.0-self.parameters.coefficient_of_restitution()) /
-                ((impact.time() * self.parameters.forcing_frequency()).cos() - self.parameters.obstacle_offset());
+            return time - 2.0*impact.velocity() / (1.0-self.parameters.coefficient_of_restitution()) /
+                ((time * self.parameters.forcing_frequency()).cos() - self.parameters.obstacle_offset());
         }
 
-        impact.time()
+        time
     }
 
-    pub fn check(&mut self, impact: Impact) -> ChatterResult {
+    pub fn check(&self, count: &mut ChatterCount, impact: Impact) -> ChatterResult {
         if self.can_chatter && impact.velocity() < self.velocity_threshold {
-            self.impact_count += 1;
-            if self.impact_count > self.count_threshold {
-                self.impact_count = 0;
+            count.impact_count += 1;
+            if count.impact_count > self.count_threshold {
+                count.impact_count = 0;
                 let new_time = self.accumulation_time(impact);
 
                 if self.sticking.time_sticks(new_time) {
-                    return ChatterResult{is_chatter: true, accumulation_impact: self.sticking.generate(new_time)};
+                    return ChatterResult{is_chatter: true, accumulation_impact: self.sticking.generate(impact.periods(), new_time)};
                 }
             }
         }
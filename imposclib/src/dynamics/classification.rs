@@ -0,0 +1,148 @@
+//
+// Classification of the attractor on which a trajectory of the impact map has settled
+//
+use super::impact::Impact as Impact;
+use super::impact::default_impact_comparer as default_impact_comparer;
+use super::parameters::Parameters as Parameters;
+use super::model_types::Distance as Distance;
+use super::impact_map::IterationResult as IterationResult;
+use std::fmt;
+
+/// The kind of motion a trajectory has settled on.
+///
+/// A periodic orbit with `impacts` impacts every `periods` forcing periods is conventionally
+/// labelled (m, n). Chatter is the (∞, n) case, in which a cycle contains an accumulation of
+/// impacts onto a sticking impact. Non-impacting motion is harmonic, with displacement bounded by
+/// `amplitude`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum OrbitType {
+    Periodic {impacts: u32, periods: u32},
+    Chatter,
+    NonImpacting {amplitude: Distance},
+    LongExcursions,
+    Aperiodic
+}
+
+impl fmt::Display for OrbitType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OrbitType::Periodic{impacts, periods} => write!(f, "({}, {})", impacts, periods),
+            OrbitType::Chatter => write!(f, "chatter"),
+            OrbitType::NonImpacting{..} => write!(f, "non-impacting"),
+            OrbitType::LongExcursions => write!(f, "long excursions"),
+            OrbitType::Aperiodic => write!(f, "aperiodic")
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct OrbitClassifier {
+    parameters: Parameters,
+    maximum_impacts: u32
+}
+
+impl OrbitClassifier {
+    /// `maximum_impacts` is the largest number of impacts per cycle which will be recognised as periodic
+    pub fn new(parameters: Parameters, maximum_impacts: u32) -> OrbitClassifier {
+        OrbitClassifier{parameters, maximum_impacts}
+    }
+
+    pub fn default(parameters: Parameters) -> OrbitClassifier {
+        OrbitClassifier::new(parameters, 20)
+    }
+
+    pub fn classify(&self, result: &IterationResult) -> OrbitType {
+        if let Some(amplitude) = result.non_impacting_amplitude() {
+            return OrbitType::NonImpacting{amplitude};
+        }
+
+        if result.has_long_excursions() {
+            return OrbitType::LongExcursions;
+        }
+
+        self.classify_tail(result.trajectory())
+    }
+
+    // Looks for the shortest cycle which the end of the trajectory has repeated at least once
+    pub fn classify_tail(&self, trajectory: &[Impact]) -> OrbitType {
+        let comparer = default_impact_comparer();
+
+        let len = trajectory.len();
+
+        for m in 1..=(self.maximum_impacts as usize) {
+            if 2 * m >= len {
+                break;
+            }
+
+            let repeats = (0..m).all(|j| comparer(trajectory[len - 1 - j], trajectory[len - 1 - j - m]));
+
+            if repeats {
+                let cycle = &trajectory[len - 1 - m..];
+
+                if cycle.iter().any(|impact| impact.velocity() == 0.0) {
+                    return OrbitType::Chatter;
+                }
+
+                let periods = (cycle[m].time_since(cycle[0]) / self.parameters.converter().period()).round() as u32;
+
+                return OrbitType::Periodic{impacts: m as u32, periods};
+            }
+        }
+
+        // A tail which keeps returning to zero velocity without settling on a short cycle is still chatter
+        let tail_start = len.saturating_sub(2 * self.maximum_impacts as usize);
+
+        if trajectory[tail_start..].iter().skip(1).any(|impact| impact.velocity() == 0.0) {
+            return OrbitType::Chatter;
+        }
+
+        OrbitType::Aperiodic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parameters::Parameters;
+    use super::super::impact_map::ImpactMap;
+
+    fn classify(frequency: f64, offset: f64, r: f64) -> OrbitType {
+        let parameters = Parameters::new(frequency, offset, r, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let result = mapper.iterate_from_point(0.0, 0.0, 2000);
+
+        OrbitClassifier::default(parameters).classify(&result)
+    }
+
+    #[test]
+    fn test_classify_periodic_orbits() {
+        assert_eq!(classify(2.0, 0.0, 0.8), OrbitType::Periodic{impacts: 1, periods: 1});
+        assert_eq!(classify(4.85, -0.1, 0.8), OrbitType::Periodic{impacts: 1, periods: 2});
+        assert_eq!(classify(2.8, 0.1, 0.8), OrbitType::Periodic{impacts: 4, periods: 4});
+    }
+
+    #[test]
+    fn test_classify_chatter() {
+        assert_eq!(classify(4.0, -0.1, 0.5), OrbitType::Chatter);
+    }
+
+    #[test]
+    fn test_classify_chatter_clear_of_forced_response() {
+        // The forced response alone would stay clear of the obstacle, but the motion after an impact does not, and
+        // slow impacts accumulate
+        let parameters = Parameters::new(4.85, 0.2, 0.5, 5).unwrap();
+
+        let result = ImpactMap::new(parameters).iterate_from_point(0.8, 0.1, 1000);
+
+        assert!(result.non_impacting_amplitude().is_none());
+        assert_eq!(OrbitClassifier::default(parameters).classify(&result), OrbitType::Chatter);
+    }
+
+    #[test]
+    fn test_orbit_type_labels() {
+        assert_eq!(format!("{}", OrbitType::Periodic{impacts: 1, periods: 2}), "(1, 2)");
+        assert_eq!(format!("{}", OrbitType::Chatter), "chatter");
+    }
+}
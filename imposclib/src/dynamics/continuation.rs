@@ -0,0 +1,570 @@
+//
+// Pseudo-arclength continuation of periodic orbits of the impact map in one of the system parameters
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::linearisation::Jacobian as Jacobian;
+use super::linearisation::Eigenvalues as Eigenvalues;
+use super::model_types::Distance as Distance;
+use super::model_types::Velocity as Velocity;
+use super::parameters::Parameters as Parameters;
+use super::parameters::ParameterName as ParameterName;
+use super::periodic_orbit::PeriodicOrbit as PeriodicOrbit;
+use std::fmt;
+
+/// Bifurcations and other events which can be detected along a branch of periodic orbits
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum EventKind {
+    /// A multiplier passes through +1, typically at a fold in the branch
+    SaddleNode,
+    /// A multiplier passes through -1
+    PeriodDoubling,
+    /// The orbit touches the singularity set, either because one of its impacts reaches zero velocity or
+    /// because the motion between impacts comes up to the obstacle without hitting it. The branch cannot be
+    /// continued smoothly beyond this point.
+    Grazing
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EventKind::SaddleNode => write!(f, "saddle-node"),
+            EventKind::PeriodDoubling => write!(f, "period-doubling"),
+            EventKind::Grazing => write!(f, "grazing")
+        }
+    }
+}
+
+/// An event located between two successive points of a branch, with the parameter value at which it occurs
+/// estimated by linear interpolation
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct BranchEvent {
+    pub kind: EventKind,
+    pub parameter: f64,
+    /// Index of the first point of the branch after the event
+    pub index: usize
+}
+
+/// Why continuation of a branch stopped
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Termination {
+    LeftParameterRange,
+    MaximumPoints,
+    Grazing,
+    StepTooSmall,
+    InvalidParameters
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Termination::LeftParameterRange => write!(f, "left parameter range"),
+            Termination::MaximumPoints => write!(f, "maximum points reached"),
+            Termination::Grazing => write!(f, "grazing"),
+            Termination::StepTooSmall => write!(f, "step too small"),
+            Termination::InvalidParameters => write!(f, "invalid parameters")
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BranchPoint {
+    parameter: f64,
+    orbit: Vec<Impact>,
+    multipliers: Eigenvalues,
+    clearance: f64
+}
+
+impl BranchPoint {
+    pub fn parameter(&self) -> f64 {
+        self.parameter
+    }
+
+    /// The impacts of one cycle of the orbit
+    pub fn orbit(&self) -> &[Impact] {
+        &self.orbit
+    }
+
+    pub fn multipliers(&self) -> Eigenvalues {
+        self.multipliers
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.multipliers.spectral_radius() < 1.0
+    }
+
+    /// The slowest impact velocity of the orbit, which vanishes when one of its impacts grazes
+    pub fn slowest_impact(&self) -> Velocity {
+        self.orbit.iter().map(|impact| impact.velocity()).fold(f64::INFINITY, f64::min)
+    }
+
+    /// The closest approach to the obstacle between impacts, which vanishes when the motion between two impacts
+    /// grazes. It is infinite if the displacement has no maximum short of the obstacle.
+    pub fn clearance(&self) -> Distance {
+        self.clearance
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Branch {
+    parameter: ParameterName,
+    points: Vec<BranchPoint>,
+    events: Vec<BranchEvent>,
+    termination: Termination
+}
+
+impl Branch {
+    pub fn parameter(&self) -> ParameterName {
+        self.parameter
+    }
+
+    pub fn points(&self) -> &[BranchPoint] {
+        &self.points
+    }
+
+    pub fn events(&self) -> &[BranchEvent] {
+        &self.events
+    }
+
+    pub fn termination(&self) -> Termination {
+        self.termination
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ContinuationSettings {
+    pub initial_step: f64,
+    pub minimum_step: f64,
+    pub maximum_step: f64,
+    pub maximum_points: usize,
+    /// Continuation stops when the parameter leaves this range
+    pub parameter_range: (f64, f64),
+    pub tolerance: f64,
+    /// An orbit whose slowest impact velocity is smaller than this is taken to be grazing. The derivatives of the
+    /// map grow without bound as an orbit approaches grazing, so the corrector cannot get arbitrarily close.
+    pub grazing_velocity: Velocity,
+    /// Likewise for an orbit whose clearance is smaller than this
+    pub grazing_clearance: Distance
+}
+
+impl ContinuationSettings {
+    pub fn new(parameter_range: (f64, f64)) -> ContinuationSettings {
+        ContinuationSettings{
+            initial_step: 0.01,
+            minimum_step: 1e-6,
+            maximum_step: 0.05,
+            maximum_points: 1000,
+            parameter_range,
+            tolerance: 1e-9,
+            grazing_velocity: 1e-2,
+            grazing_clearance: 1e-2
+        }
+    }
+}
+
+// A point in the space of unknowns: (phase of the first impact, its velocity, parameter value)
+type State = [f64; 3];
+
+/// Follows an (m, n) orbit as one parameter varies.
+///
+/// The orbit is described by the phase and velocity of its first impact. Together with the parameter these
+/// satisfy two equations - that m impacts later the motion returns to the same velocity after exactly n
+/// periods - so solutions form curves, which are followed by a predictor along the tangent and a Newton
+/// corrector constrained to the plane a fixed arclength along it. This passes around folds, where the
+/// parameter reverses direction, unlike continuation in the parameter itself.
+pub struct Continuation {
+    parameters: Parameters,
+    parameter: ParameterName,
+    impacts: u32,
+    periods: u32,
+    settings: ContinuationSettings
+}
+
+impl Continuation {
+    pub fn new(parameters: Parameters, parameter: ParameterName, impacts: u32, periods: u32, settings: ContinuationSettings) -> Continuation {
+        Continuation{parameters, parameter, impacts, periods, settings}
+    }
+
+    /// Continues from `orbit`, a solution for the starting parameters, initially increasing the parameter if
+    /// `increasing` is set and decreasing it otherwise
+    pub fn run(&self, orbit: &PeriodicOrbit, increasing: bool) -> Branch {
+        const MINIMUM_TANGENT_ALIGNMENT: f64 = 0.9;
+
+        let start = orbit.points()[0];
+
+        let mut state = [start.phase(), start.velocity(), self.parameters.value(self.parameter)];
+
+        let mut branch = Branch{parameter: self.parameter, points: vec![], events: vec![], termination: Termination::MaximumPoints};
+
+        let mut direction = match self.linearise(state).and_then(|jacobian| tangent(&jacobian, None)) {
+            Some(direction) => direction,
+            None => {
+                branch.termination = Termination::InvalidParameters;
+                return branch;
+            }
+        };
+
+        if (direction[2] < 0.0) == increasing {
+            direction = scale(direction, -1.0);
+        }
+
+        match self.branch_point(state) {
+            Some(point) => branch.points.push(point),
+            None => {
+                branch.termination = Termination::InvalidParameters;
+                return branch;
+            }
+        }
+
+        let mut step = self.settings.initial_step;
+
+        while branch.points.len() < self.settings.maximum_points {
+            let corrected = self.correct(state, direction, step);
+
+            let (next, iterations) = match corrected {
+                Some(result) => result,
+                None => {
+                    step *= 0.5;
+
+                    if step < self.settings.minimum_step {
+                        branch.termination = Termination::StepTooSmall;
+                        break;
+                    }
+
+                    continue;
+                }
+            };
+
+            let point = match self.branch_point(next) {
+                Some(point) => point,
+                None => {
+                    branch.termination = Termination::InvalidParameters;
+                    break;
+                }
+            };
+
+            let next_tangent = match self.linearise(next).and_then(|jacobian| tangent(&jacobian, Some(direction))) {
+                Some(next_tangent) => next_tangent,
+                None => {
+                    branch.termination = Termination::InvalidParameters;
+                    break;
+                }
+            };
+
+            // A sharp turn means the corrector has jumped across a discontinuity, e.g. onto the far side of a
+            // grazing, so approach it more cautiously
+            if dot(next_tangent, direction) < MINIMUM_TANGENT_ALIGNMENT {
+                step *= 0.5;
+
+                if step < self.settings.minimum_step {
+                    branch.termination = Termination::StepTooSmall;
+                    break;
+                }
+
+                continue;
+            }
+
+            self.detect_events(&mut branch, &point);
+
+            branch.points.push(point);
+
+            if branch.events.last().is_some_and(|event| event.kind == EventKind::Grazing) {
+                branch.termination = Termination::Grazing;
+                break;
+            }
+
+            if next[2] < self.settings.parameter_range.0 || next[2] > self.settings.parameter_range.1 {
+                branch.termination = Termination::LeftParameterRange;
+                break;
+            }
+
+            state = next;
+            direction = next_tangent;
+
+            if iterations <= 3 {
+                step = (1.5 * step).min(self.settings.maximum_step);
+            }
+        }
+
+        branch
+    }
+
+    // Compares the new point with the last one on the branch for sign changes in the test functions
+    fn detect_events(&self, branch: &mut Branch, point: &BranchPoint) {
+        let previous = branch.points.last().unwrap();
+
+        let (previous_parameter, previous_multipliers) = (previous.parameter, previous.multipliers);
+
+        let (previous_velocity, previous_clearance) = (previous.slowest_impact(), previous.clearance());
+
+        let index = branch.points.len();
+
+        let mut record = |kind: EventKind, before: f64, after: f64| {
+            // A clearance can appear from nowhere, when the displacement first has a maximum between impacts
+            let fraction = if before.is_finite() {before / (before - after)} else {1.0};
+
+            branch.events.push(BranchEvent{kind, parameter: previous_parameter + fraction * (point.parameter - previous_parameter), index});
+        };
+
+        let (saddle_node_before, period_doubling_before) = test_functions(previous_multipliers);
+        let (saddle_node_after, period_doubling_after) = test_functions(point.multipliers);
+
+        if saddle_node_before * saddle_node_after < 0.0 {
+            record(EventKind::SaddleNode, saddle_node_before, saddle_node_after);
+        }
+
+        if period_doubling_before * period_doubling_after < 0.0 {
+            record(EventKind::PeriodDoubling, period_doubling_before, period_doubling_after);
+        }
+
+        // The velocity and the clearance are tracked separately, since they are not measured in the same units
+        if point.slowest_impact() < self.settings.grazing_velocity {
+            record(EventKind::Grazing, previous_velocity, point.slowest_impact());
+        } else if point.clearance() < self.settings.grazing_clearance {
+            record(EventKind::Grazing, previous_clearance, point.clearance());
+        }
+    }
+
+    // Predicts along the tangent and then corrects back onto the branch, returning the new point and the
+    // number of Newton iterations taken
+    fn correct(&self, state: State, tangent: State, step: f64) -> Option<(State, u32)> {
+        const MAXIMUM_ITERATIONS: u32 = 8;
+
+        let mut current = add(state, scale(tangent, step));
+
+        for iteration in 1..=MAXIMUM_ITERATIONS {
+            let residual = self.residual(current)?;
+
+            let arclength = dot(subtract(current, state), tangent) - step;
+
+            let jacobian = self.linearise(current)?;
+
+            let correction = solve([jacobian[0], jacobian[1], tangent], [-residual[0], -residual[1], -arclength])?;
+
+            // A correction longer than the largest step has left the neighbourhood of the branch
+            if !norm(correction).is_finite() || norm(correction) > self.settings.maximum_step {
+                return None;
+            }
+
+            current = add(current, correction);
+
+            if norm(correction) < self.settings.tolerance {
+                return Some((current, iteration));
+            }
+        }
+
+        None
+    }
+
+    // The (m, n) orbit conditions: (phase advance less n, change in velocity) after m impacts
+    fn residual(&self, state: State) -> Option<[f64; 2]> {
+        // Beyond grazing the predictor can overshoot to impacts with negative velocity, which are unphysical
+        if state[1] <= 0.0 {
+            return None;
+        }
+
+        let mapper = ImpactMap::new(self.parameters.with_value(self.parameter, state[2]).ok()?);
+
+        let period = mapper.converter().period();
+
+        let start = mapper.generate_impact(state[0] * period, state[1]);
+
+        let image = mapper.apply_repeatedly(start, self.impacts)?;
+
+        Some([image.time_since(start) / period - self.periods as f64, image.velocity() - state[1]])
+    }
+
+    // Derivatives of the residual with respect to each of the unknowns, by central differences
+    fn linearise(&self, state: State) -> Option<[State; 2]> {
+        const STEP: f64 = 1e-6;
+
+        let mut rows = [[0.0; 3]; 2];
+
+        for column in 0..3 {
+            let mut plus = state;
+            let mut minus = state;
+
+            plus[column] += STEP;
+            minus[column] -= STEP;
+
+            let (upper, lower) = (self.residual(plus)?, self.residual(minus)?);
+
+            for row in 0..2 {
+                rows[row][column] = (upper[row] - lower[row]) / (2.0 * STEP);
+            }
+        }
+
+        Some(rows)
+    }
+
+    fn branch_point(&self, state: State) -> Option<BranchPoint> {
+        let mapper = ImpactMap::new(self.parameters.with_value(self.parameter, state[2]).ok()?);
+
+        let first = mapper.impact_from_point(state[0].rem_euclid(1.0), state[1]);
+
+        let mut orbit = vec![first];
+
+        for _ in 1..self.impacts {
+            orbit.push(mapper.apply_repeatedly(*orbit.last().unwrap(), 1)?);
+        }
+
+        let multipliers = Jacobian::of_return_map(&mapper, first, self.impacts)?.eigenvalues();
+
+        let clearance = orbit.iter().filter_map(|&impact| mapper.clearance(impact)).fold(f64::INFINITY, f64::min);
+
+        Some(BranchPoint{parameter: state[2], orbit, multipliers, clearance})
+    }
+}
+
+// For a real multiplier crossing +1 or -1 the product (λ1 - 1)(λ2 - 1) or (λ1 + 1)(λ2 + 1) changes sign
+fn test_functions(multipliers: Eigenvalues) -> (f64, f64) {
+    match multipliers {
+        Eigenvalues::Real(first, second) => ((first - 1.0) * (second - 1.0), (first + 1.0) * (second + 1.0)),
+        Eigenvalues::ComplexPair{real, imaginary} => {
+            let modulus_squared = real * real + imaginary * imaginary;
+
+            (1.0 - 2.0 * real + modulus_squared, 1.0 + 2.0 * real + modulus_squared)
+        }
+    }
+}
+
+// The unit tangent to the branch is orthogonal to both rows of the linearisation. It is oriented to keep going the
+// same way along the branch as `previous`, if given.
+fn tangent(jacobian: &[State; 2], previous: Option<State>) -> Option<State> {
+    let [a, b] = jacobian;
+
+    let cross = [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+
+    let length = norm(cross);
+
+    if length == 0.0 || !length.is_finite() {
+        return None;
+    }
+
+    let unit = scale(cross, 1.0 / length);
+
+    Some(match previous {
+        Some(previous) if dot(unit, previous) < 0.0 => scale(unit, -1.0),
+        _ => unit
+    })
+}
+
+// Gaussian elimination with partial pivoting
+fn solve(matrix: [State; 3], vector: State) -> Option<State> {
+    let mut rows = [
+        [matrix[0][0], matrix[0][1], matrix[0][2], vector[0]],
+        [matrix[1][0], matrix[1][1], matrix[1][2], vector[1]],
+        [matrix[2][0], matrix[2][1], matrix[2][2], vector[2]]];
+
+    for column in 0..3 {
+        let pivot = (column..3).max_by(|&i, &j| rows[i][column].abs().total_cmp(&rows[j][column].abs()))?;
+
+        if rows[pivot][column] == 0.0 {
+            return None;
+        }
+
+        rows.swap(column, pivot);
+
+        for row in (column + 1)..3 {
+            let factor = rows[row][column] / rows[column][column];
+
+            let pivot_row = rows[column];
+
+            for (entry, pivot_entry) in rows[row].iter_mut().zip(pivot_row.iter()).skip(column) {
+                *entry -= factor * pivot_entry;
+            }
+        }
+    }
+
+    let mut solution = [0.0; 3];
+
+    for row in (0..3).rev() {
+        let known: f64 = ((row + 1)..3).map(|k| rows[row][k] * solution[k]).sum();
+
+        solution[row] = (rows[row][3] - known) / rows[row][row];
+    }
+
+    if solution.iter().all(|x| x.is_finite()) {Some(solution)} else {None}
+}
+
+fn add(x: State, y: State) -> State {
+    [x[0] + y[0], x[1] + y[1], x[2] + y[2]]
+}
+
+fn subtract(x: State, y: State) -> State {
+    [x[0] - y[0], x[1] - y[1], x[2] - y[2]]
+}
+
+fn scale(x: State, factor: f64) -> State {
+    [factor * x[0], factor * x[1], factor * x[2]]
+}
+
+fn dot(x: State, y: State) -> f64 {
+    x[0] * y[0] + x[1] * y[1] + x[2] * y[2]
+}
+
+fn norm(x: State) -> f64 {
+    dot(x, x).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::periodic_orbit::OrbitSolver;
+
+    fn orbit(parameters: Parameters, guess: (f64, f64)) -> PeriodicOrbit {
+        let mapper = ImpactMap::new(parameters);
+
+        OrbitSolver::default(&mapper).solve(mapper.impact_from_point(guess.0, guess.1), 1, 1).unwrap()
+    }
+
+    #[test]
+    fn test_solve_linear_system() {
+        let solution = solve([[2.0, 1.0, 0.0], [0.0, 0.0, 3.0], [1.0, 0.0, 1.0]], [3.0, 6.0, 3.0]).unwrap();
+
+        for (x, y) in solution.iter().zip([1.0, 1.0, 2.0].iter()) {
+            assert!((x - y).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_period_doubling_in_frequency() {
+        // The (1, 1) orbit is stable for ω = 2.5 and a flip saddle for ω = 2.8
+        let parameters = Parameters::new(2.5, 0.0, 0.8, 100).unwrap();
+
+        let continuation = Continuation::new(parameters, ParameterName::Frequency, 1, 1, ContinuationSettings::new((2.4, 2.8)));
+
+        let branch = continuation.run(&orbit(parameters, (0.4784, 0.6454)), true);
+
+        assert_eq!(branch.termination(), Termination::LeftParameterRange);
+        assert!(branch.points()[0].is_stable());
+        assert!(!branch.points().last().unwrap().is_stable());
+
+        let event = branch.events().iter().find(|event| event.kind == EventKind::PeriodDoubling).unwrap();
+
+        assert!(2.6 < event.parameter && event.parameter < 2.7);
+    }
+
+    #[test]
+    fn test_branch_ends_at_grazing() {
+        // Raising the obstacle slows the impacts of the (1, 1) orbit until it grazes
+        let parameters = Parameters::new(2.6, 0.0, 0.8, 100).unwrap();
+
+        let continuation = Continuation::new(parameters, ParameterName::Offset, 1, 1, ContinuationSettings::new((-1.0, 1.0)));
+
+        let branch = continuation.run(&orbit(parameters, (0.4821, 0.5054)), true);
+
+        assert_eq!(branch.termination(), Termination::Grazing);
+
+        let event = branch.events().last().unwrap();
+
+        assert_eq!(event.kind, EventKind::Grazing);
+        assert!(0.1 < event.parameter && event.parameter < 0.3);
+
+        // It is the impact velocity which grazes, not the motion in between
+        let last = branch.points().last().unwrap();
+
+        assert!(last.slowest_impact() < continuation.settings.grazing_velocity);
+        assert!(last.clearance() >= continuation.settings.grazing_clearance);
+    }
+}
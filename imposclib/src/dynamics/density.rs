@@ -0,0 +1,272 @@
+//
+// Estimates of the invariant density of the impact map, accumulated while the map is iterated
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::impact_map::IterationContext as IterationContext;
+use super::impact_map::IterationControl as IterationControl;
+use super::impact_map::TrajectorySampling as TrajectorySampling;
+use super::model_types::Phase as Phase;
+use super::model_types::Velocity as Velocity;
+use std::sync::atomic::AtomicBool;
+
+/// A histogram of impacts over (phase, velocity) with a fixed number of bins, so that its size does not depend on
+/// how many impacts are added.
+///
+/// The phase bins cover one forcing cycle, and phases are wrapped into it. The velocity bins cover a range whose
+/// width is a power of two, starting from the bins around the first impact. An impact outside this range doubles
+/// it towards the impact, merging adjacent pairs of bins, until the range contains it, so the bounds adapt to the
+/// attractor and the range only grows when an impact falls outside it.
+#[derive(Debug, Clone)]
+pub struct DensityHistogram {
+    phase_bins: usize,
+    velocity_bins: usize,
+    velocity_width: Velocity,
+    velocity_lower: Velocity,
+    // One row of phase bins for each velocity bin
+    counts: Vec<u64>,
+    total: u64
+}
+
+impl DensityHistogram {
+    // Width of the velocity range when the first impact is added
+    const INITIAL_WIDTH: Velocity = 1.0 / 64.0;
+
+    /// The number of velocity bins is rounded up to an even number, so that bins can be merged in pairs
+    pub fn new(phase_bins: usize, velocity_bins: usize) -> DensityHistogram {
+        let phase_bins = std::cmp::max(1, phase_bins);
+        let velocity_bins = std::cmp::max(2, velocity_bins + velocity_bins % 2);
+
+        DensityHistogram{
+            phase_bins,
+            velocity_bins,
+            velocity_width: DensityHistogram::INITIAL_WIDTH,
+            velocity_lower: 0.0,
+            counts: vec![0; phase_bins * velocity_bins],
+            total: 0
+        }
+    }
+
+    pub fn add(&mut self, impact: Impact) {
+        self.add_point(impact.phase(), impact.velocity());
+    }
+
+    /// Points with a velocity which is not finite are ignored
+    pub fn add_point(&mut self, phase: Phase, velocity: Velocity) {
+        if !velocity.is_finite() || !phase.is_finite() {
+            return;
+        }
+
+        if self.total == 0 {
+            self.velocity_lower = (velocity / self.velocity_width).floor() * self.velocity_width;
+        }
+
+        while !self.velocity_in_range(velocity) {
+            self.double_velocity_width(velocity < self.velocity_lower);
+        }
+
+        let phase_bin = ((phase - phase.floor()) * self.phase_bins as f64) as usize % self.phase_bins;
+
+        let velocity_bin = std::cmp::min(self.velocity_bins - 1,
+            ((velocity - self.velocity_range().0) / self.velocity_width * self.velocity_bins as f64) as usize);
+
+        self.counts[velocity_bin * self.phase_bins + phase_bin] += 1;
+
+        self.total += 1;
+    }
+
+    /// Iterates `mapper` from `initial_impact`, discarding `transient` impacts and adding the impacts from the next
+    /// `num_iterations` iterations to the histogram. No trajectory is kept. The initial impact itself is never added,
+    /// so accumulation can be resumed from the last impact, which is returned, without counting it twice. Setting
+    /// `cancellation` stops the iteration early, leaving whatever has been added so far.
+    pub fn accumulate(&mut self, mapper: &ImpactMap, initial_impact: Impact, transient: u32, num_iterations: u32, cancellation: &AtomicBool) -> Impact {
+        let mut context = IterationContext::new();
+
+        let start = if transient > 0 {
+            let mut control = IterationControl::new().with_sampling(TrajectorySampling::none()).with_cancellation(cancellation);
+
+            mapper.iterate_with_control(&mut context, &mut control, initial_impact, transient).final_impact()
+        } else {
+            initial_impact
+        };
+
+        let mut control = IterationControl::new().with_sampling(TrajectorySampling::none()).with_cancellation(cancellation).with_density(self);
+
+        mapper.iterate_with_control(&mut context, &mut control, start, num_iterations).final_impact()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn phase_bins(&self) -> usize {
+        self.phase_bins
+    }
+
+    pub fn velocity_bins(&self) -> usize {
+        self.velocity_bins
+    }
+
+    /// The lower and upper bounds of the velocity bins
+    pub fn velocity_range(&self) -> (Velocity, Velocity) {
+        (self.velocity_lower, self.velocity_lower + self.velocity_width)
+    }
+
+    pub fn count(&self, phase_bin: usize, velocity_bin: usize) -> u64 {
+        self.counts[velocity_bin * self.phase_bins + phase_bin]
+    }
+
+    /// The edges of the phase bins, from 0 to 1
+    pub fn phase_edges(&self) -> Vec<Phase> {
+        (0..=self.phase_bins).map(|i| i as f64 / self.phase_bins as f64).collect()
+    }
+
+    pub fn velocity_edges(&self) -> Vec<Velocity> {
+        let (lower, _) = self.velocity_range();
+
+        (0..=self.velocity_bins).map(|i| lower + self.velocity_width * i as f64 / self.velocity_bins as f64).collect()
+    }
+
+    /// The estimated density in each bin, with one row of phase bins per velocity bin, normalised so that it
+    /// integrates to one over the impact surface. All zero if nothing has been added.
+    pub fn density(&self) -> Vec<Vec<f64>> {
+        let cell_area = self.velocity_width / (self.velocity_bins * self.phase_bins) as f64;
+
+        let scale = if self.total > 0 {1.0 / (self.total as f64 * cell_area)} else {0.0};
+
+        self.counts.chunks(self.phase_bins)
+            .map(|row| row.iter().map(|&count| count as f64 * scale).collect())
+            .collect()
+    }
+
+    fn velocity_in_range(&self, velocity: Velocity) -> bool {
+        let (lower, upper) = self.velocity_range();
+
+        lower <= velocity && velocity < upper
+    }
+
+    // The current range becomes the upper half of the new one when growing downwards, and the lower half otherwise
+    fn double_velocity_width(&mut self, downwards: bool) {
+        let half = self.velocity_bins / 2;
+
+        let offset = if downwards {half} else {0};
+
+        let mut merged = vec![0; self.counts.len()];
+
+        for bin in 0..self.velocity_bins {
+            let target = offset + bin / 2;
+
+            for phase_bin in 0..self.phase_bins {
+                merged[target * self.phase_bins + phase_bin] += self.counts[bin * self.phase_bins + phase_bin];
+            }
+        }
+
+        self.counts = merged;
+
+        if downwards {
+            self.velocity_lower -= self.velocity_width;
+        }
+
+        self.velocity_width *= 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parameters::Parameters as Parameters;
+
+    #[test]
+    fn test_phases_wrap_and_bounds_adapt() {
+        let mut histogram = DensityHistogram::new(4, 3);
+
+        assert_eq!(histogram.velocity_bins(), 4);
+
+        histogram.add_point(0.1, 0.5);
+        histogram.add_point(1.1, 0.5);
+        histogram.add_point(-0.9, 0.5);
+
+        let (lower, upper) = histogram.velocity_range();
+
+        assert!(lower <= 0.5 && 0.5 < upper);
+        assert_eq!(upper - lower, DensityHistogram::INITIAL_WIDTH);
+
+        histogram.add_point(0.6, 3.0);
+
+        let (lower, upper) = histogram.velocity_range();
+
+        assert!(lower <= 0.5 && 3.0 < upper);
+        assert_eq!(histogram.total(), 4);
+
+        let phase_totals: Vec<u64> = (0..4).map(|phase_bin| (0..4).map(|velocity_bin| histogram.count(phase_bin, velocity_bin)).sum()).collect();
+
+        assert_eq!(phase_totals, vec![3, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_range_grows_across_zero() {
+        let mut histogram = DensityHistogram::new(4, 4);
+
+        histogram.add_point(0.1, 0.5);
+        histogram.add_point(0.1, -0.01);
+
+        let (lower, upper) = histogram.velocity_range();
+
+        assert!(lower <= -0.01 && 0.5 < upper);
+        assert_eq!(histogram.total(), 2);
+
+        let velocity_totals: Vec<u64> = (0..4).map(|velocity_bin| histogram.count(0, velocity_bin)).collect();
+
+        assert_eq!(velocity_totals.iter().sum::<u64>(), 2);
+        assert_eq!(velocity_totals.iter().filter(|&&count| count > 0).count(), 2);
+
+        let mapper = ImpactMap::new(Parameters::new(4.85, 0.2, 0.5, 100).unwrap());
+
+        let mut histogram = DensityHistogram::new(20, 20);
+
+        histogram.accumulate(&mapper, mapper.impact_from_point(0.8, 0.1), 0, 2000, &AtomicBool::new(false));
+
+        assert!(histogram.total() > 0);
+    }
+
+    #[test]
+    fn test_density_integrates_to_one() {
+        let mapper = ImpactMap::new(Parameters::new(4.85, -0.1, 0.8, 100).unwrap());
+
+        let mut histogram = DensityHistogram::new(50, 40);
+
+        histogram.accumulate(&mapper, mapper.impact_from_point(0.0, 0.0), 100, 2000, &AtomicBool::new(false));
+
+        assert!(histogram.total() >= 2000);
+
+        let cell_area = (histogram.velocity_range().1 - histogram.velocity_range().0) / (50.0 * 40.0);
+
+        let integral: f64 = histogram.density().iter().flatten().map(|density| density * cell_area).sum();
+
+        assert!((integral - 1.0).abs() < 1e-9);
+
+        assert_eq!(histogram.phase_edges().len(), 51);
+        assert_eq!(histogram.velocity_edges().len(), 41);
+    }
+
+    #[test]
+    fn test_accumulation_can_be_resumed() {
+        let mapper = ImpactMap::new(Parameters::new(4.85, -0.1, 0.8, 100).unwrap());
+
+        let initial_impact = mapper.impact_from_point(0.0, 0.0);
+
+        let mut whole = DensityHistogram::new(20, 20);
+
+        let running = AtomicBool::new(false);
+
+        whole.accumulate(&mapper, initial_impact, 0, 200, &running);
+
+        let mut halves = DensityHistogram::new(20, 20);
+
+        let middle = halves.accumulate(&mapper, initial_impact, 0, 100, &running);
+
+        halves.accumulate(&mapper, middle, 0, 100, &running);
+
+        assert_eq!(whole.density(), halves.density());
+    }
+}
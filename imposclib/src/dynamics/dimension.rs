@@ -0,0 +1,329 @@
+//
+// Fractal dimensions of sets of impacts, for comparing chaotic attractors
+//
+use super::impact::SimpleImpact as SimpleImpact;
+use super::linearisation::phase_difference as phase_difference;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Distance between two points of the impact surface, treating phase as periodic as the impact comparers do
+pub fn simple_impact_distance(x: SimpleImpact, y: SimpleImpact) -> f64 {
+    phase_difference(x.phase(), y.phase()).hypot(x.velocity() - y.velocity())
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DimensionMethod {
+    Correlation,
+    BoxCounting
+}
+
+impl fmt::Display for DimensionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DimensionMethod::Correlation => write!(f, "correlation"),
+            DimensionMethod::BoxCounting => write!(f, "box-counting")
+        }
+    }
+}
+
+/// A least-squares line through part of a log-log plot, where the plot is straight enough for its slope to be
+/// taken as a dimension. `from` and `to` index the first and last points of the plot used in the fit.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ScalingFit {
+    pub slope: f64,
+    pub intercept: f64,
+    pub standard_error: f64,
+    pub from: usize,
+    pub to: usize
+}
+
+impl ScalingFit {
+    /// The scaling region is the run of consecutive points, at least a third of those available (and at least
+    /// four), over which the fitted slope has the smallest standard error. Returns `None` if there are too few
+    /// points.
+    pub fn find(x: &[f64], y: &[f64]) -> Option<ScalingFit> {
+        const MINIMUM_POINTS: usize = 4;
+
+        let minimum_length = std::cmp::max(MINIMUM_POINTS, x.len().div_ceil(3));
+
+        if x.len() < minimum_length {
+            return None;
+        }
+
+        let mut best: Option<ScalingFit> = None;
+
+        for from in 0..=(x.len() - minimum_length) {
+            for to in (from + minimum_length - 1)..x.len() {
+                if let Some(fit) = ScalingFit::fit(x, y, from, to) {
+                    if best.is_none_or(|best| fit.standard_error < best.standard_error) {
+                        best = Some(fit);
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    fn fit(x: &[f64], y: &[f64], from: usize, to: usize) -> Option<ScalingFit> {
+        let x = &x[from..=to];
+        let y = &y[from..=to];
+
+        let n = x.len() as f64;
+
+        let mean_x = x.iter().sum::<f64>() / n;
+        let mean_y = y.iter().sum::<f64>() / n;
+
+        let sxx: f64 = x.iter().map(|xi| (xi - mean_x).powi(2)).sum();
+        let sxy: f64 = x.iter().zip(y.iter()).map(|(xi, yi)| (xi - mean_x) * (yi - mean_y)).sum();
+
+        if sxx == 0.0 {
+            return None;
+        }
+
+        let slope = sxy / sxx;
+        let intercept = mean_y - slope * mean_x;
+
+        let residuals: f64 = x.iter().zip(y.iter()).map(|(xi, yi)| (yi - intercept - slope * xi).powi(2)).sum();
+
+        let standard_error = (residuals / (n - 2.0) / sxx).sqrt();
+
+        Some(ScalingFit{slope, intercept, standard_error, from, to})
+    }
+}
+
+/// A dimension estimated from the slope of a log-log plot of some measure of the set against the scale at which
+/// it is measured: the correlation sum for the correlation dimension, and the number of occupied boxes for the
+/// box-counting dimension. The error is the standard error of the slope over the scaling region.
+#[derive(Debug, Clone)]
+pub struct DimensionEstimate {
+    method: DimensionMethod,
+    log_scales: Vec<f64>,
+    log_measures: Vec<f64>,
+    fit: ScalingFit
+}
+
+impl DimensionEstimate {
+    pub fn method(&self) -> DimensionMethod {
+        self.method
+    }
+
+    pub fn dimension(&self) -> f64 {
+        match self.method {
+            DimensionMethod::Correlation => self.fit.slope,
+            DimensionMethod::BoxCounting => -self.fit.slope
+        }
+    }
+
+    pub fn error(&self) -> f64 {
+        self.fit.standard_error
+    }
+
+    /// Natural logarithms of the scales, in increasing order
+    pub fn log_scales(&self) -> &[f64] {
+        &self.log_scales
+    }
+
+    pub fn log_measures(&self) -> &[f64] {
+        &self.log_measures
+    }
+
+    pub fn fit(&self) -> ScalingFit {
+        self.fit
+    }
+}
+
+/// Grassberger–Procaccia estimate from the correlation sum C(ε), the proportion of pairs of points closer than ε,
+/// at `num_scales` logarithmically spaced scales from a thousandth of the size of the set up to its size. Scales at
+/// which no pair (or every pair) is counted are left out. Every pair of points is compared, in parallel.
+pub fn correlation_dimension(points: &[SimpleImpact], num_scales: usize) -> Option<DimensionEstimate> {
+    const SCALE_RANGE: f64 = 1e3;
+
+    if points.len() < 2 || num_scales < 2 {
+        return None;
+    }
+
+    let largest_scale = extent(points);
+
+    if largest_scale <= 0.0 {
+        return None;
+    }
+
+    let smallest_scale = largest_scale / SCALE_RANGE;
+
+    let log_step = SCALE_RANGE.ln() / (num_scales - 1) as f64;
+
+    // Counts of pairs by the smallest scale which exceeds their distance
+    let counts = (0..points.len()).into_par_iter().map(|i| {
+        let mut counts = vec![0u64; num_scales];
+
+        for j in (i + 1)..points.len() {
+            let distance = simple_impact_distance(points[i], points[j]);
+
+            let index = if distance < smallest_scale {0} else {((distance / smallest_scale).ln() / log_step).floor() as usize + 1};
+
+            if index < num_scales {
+                counts[index] += 1;
+            }
+        }
+
+        counts
+    }).reduce(|| vec![0u64; num_scales], |a, b| a.iter().zip(b.iter()).map(|(x, y)| x + y).collect());
+
+    let pairs = (points.len() * (points.len() - 1) / 2) as f64;
+
+    let mut cumulative = 0u64;
+
+    let mut log_scales = vec![];
+    let mut log_measures = vec![];
+
+    for (index, count) in counts.iter().enumerate() {
+        cumulative += count;
+
+        if cumulative > 0 && (cumulative as f64) < pairs {
+            log_scales.push(smallest_scale.ln() + index as f64 * log_step);
+            log_measures.push((cumulative as f64 / pairs).ln());
+        }
+    }
+
+    let fit = ScalingFit::find(&log_scales, &log_measures)?;
+
+    Some(DimensionEstimate{method: DimensionMethod::Correlation, log_scales, log_measures, fit})
+}
+
+/// The most scales a box-counting estimate can use: boxes any smaller than 2⁻⁵² cannot tell phases apart
+pub const MAXIMUM_BOX_SCALES: usize = 52;
+
+/// Estimate from the number of occupied square boxes of side 2⁻ᵏ for k = 1, ..., `num_scales`, up to
+/// `MAXIMUM_BOX_SCALES`. The boxes divide the forcing cycle exactly, so that they wrap around the cylinder. Scales at
+/// which there are on average fewer than four points to an occupied box are left out, since by then the count is
+/// saturating at the number of points.
+pub fn box_counting_dimension(points: &[SimpleImpact], num_scales: usize) -> Option<DimensionEstimate> {
+    const POINTS_PER_BOX: usize = 4;
+
+    if points.is_empty() {
+        return None;
+    }
+
+    let mut log_scales = vec![];
+    let mut log_measures = vec![];
+
+    for k in (1..=num_scales.min(MAXIMUM_BOX_SCALES)).rev() {
+        let boxes_per_cycle = (1u64 << k) as f64;
+
+        let occupied: HashSet<(i64, i64)> = points.iter()
+            .map(|point| (((point.phase() - point.phase().floor()) * boxes_per_cycle).floor() as i64 % boxes_per_cycle as i64,
+                (point.velocity() * boxes_per_cycle).floor() as i64))
+            .collect();
+
+        if occupied.len() * POINTS_PER_BOX > points.len() {
+            continue;
+        }
+
+        log_scales.push(-boxes_per_cycle.ln());
+        log_measures.push((occupied.len() as f64).ln());
+    }
+
+    let fit = ScalingFit::find(&log_scales, &log_measures)?;
+
+    Some(DimensionEstimate{method: DimensionMethod::BoxCounting, log_scales, log_measures, fit})
+}
+
+// The largest distance between points, bounded above by half a cycle of phase and the range of velocities
+fn extent(points: &[SimpleImpact]) -> f64 {
+    let lowest = points.iter().map(|point| point.velocity()).fold(f64::INFINITY, f64::min);
+    let highest = points.iter().map(|point| point.velocity()).fold(f64::NEG_INFINITY, f64::max);
+
+    0.5f64.hypot(highest - lowest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::impact_map::ImpactMap as ImpactMap;
+    use super::super::parameters::Parameters as Parameters;
+
+    fn line(num_points: usize) -> Vec<SimpleImpact> {
+        (0..num_points).map(|i| SimpleImpact::new(i as f64 / num_points as f64, 1.0)).collect()
+    }
+
+    fn square(points_per_side: usize) -> Vec<SimpleImpact> {
+        (0..points_per_side * points_per_side)
+            .map(|i| SimpleImpact::new((i % points_per_side) as f64 / points_per_side as f64, 1.0 + (i / points_per_side) as f64 / points_per_side as f64))
+            .collect()
+    }
+
+    #[test]
+    fn test_distance_wraps_phase() {
+        assert!((simple_impact_distance(SimpleImpact::new(0.05, 1.0), SimpleImpact::new(0.95, 1.0)) - 0.1).abs() < 1e-12);
+        assert!((simple_impact_distance(SimpleImpact::new(0.0, 1.0), SimpleImpact::new(0.0, 1.3)) - 0.3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fit_recovers_straight_line() {
+        let x: Vec<f64> = (0..10).map(|i| i as f64).collect();
+        let y: Vec<f64> = x.iter().map(|x| 2.0 * x + 1.0).collect();
+
+        let fit = ScalingFit::find(&x, &y).unwrap();
+
+        assert!((fit.slope - 2.0).abs() < 1e-12);
+        assert!((fit.intercept - 1.0).abs() < 1e-12);
+        assert!(fit.standard_error < 1e-9);
+    }
+
+    #[test]
+    fn test_dimensions_of_line_and_square() {
+        let line_estimate = correlation_dimension(&line(1000), 20).unwrap();
+
+        assert!((line_estimate.dimension() - 1.0).abs() < 0.1, "{:?}", line_estimate);
+
+        let square_estimate = correlation_dimension(&square(50), 20).unwrap();
+
+        assert!((square_estimate.dimension() - 2.0).abs() < 0.2, "{:?}", square_estimate);
+
+        let line_boxes = box_counting_dimension(&line(4096), 10).unwrap();
+
+        assert!((line_boxes.dimension() - 1.0).abs() < 0.05, "{:?}", line_boxes);
+        assert!(line_boxes.error() < 0.05);
+
+        let square_boxes = box_counting_dimension(&square(128), 10).unwrap();
+
+        assert!((square_boxes.dimension() - 2.0).abs() < 0.1, "{:?}", square_boxes);
+
+        // Scales beyond the resolution of the phase are ignored
+        let finest_boxes = box_counting_dimension(&line(4096), 100).unwrap();
+
+        assert_eq!(finest_boxes.log_scales, box_counting_dimension(&line(4096), MAXIMUM_BOX_SCALES).unwrap().log_scales);
+    }
+
+    #[test]
+    fn test_chaotic_attractor_is_fractal() {
+        let parameters = Parameters::new(2.8, 0.0, 0.8, 100).unwrap();
+
+        let result = ImpactMap::new(parameters).iterate_from_point(0.0, 0.0, 3000);
+
+        let points: Vec<SimpleImpact> = result.trajectory().iter().skip(1000).map(|impact| impact.get_simple_impact()).collect();
+
+        let correlation = correlation_dimension(&points, 12).unwrap();
+        let boxes = box_counting_dimension(&points, 12).unwrap();
+
+        for estimate in [correlation, boxes].iter() {
+            assert!(1.0 < estimate.dimension() && estimate.dimension() < 2.0, "{:?}", estimate);
+            assert!(estimate.error() < 0.1);
+        }
+    }
+
+    #[test]
+    fn test_periodic_orbit_has_dimension_zero() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let result = ImpactMap::new(parameters).iterate_from_point(0.0, 0.0, 1500);
+
+        let points: Vec<SimpleImpact> = result.trajectory().iter().skip(1000).map(|impact| impact.get_simple_impact()).collect();
+
+        let estimate = box_counting_dimension(&points, 12).unwrap();
+
+        assert!(estimate.dimension().abs() < 0.1, "{:?}", estimate);
+    }
+}
@@ -40,6 +40,21 @@ impl PhaseConverter {
         scaled_time - scaled_time.floor()
     }
 
+    /// Splits a simulation time into the number of whole forcing periods before it and the phase within the
+    /// period in which it falls
+    pub fn periods_and_phase(&self, sim_time: Time) -> (i64, Phase) {
+        let scaled_time = sim_time / self.period;
+
+        let periods = scaled_time.floor();
+
+        (periods as i64, scaled_time - periods)
+    }
+
+    /// The simulation time at `time` into the forcing period which starts after `periods` whole periods
+    pub fn time_after_periods(&self, periods: i64, time: Time) -> Time {
+        (periods as f64) * self.period + time
+    }
+
     pub fn time_into_cycle(&self, phase: Phase) -> Time {
         phase * self.period
     }
@@ -69,6 +84,23 @@ mod tests {
     use super::*;
     use float_eq::assert_float_eq;
 
+    #[test]
+    fn test_periods_and_phase() {
+        let converter = PhaseConverter::new(2.0).unwrap();
+
+        let (periods, phase) = converter.periods_and_phase(3.25 * converter.period());
+
+        assert_eq!(periods, 3);
+        assert_float_eq!(phase, 0.25, abs <= 1e-12);
+
+        let (periods, phase) = converter.periods_and_phase(-0.25 * converter.period());
+
+        assert_eq!(periods, -1);
+        assert_float_eq!(phase, 0.75, abs <= 1e-12);
+
+        assert_float_eq!(converter.time_after_periods(3, converter.time_into_cycle(0.25)), 3.25 * converter.period(), abs <= 1e-12);
+    }
+
     #[test]
     fn zero_frequency_returns_error() {
         assert_eq!(PhaseConverter::new(0.0).unwrap_err(), ParameterError::ZeroForcingFrequency);
@@ -0,0 +1,700 @@
+//
+// Grazing bifurcations of periodic orbits: locating the critical parameter value, and the square-root normal form
+// of the impact map about the grazing orbit
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::linearisation::Jacobian as Jacobian;
+use super::model_types::Phase as Phase;
+use super::model_types::Velocity as Velocity;
+use super::linearisation::phase_difference as phase_difference;
+use super::linearisation::impact_distance as impact_distance;
+use super::parameters::Parameters as Parameters;
+use super::parameters::ParameterName as ParameterName;
+use super::periodic_orbit::OrbitSolver as OrbitSolver;
+use super::periodic_orbit::PeriodicOrbit as PeriodicOrbit;
+use super::continuation::Continuation as Continuation;
+use super::continuation::ContinuationSettings as ContinuationSettings;
+use super::continuation::Termination as Termination;
+use std::fmt;
+
+/// How a periodic orbit grazes
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GrazingKind {
+    /// One of the impacts of the orbit slows to zero velocity
+    ImpactVelocity,
+    /// The motion between two impacts of the orbit rises to touch the obstacle without hitting it
+    Clearance
+}
+
+impl fmt::Display for GrazingKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GrazingKind::ImpactVelocity => write!(f, "impact velocity"),
+            GrazingKind::Clearance => write!(f, "clearance")
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GrazingError {
+    NoGrazing {termination: Termination},
+    NotLocated {margin: f64},
+    NoGrazingImpact,
+    InvalidParameters
+}
+
+impl fmt::Display for GrazingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GrazingError::NoGrazing{ref termination} => write!(f, "The orbit does not graze in the parameter range: continuation stopped because of {}", termination),
+            GrazingError::NotLocated{ref margin} => write!(f, "The grazing parameter could not be located: the closest orbit found is still {:e} from grazing", margin),
+            GrazingError::NoGrazingImpact => write!(f, "Perturbing the grazing orbit into the obstacle failed to produce a grazing impact"),
+            GrazingError::InvalidParameters => write!(f, "The parameters near grazing are invalid")
+        }
+    }
+}
+
+/// Where a branch of periodic orbits grazes. Close to grazing the map is singular, so the orbit itself cannot
+/// always be found right up to the critical parameter value, which is extrapolated from the closest orbits which
+/// can be found.
+#[derive(Debug, Clone)]
+pub struct GrazingPoint {
+    parameter: f64,
+    kind: GrazingKind,
+    orbit: Vec<Impact>,
+    orbit_parameter: f64,
+    index: usize,
+    margin: f64,
+    slope: f64
+}
+
+impl GrazingPoint {
+    /// The critical value of the parameter
+    pub fn parameter(&self) -> f64 {
+        self.parameter
+    }
+
+    pub fn kind(&self) -> GrazingKind {
+        self.kind
+    }
+
+    /// The impacts of one cycle of the closest orbit to grazing which was found
+    pub fn orbit(&self) -> &[Impact] {
+        &self.orbit
+    }
+
+    /// The value of the parameter for `orbit`
+    pub fn orbit_parameter(&self) -> f64 {
+        self.orbit_parameter
+    }
+
+    /// Which impact of the orbit grazes, or is followed by the grazing motion
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// How far `orbit` is from grazing: its slowest impact velocity or its clearance, according to the kind
+    pub fn margin(&self) -> f64 {
+        self.margin
+    }
+
+    /// The rate of change of the margin with the parameter, which is finite at grazing
+    pub fn slope(&self) -> f64 {
+        self.slope
+    }
+}
+
+/// What the one-dimensional square-root map predicts for the attractor just beyond grazing, following Chin, Ott,
+/// Nusse and Grebogi. This depends on the multiplier λ of the orbit before grazing.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GrazingScenario {
+    /// The orbit is unstable before grazing, so there is no attractor to follow through it
+    UnstableOrbit,
+    /// 0 < λ < 1/4: a sequence of periodic orbits whose periods increase by one at each step
+    PeriodAdding,
+    /// 1/4 <= λ < 2/3: periodic windows interspersed with chaos
+    PeriodicWindowsAndChaos,
+    /// 2/3 <= λ < 1: chaos immediately after grazing
+    RobustChaos,
+    /// The multipliers are negative or complex, which the one-dimensional analysis does not cover
+    Unclassified
+}
+
+impl fmt::Display for GrazingScenario {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GrazingScenario::UnstableOrbit => write!(f, "unstable orbit"),
+            GrazingScenario::PeriodAdding => write!(f, "period adding"),
+            GrazingScenario::PeriodicWindowsAndChaos => write!(f, "periodic windows and chaos"),
+            GrazingScenario::RobustChaos => write!(f, "robust chaos"),
+            GrazingScenario::Unclassified => write!(f, "unclassified")
+        }
+    }
+}
+
+/// The impact map about a grazing orbit, to leading order in the deviation x = (phase, velocity) from the orbit
+/// and ε from the critical parameter value. Orbits which miss the obstacle are mapped smoothly,
+///
+/// F(x, ε) = A x + b ε,
+///
+/// while those which would otherwise penetrate it to a depth h(x, ε) = g.x + g_ε ε acquire an extra low velocity
+/// impact, which adds a term c √h. It is this square root which gives grazing bifurcations their character.
+#[derive(Debug, Copy, Clone)]
+pub struct NormalForm {
+    parameter: f64,
+    origin: Impact,
+    smooth: Jacobian,
+    parameter_derivative: (f64, f64),
+    depth_gradient: (f64, f64),
+    depth_parameter_derivative: f64,
+    square_root: (f64, f64)
+}
+
+impl NormalForm {
+    /// The critical value of the parameter
+    pub fn parameter(&self) -> f64 {
+        self.parameter
+    }
+
+    /// The impact of the grazing orbit which is followed by the grazing motion, about which deviations are taken
+    pub fn origin(&self) -> Impact {
+        self.origin
+    }
+
+    /// A
+    pub fn smooth(&self) -> Jacobian {
+        self.smooth
+    }
+
+    /// b
+    pub fn parameter_derivative(&self) -> (f64, f64) {
+        self.parameter_derivative
+    }
+
+    /// g
+    pub fn depth_gradient(&self) -> (f64, f64) {
+        self.depth_gradient
+    }
+
+    /// g_ε
+    pub fn depth_parameter_derivative(&self) -> f64 {
+        self.depth_parameter_derivative
+    }
+
+    /// c
+    pub fn square_root(&self) -> (f64, f64) {
+        self.square_root
+    }
+
+    /// How far a deviation from the grazing orbit would take the motion into the obstacle
+    pub fn depth(&self, deviation: (f64, f64), epsilon: f64) -> f64 {
+        self.depth_gradient.0 * deviation.0 + self.depth_gradient.1 * deviation.1 + self.depth_parameter_derivative * epsilon
+    }
+
+    /// The image of a deviation from the grazing orbit after one cycle of the orbit
+    pub fn apply(&self, deviation: (f64, f64), epsilon: f64) -> (f64, f64) {
+        let smooth = self.smooth.apply(deviation);
+
+        let mut image = (smooth.0 + self.parameter_derivative.0 * epsilon, smooth.1 + self.parameter_derivative.1 * epsilon);
+
+        let depth = self.depth(deviation, epsilon);
+
+        if depth > 0.0 {
+            image.0 += self.square_root.0 * depth.sqrt();
+            image.1 += self.square_root.1 * depth.sqrt();
+        }
+
+        image
+    }
+
+    /// Whether the orbit hits the obstacle beyond grazing when the parameter is increased, rather than decreased.
+    /// This is the sign of the depth of the fixed point (I - A)⁻¹ b ε of the smooth part of the map.
+    pub fn penetrates_for_increasing_parameter(&self) -> Option<bool> {
+        let shifted = Jacobian::new([
+            [1.0 - self.smooth.entry(0, 0), -self.smooth.entry(0, 1)],
+            [-self.smooth.entry(1, 0), 1.0 - self.smooth.entry(1, 1)]]);
+
+        let fixed_point = shifted.solve(self.parameter_derivative)?;
+
+        Some(self.depth(fixed_point, 1.0) > 0.0)
+    }
+
+    pub fn scenario(&self) -> GrazingScenario {
+        use super::linearisation::Eigenvalues;
+
+        let multipliers = self.smooth.eigenvalues();
+
+        if multipliers.spectral_radius() >= 1.0 {
+            return GrazingScenario::UnstableOrbit;
+        }
+
+        match multipliers {
+            Eigenvalues::Real(larger, _) if larger > 0.0 => {
+                if larger < 0.25 {
+                    GrazingScenario::PeriodAdding
+                } else if larger < 2.0 / 3.0 {
+                    GrazingScenario::PeriodicWindowsAndChaos
+                } else {
+                    GrazingScenario::RobustChaos
+                }
+            },
+            _ => GrazingScenario::Unclassified
+        }
+    }
+}
+
+// The smooth part of the map about a grazing orbit, with the depth of the motion it follows
+type SmoothMap<'a> = dyn Fn(&ImpactMap, Phase, Velocity) -> Option<(Impact, f64)> + 'a;
+
+/// Finds where a branch of (m, n) orbits grazes as one of the parameters is varied, and the normal form there
+pub struct GrazingLocator {
+    parameters: Parameters,
+    parameter: ParameterName,
+    impacts: u32,
+    periods: u32
+}
+
+// An orbit on the branch, with how close it is to grazing, and how and where it grazes
+#[derive(Debug, Clone)]
+struct Estimate {
+    parameter: f64,
+    margin: f64,
+    kind: GrazingKind,
+    orbit: Vec<Impact>,
+    index: usize
+}
+
+impl GrazingLocator {
+    pub fn new(parameters: Parameters, parameter: ParameterName, impacts: u32, periods: u32) -> GrazingLocator {
+        GrazingLocator{parameters, parameter, impacts, periods}
+    }
+
+    /// Continues `orbit`, which is a solution for the starting parameters, towards the parameter value `towards`
+    /// until it grazes, then refines the critical value by the secant method. The margin from grazing varies
+    /// linearly with the parameter, even though the multipliers blow up, so the secant method converges quickly
+    /// for as long as the orbits can be found.
+    pub fn locate(&self, orbit: &PeriodicOrbit, towards: f64) -> Result<GrazingPoint, GrazingError> {
+        const MAXIMUM_ITERATIONS: u32 = 30;
+        const TOLERANCE: f64 = 1e-9;
+
+        let start = self.parameters.value(self.parameter);
+
+        let settings = ContinuationSettings::new((start.min(towards), start.max(towards)));
+
+        let branch = Continuation::new(self.parameters, self.parameter, self.impacts, self.periods, settings).run(orbit, towards > start);
+
+        if branch.termination() != Termination::Grazing || branch.points().len() < 2 {
+            return Err(GrazingError::NoGrazing{termination: branch.termination()});
+        }
+
+        let points = branch.points();
+
+        // Continuation stops at whichever margin falls within its tolerance first
+        let kind = if points[points.len() - 1].slowest_impact() < settings.grazing_velocity {GrazingKind::ImpactVelocity} else {GrazingKind::Clearance};
+
+        let estimate = |point: &super::continuation::BranchPoint| self.evaluate(point.parameter(), (point.orbit()[0].phase(), point.orbit()[0].velocity()), kind)
+            .ok_or(GrazingError::InvalidParameters);
+
+        let mut previous = estimate(&points[points.len() - 2])?;
+        let mut latest = estimate(&points[points.len() - 1])?;
+
+        // The nearest parameter value known to be beyond where the orbit can be found
+        let mut beyond: Option<f64> = None;
+
+        for _ in 0..MAXIMUM_ITERATIONS {
+            if latest.margin < TOLERANCE {
+                break;
+            }
+
+            let mut parameter = secant_root(&previous, &latest);
+
+            if let Some(limit) = beyond {
+                if (parameter - limit) * (parameter - latest.parameter) >= 0.0 {
+                    parameter = 0.5 * (latest.parameter + limit);
+                }
+            }
+
+            if parameter == latest.parameter || !parameter.is_finite() {
+                break;
+            }
+
+            let guess = extrapolate(&previous, &latest, parameter);
+
+            // Newton's method can converge to another branch, so insist on staying close to this one
+            let reach = impact_distance(latest.orbit[0], previous.orbit[0]);
+
+            match self.evaluate(parameter, guess, kind) {
+                Some(next) if next.margin > 0.0 && phase_difference(next.orbit[0].phase(), guess.0).hypot(next.orbit[0].velocity() - guess.1) <= reach => {
+                    previous = latest;
+                    latest = next;
+                },
+                _ => beyond = Some(parameter)
+            }
+        }
+
+        let slope = (latest.margin - previous.margin) / (latest.parameter - previous.parameter);
+
+        let parameter = secant_root(&previous, &latest);
+
+        if !parameter.is_finite() || slope == 0.0 {
+            return Err(GrazingError::NotLocated{margin: latest.margin});
+        }
+
+        Ok(GrazingPoint{
+            parameter,
+            kind: latest.kind,
+            orbit: latest.orbit,
+            orbit_parameter: latest.parameter,
+            index: latest.index,
+            margin: latest.margin,
+            slope
+        })
+    }
+
+    /// The normal form of the map about a grazing orbit.
+    ///
+    /// - When the orbit grazes with its clearance, it is the return map of the orbit, about the impact followed by
+    ///   the grazing motion, and the depth is how far the motion after that impact would reach into the obstacle.
+    /// - When an impact velocity vanishes instead, it is the return map about the grazing impact itself. Its
+    ///   smooth part takes an impact to the point where the motion after the impact before the grazing one comes
+    ///   closest to the obstacle (an impact with zero velocity on the grazing orbit), so the depth is how far that
+    ///   motion would reach into the obstacle. Deviations with positive depth hit the obstacle there, with a
+    ///   velocity and a shift in phase both proportional to the square root of the depth.
+    pub fn normal_form(&self, grazing: &GrazingPoint) -> Result<NormalForm, GrazingError> {
+        // Distance from grazing at which to linearise the smooth part of the map
+        const SMOOTH_MARGIN: f64 = 1e-4;
+        const STEP: f64 = 1e-6;
+        const PENETRATION: f64 = 1e-6;
+        // How far the time of closest approach to the obstacle is looked for either side of the grazing time
+        const REACH: f64 = 0.1;
+        const MAXIMUM_ITERATIONS: u32 = 10;
+        const TOLERANCE: f64 = 1e-12;
+
+        let closest = Estimate{
+            parameter: grazing.orbit_parameter,
+            margin: grazing.margin,
+            kind: grazing.kind,
+            orbit: grazing.orbit[grazing.index..].iter().chain(grazing.orbit[..grazing.index].iter()).copied().collect(),
+            index: 0
+        };
+
+        let critical = self.mapper(grazing.parameter).ok_or(GrazingError::InvalidParameters)?;
+
+        // The smooth part of the map from an impact, with how far the motion reaches into the obstacle. With
+        // clearance grazing the obstacle is met again after the grazing motion, which adds an impact.
+        let (smooth_map, penetrating_impacts): (Box<SmoothMap>, u32) = match grazing.kind {
+            GrazingKind::Clearance => (Box::new(|mapper: &ImpactMap, phase: Phase, velocity: Velocity| {
+                let impact = mapper.impact_from_point(phase, velocity);
+
+                Some((mapper.apply_repeatedly(impact, self.impacts)?, -mapper.clearance(impact)?))
+            }), self.impacts + 1),
+            GrazingKind::ImpactVelocity => (Box::new(|mapper: &ImpactMap, phase: Phase, velocity: Velocity| {
+                let impact = mapper.impact_from_point(phase, velocity);
+
+                let previous = mapper.apply_repeatedly(impact, self.impacts - 1)?;
+
+                let cycle = self.periods as f64 * mapper.converter().period();
+
+                let (peak, displacement) = mapper.peak_after(previous, cycle - previous.time_since(impact), REACH)?;
+
+                Some((peak, displacement - mapper.parameters().obstacle_offset()))
+            }), self.impacts)
+        };
+
+        let evaluate = |mapper: &ImpactMap, phase: Phase, velocity: Velocity| smooth_map(mapper, phase, velocity).ok_or(GrazingError::InvalidParameters);
+
+        // Where to linearise the smooth part of the map, and the grazing impact at the critical parameter
+        let (linearised_at, base, origin) = match grazing.kind {
+            GrazingKind::Clearance => {
+                // Before grazing, far enough that finite differences don't reach the obstacle
+                let before = grazing.parameter + SMOOTH_MARGIN / grazing.slope;
+
+                let smooth_orbit = self.evaluate(before, (closest.orbit[0].phase(), closest.orbit[0].velocity()), grazing.kind).ok_or(GrazingError::InvalidParameters)?;
+
+                let origin_point = extrapolate(&smooth_orbit, &closest, grazing.parameter);
+
+                (before, smooth_orbit.orbit[0], critical.impact_from_point(origin_point.0, origin_point.1))
+            },
+            GrazingKind::ImpactVelocity => {
+                // The smooth part goes through grazing unchanged, so it can be linearised at the grazing orbit
+                // itself, which is the impact with zero velocity whose phase it leaves fixed
+                let mut phase = closest.orbit[0].phase();
+
+                let residual = |phase: Phase| evaluate(&critical, phase, 0.0).map(|(peak, _)| phase_difference(peak.phase(), phase));
+
+                for _ in 0..MAXIMUM_ITERATIONS {
+                    let slope = (residual(phase + STEP)? - residual(phase - STEP)?) / (2.0 * STEP);
+
+                    let correction = residual(phase)? / slope;
+
+                    if !correction.is_finite() {
+                        return Err(GrazingError::InvalidParameters);
+                    }
+
+                    phase -= correction;
+
+                    if correction.abs() < TOLERANCE {
+                        break;
+                    }
+                }
+
+                let origin = critical.impact_from_point(phase, 0.0);
+
+                (grazing.parameter, origin, origin)
+            }
+        };
+
+        let mapper = self.mapper(linearised_at).ok_or(GrazingError::InvalidParameters)?;
+
+        let (lower, upper) = (self.mapper(linearised_at - STEP).ok_or(GrazingError::InvalidParameters)?, self.mapper(linearised_at + STEP).ok_or(GrazingError::InvalidParameters)?);
+
+        let (phase_plus, phase_minus) = (evaluate(&mapper, base.phase() + STEP, base.velocity())?, evaluate(&mapper, base.phase() - STEP, base.velocity())?);
+        let (velocity_plus, velocity_minus) = (evaluate(&mapper, base.phase(), base.velocity() + STEP)?, evaluate(&mapper, base.phase(), base.velocity() - STEP)?);
+        let (parameter_plus, parameter_minus) = (evaluate(&upper, base.phase(), base.velocity())?, evaluate(&lower, base.phase(), base.velocity())?);
+
+        let difference = |(plus, plus_depth): (Impact, f64), (minus, minus_depth): (Impact, f64)| (
+            phase_difference(plus.phase(), minus.phase()) / (2.0 * STEP),
+            (plus.velocity() - minus.velocity()) / (2.0 * STEP),
+            (plus_depth - minus_depth) / (2.0 * STEP));
+
+        let (by_phase, by_velocity, by_parameter) = (difference(phase_plus, phase_minus), difference(velocity_plus, velocity_minus), difference(parameter_plus, parameter_minus));
+
+        let smooth = Jacobian::new([[by_phase.0, by_velocity.0], [by_phase.1, by_velocity.1]]);
+
+        let parameter_derivative = (by_parameter.0, by_parameter.1);
+
+        let depth_gradient = (by_phase.2, by_velocity.2);
+
+        let depth_parameter_derivative = by_parameter.2;
+
+        // The square root term is what remains of the image of a penetrating orbit after the smooth part is removed
+        let deviation = match grazing.kind {
+            GrazingKind::Clearance => {
+                let gradient_squared = depth_gradient.0 * depth_gradient.0 + depth_gradient.1 * depth_gradient.1;
+
+                (PENETRATION * depth_gradient.0 / gradient_squared, PENETRATION * depth_gradient.1 / gradient_squared)
+            },
+            // Moving the phase alone keeps the impact velocity from going negative
+            GrazingKind::ImpactVelocity => (PENETRATION / depth_gradient.0, 0.0)
+        };
+
+        if !deviation.0.is_finite() {
+            return Err(GrazingError::InvalidParameters);
+        }
+
+        let perturbed = critical.impact_from_point(origin.phase() + deviation.0, origin.velocity() + deviation.1);
+
+        let image = critical.apply_repeatedly(perturbed, penetrating_impacts).ok_or(GrazingError::NoGrazingImpact)?;
+
+        let period = critical.converter().period();
+
+        if (image.time_since(perturbed) / period - self.periods as f64).abs() > 0.25 {
+            return Err(GrazingError::NoGrazingImpact);
+        }
+
+        let predicted = smooth.apply(deviation);
+
+        let square_root = (
+            (phase_difference(image.phase(), origin.phase()) - predicted.0) / PENETRATION.sqrt(),
+            (image.velocity() - origin.velocity() - predicted.1) / PENETRATION.sqrt());
+
+        Ok(NormalForm{
+            parameter: grazing.parameter,
+            origin,
+            smooth,
+            parameter_derivative,
+            depth_gradient,
+            depth_parameter_derivative,
+            square_root
+        })
+    }
+
+    fn mapper(&self, parameter: f64) -> Option<ImpactMap> {
+        Some(ImpactMap::new(self.parameters.with_value(self.parameter, parameter).ok()?))
+    }
+
+    // Solves for the orbit at `parameter`, which is returned starting from the solution nearest `guess`, with its
+    // margin from grazing of the given kind
+    fn evaluate(&self, parameter: f64, guess: (Phase, Velocity), kind: GrazingKind) -> Option<Estimate> {
+        let mapper = self.mapper(parameter)?;
+
+        let orbit = OrbitSolver::default(&mapper).solve(mapper.impact_from_point(guess.0, guess.1), self.impacts, self.periods).ok()?;
+
+        let points = orbit.points().to_vec();
+
+        let (index, margin) = match kind {
+            GrazingKind::ImpactVelocity => points.iter().enumerate()
+                .map(|(index, impact)| (index, impact.velocity()))
+                .min_by(|x, y| x.1.total_cmp(&y.1))?,
+            GrazingKind::Clearance => points.iter().enumerate()
+                .filter_map(|(index, &impact)| mapper.clearance(impact).map(|clearance| (index, clearance)))
+                .min_by(|x, y| x.1.total_cmp(&y.1))
+                .unwrap_or((0, f64::INFINITY))
+        };
+
+        Some(Estimate{parameter, margin, kind, orbit: points, index})
+    }
+}
+
+// Where the margin from grazing vanishes, by linear extrapolation
+fn secant_root(previous: &Estimate, latest: &Estimate) -> f64 {
+    latest.parameter - latest.margin * (latest.parameter - previous.parameter) / (latest.margin - previous.margin)
+}
+
+// Linear extrapolation of the orbit along the branch, which is a much better guess near grazing than the last
+// orbit found, because the map has a square root singularity there
+fn extrapolate(previous: &Estimate, latest: &Estimate, parameter: f64) -> (Phase, Velocity) {
+    let fraction = (parameter - latest.parameter) / (latest.parameter - previous.parameter);
+
+    let (from, to) = (previous.orbit[0], latest.orbit[0]);
+
+    (to.phase() + fraction * phase_difference(to.phase(), from.phase()), to.velocity() + fraction * (to.velocity() - from.velocity()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::linearisation::Eigenvalues;
+    use float_eq::assert_float_eq;
+
+    fn orbit(parameters: Parameters, impacts: u32, periods: u32, guess: (f64, f64)) -> PeriodicOrbit {
+        let mapper = ImpactMap::new(parameters);
+
+        OrbitSolver::default(&mapper).solve(mapper.impact_from_point(guess.0, guess.1), impacts, periods).unwrap()
+    }
+
+    #[test]
+    fn test_locates_impact_velocity_grazing() {
+        // Raising the obstacle slows the impacts of the (1, 1) orbit until it grazes
+        let parameters = Parameters::new(2.6, 0.0, 0.8, 100).unwrap();
+
+        let locator = GrazingLocator::new(parameters, ParameterName::Offset, 1, 1);
+
+        let grazing = locator.locate(&orbit(parameters, 1, 1, (0.4821, 0.5054)), 1.0).unwrap();
+
+        assert_eq!(grazing.kind(), GrazingKind::ImpactVelocity);
+        assert!(grazing.margin() < 0.01);
+        assert!(0.17 < grazing.parameter() && grazing.parameter() < 0.18);
+        assert!(grazing.orbit_parameter() < grazing.parameter());
+        assert!(grazing.slope() < 0.0);
+
+        // The margin vanishes linearly
+        assert_float_eq!(grazing.margin(), grazing.slope() * (grazing.orbit_parameter() - grazing.parameter()), rmax <= 1e-6);
+
+        let normal_form = locator.normal_form(&grazing).unwrap();
+
+        // Every image of the smooth part is a zero velocity impact, so one multiplier vanishes
+        match normal_form.smooth().eigenvalues() {
+            Eigenvalues::Real(larger, smaller) => {
+                assert!(smaller.abs() < 1e-6 || larger.abs() < 1e-6);
+                assert!(larger.max(smaller).abs() < 1.0);
+            },
+            eigenvalues => panic!("Unexpected multipliers {:?}", eigenvalues)
+        }
+
+        assert_float_eq!(normal_form.origin().velocity(), 0.0, abs <= 1e-12);
+
+        // The motion reaches the obstacle as it is lowered
+        assert_eq!(normal_form.penetrates_for_increasing_parameter(), Some(false));
+
+        // An orbit which would have turned back just beyond the obstacle picks up a low velocity impact instead
+        let gradient = normal_form.depth_gradient();
+        let gradient_squared = gradient.0 * gradient.0 + gradient.1 * gradient.1;
+
+        let deviation = (4e-6 * gradient.0 / gradient_squared - 1e-5 * gradient.1, 4e-6 * gradient.1 / gradient_squared + 1e-5 * gradient.0);
+
+        assert!(deviation.1 > 0.0);
+
+        let depth = normal_form.depth(deviation, 0.0);
+
+        assert!(depth > 0.0);
+
+        let mapper = ImpactMap::new(parameters.with_value(ParameterName::Offset, normal_form.parameter()).unwrap());
+
+        let origin = normal_form.origin();
+
+        let image = mapper.apply_repeatedly(mapper.impact_from_point(origin.phase() + deviation.0, origin.velocity() + deviation.1), 1).unwrap();
+
+        let predicted = normal_form.apply(deviation, 0.0);
+
+        let square_root = normal_form.square_root();
+        let scale = square_root.0.hypot(square_root.1) * depth.sqrt();
+
+        assert!(scale > 0.0);
+        assert!((phase_difference(image.phase(), origin.phase()) - predicted.0).abs() < 0.05 * scale);
+        assert!((image.velocity() - origin.velocity() - predicted.1).abs() < 0.05 * scale);
+    }
+
+    #[test]
+    fn test_normal_form_predicts_penetrating_orbits() {
+        // The unstable (1, 2) orbit, beyond the fold in the branch of the stable one for σ = -0.1, grazes with
+        // the motion between impacts as the obstacle is raised
+        let parameters = Parameters::new(4.85, -0.12, 0.8, 100).unwrap();
+
+        let locator = GrazingLocator::new(parameters, ParameterName::Offset, 1, 2);
+
+        let grazing = locator.locate(&orbit(parameters, 1, 2, (0.0219, 0.295)), 0.0).unwrap();
+
+        assert_eq!(grazing.kind(), GrazingKind::Clearance);
+        assert!(-0.1 < grazing.parameter() && grazing.parameter() < -0.09);
+
+        let normal_form = locator.normal_form(&grazing).unwrap();
+
+        assert_eq!(normal_form.scenario(), GrazingScenario::UnstableOrbit);
+
+        // The margin shrinks as the obstacle is raised
+        assert_eq!(normal_form.penetrates_for_increasing_parameter(), Some(true));
+
+        // A different deviation into the obstacle from the one used to find the square root term
+        let gradient = normal_form.depth_gradient();
+        let gradient_squared = gradient.0 * gradient.0 + gradient.1 * gradient.1;
+
+        let deviation = (4e-6 * gradient.0 / gradient_squared - 1e-5 * gradient.1, 4e-6 * gradient.1 / gradient_squared + 1e-5 * gradient.0);
+
+        let depth = normal_form.depth(deviation, 0.0);
+
+        assert!(depth > 0.0);
+
+        let mapper = ImpactMap::new(parameters.with_value(ParameterName::Offset, normal_form.parameter()).unwrap());
+
+        let origin = normal_form.origin();
+
+        let image = mapper.apply_repeatedly(mapper.impact_from_point(origin.phase() + deviation.0, origin.velocity() + deviation.1), 2).unwrap();
+
+        let predicted = normal_form.apply(deviation, 0.0);
+
+        let square_root = normal_form.square_root();
+        let scale = square_root.0.hypot(square_root.1) * depth.sqrt();
+
+        assert!(scale > 0.0);
+        assert!((phase_difference(image.phase(), origin.phase()) - predicted.0).abs() < 0.05 * scale);
+        assert!((image.velocity() - origin.velocity() - predicted.1).abs() < 0.05 * scale);
+    }
+
+    #[test]
+    fn test_grazing_scenarios() {
+        let normal_form = |entries: [[f64; 2]; 2]| NormalForm{
+            parameter: 0.0,
+            origin: ImpactMap::new(Parameters::new(2.8, 0.0, 0.8, 100).unwrap()).impact_from_point(0.0, 1.0),
+            smooth: Jacobian::new(entries),
+            parameter_derivative: (0.0, 1.0),
+            depth_gradient: (0.0, 1.0),
+            depth_parameter_derivative: 1.0,
+            square_root: (0.0, -1.0)
+        };
+
+        assert_eq!(normal_form([[0.2, 0.0], [0.0, 0.1]]).scenario(), GrazingScenario::PeriodAdding);
+        assert_eq!(normal_form([[0.5, 0.0], [0.0, 0.1]]).scenario(), GrazingScenario::PeriodicWindowsAndChaos);
+        assert_eq!(normal_form([[0.9, 0.0], [0.0, 0.1]]).scenario(), GrazingScenario::RobustChaos);
+        assert_eq!(normal_form([[-0.5, 0.0], [0.0, 0.1]]).scenario(), GrazingScenario::Unclassified);
+        assert_eq!(normal_form([[1.5, 0.0], [0.0, 0.1]]).scenario(), GrazingScenario::UnstableOrbit);
+        assert!(matches!(normal_form([[0.0, -0.5], [0.5, 0.0]]).smooth().eigenvalues(), Eigenvalues::ComplexPair{..}));
+        assert_eq!(normal_form([[0.0, -0.5], [0.5, 0.0]]).scenario(), GrazingScenario::Unclassified);
+
+        // Only deviations into the obstacle pick up the square root term
+        assert_eq!(normal_form([[0.5, 0.0], [0.0, 0.5]]).apply((0.0, -0.04), 0.0), (0.0, -0.02));
+        assert_eq!(normal_form([[0.5, 0.0], [0.0, 0.5]]).apply((0.0, 0.04), 0.0), (0.0, 0.02 - 0.2));
+    }
+}
+
@@ -0,0 +1,314 @@
+//
+// Bifurcation diagrams which carry the state of the system from one parameter value to the next, as on a shaker
+//
+use super::classification::OrbitClassifier as OrbitClassifier;
+use super::classification::OrbitType as OrbitType;
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::impact_map::IterationContext as IterationContext;
+use super::impact_map::IterationControl as IterationControl;
+use super::impact_map::TrajectorySampling as TrajectorySampling;
+use super::model_types::Phase as Phase;
+use super::model_types::Velocity as Velocity;
+use super::orbit_chart::ChartAxis as ChartAxis;
+use super::parameters::Parameters as Parameters;
+use super::parameters::ParameterName as ParameterName;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SweepDirection {
+    Up,
+    Down
+}
+
+impl fmt::Display for SweepDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SweepDirection::Up => write!(f, "up"),
+            SweepDirection::Down => write!(f, "down")
+        }
+    }
+}
+
+/// The impacts recorded at one parameter value of a sweep, after the transient has been discarded. Parameter
+/// values which are not valid (e.g. a coefficient of restitution greater than one) are passed over with no
+/// impacts and no orbit type, and the state is carried across them unchanged.
+#[derive(Debug, Clone)]
+pub struct SweepStep {
+    parameter: f64,
+    impacts: Vec<Impact>,
+    orbit_type: Option<OrbitType>
+}
+
+impl SweepStep {
+    pub fn parameter(&self) -> f64 {
+        self.parameter
+    }
+
+    pub fn impacts(&self) -> &[Impact] {
+        &self.impacts
+    }
+
+    pub fn orbit_type(&self) -> Option<OrbitType> {
+        self.orbit_type
+    }
+}
+
+/// A change in the type of orbit between consecutive steps of a sweep
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Jump {
+    pub from_parameter: f64,
+    pub to_parameter: f64,
+    pub from: OrbitType,
+    pub to: OrbitType
+}
+
+/// One branch of a bifurcation diagram, with steps in the order in which they were visited
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    parameter: ParameterName,
+    direction: SweepDirection,
+    steps: Vec<SweepStep>,
+    final_point: (Phase, Velocity)
+}
+
+impl Sweep {
+    pub fn parameter(&self) -> ParameterName {
+        self.parameter
+    }
+
+    pub fn direction(&self) -> SweepDirection {
+        self.direction
+    }
+
+    pub fn steps(&self) -> &[SweepStep] {
+        &self.steps
+    }
+
+    /// The phase and velocity of the last impact of the sweep, from which a sweep in the opposite direction starts
+    pub fn final_point(&self) -> (Phase, Velocity) {
+        self.final_point
+    }
+
+    /// Every change of orbit type between consecutive valid steps. Non-impacting orbits of different amplitudes
+    /// are treated as the same type.
+    pub fn jumps(&self) -> Vec<Jump> {
+        let classified: Vec<(f64, OrbitType)> = self.steps.iter()
+            .filter_map(|step| step.orbit_type.map(|orbit_type| (step.parameter, orbit_type)))
+            .collect();
+
+        classified.windows(2)
+            .filter(|pair| !same_type(pair[0].1, pair[1].1))
+            .map(|pair| Jump{from_parameter: pair[0].0, to_parameter: pair[1].0, from: pair[0].1, to: pair[1].1})
+            .collect()
+    }
+}
+
+/// An up-sweep followed by a down-sweep which starts from where the up-sweep finished. Where the two branches
+/// settle on different types of orbit at the same parameter value, the attractors coexist and the system
+/// shows hysteresis.
+#[derive(Debug, Clone)]
+pub struct HysteresisSweep {
+    up: Sweep,
+    down: Sweep
+}
+
+impl HysteresisSweep {
+    pub fn up(&self) -> &Sweep {
+        &self.up
+    }
+
+    pub fn down(&self) -> &Sweep {
+        &self.down
+    }
+
+    /// Parameter values at which the two branches have settled on different types of orbit
+    pub fn coexisting(&self) -> Vec<(f64, OrbitType, OrbitType)> {
+        self.up.steps.iter().zip(self.down.steps.iter().rev())
+            .filter_map(|(up, down)| match (up.orbit_type, down.orbit_type) {
+                (Some(u), Some(d)) if !same_type(u, d) => Some((up.parameter, u, d)),
+                _ => None
+            })
+            .collect()
+    }
+}
+
+fn same_type(first: OrbitType, second: OrbitType) -> bool {
+    match (first, second) {
+        (OrbitType::NonImpacting{..}, OrbitType::NonImpacting{..}) => true,
+        _ => first == second
+    }
+}
+
+/// Sweeps one parameter over a range of values. At each value the map is iterated for `transient` impacts,
+/// which are discarded, and then `recorded` impacts are kept. The final impact at each value, by phase and
+/// velocity, is the initial impact at the next, so that the system stays on whichever attractor it has found
+/// until that attractor is destroyed. `recorded` should be positive: as for `TrajectorySampling`, a limit of zero
+/// records every impact after the transient.
+pub struct ParameterSweep {
+    parameters: Parameters,
+    axis: ChartAxis,
+    transient: u32,
+    recorded: u32
+}
+
+impl ParameterSweep {
+    pub fn new(parameters: Parameters, axis: ChartAxis, transient: u32, recorded: u32) -> ParameterSweep {
+        ParameterSweep{parameters, axis, transient, recorded}
+    }
+
+    /// The sweep, or `None` if `cancellation` is set before it has finished
+    pub fn sweep(&self, direction: SweepDirection, initial_point: (Phase, Velocity), cancellation: &AtomicBool) -> Option<Sweep> {
+        let mut values = self.axis.values();
+
+        if direction == SweepDirection::Down {
+            values.reverse();
+        }
+
+        let mut point = initial_point;
+
+        let mut steps = Vec::with_capacity(values.len());
+
+        for value in values {
+            if cancellation.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            steps.push(match self.parameters.with_value(self.axis.parameter(), value) {
+                Ok(parameters) => {
+                    let (step, final_impact) = self.step(parameters, value, point, cancellation);
+
+                    point = (final_impact.phase(), final_impact.velocity());
+
+                    step
+                },
+                Err(_) => SweepStep{parameter: value, impacts: vec![], orbit_type: None}
+            });
+        }
+
+        // The last step may have been cut short
+        if cancellation.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        Some(Sweep{parameter: self.axis.parameter(), direction, steps, final_point: point})
+    }
+
+    /// Sweeps up from `initial_point` and then back down again, or gives `None` if `cancellation` is set first
+    pub fn hysteresis(&self, initial_point: (Phase, Velocity), cancellation: &AtomicBool) -> Option<HysteresisSweep> {
+        let up = self.sweep(SweepDirection::Up, initial_point, cancellation)?;
+
+        let down = self.sweep(SweepDirection::Down, up.final_point(), cancellation)?;
+
+        Some(HysteresisSweep{up, down})
+    }
+
+    fn step(&self, parameters: Parameters, value: f64, point: (Phase, Velocity), cancellation: &AtomicBool) -> (SweepStep, Impact) {
+        let mapper = ImpactMap::new(parameters);
+
+        let mut control = IterationControl::new()
+            .with_sampling(TrajectorySampling::new(self.transient + 1, 1, self.recorded))
+            .with_cancellation(cancellation);
+
+        let result = mapper.iterate_with_control(&mut IterationContext::new(), &mut control, mapper.impact_from_point(point.0, point.1), self.transient + self.recorded);
+
+        let orbit_type = OrbitClassifier::default(parameters).classify(&result);
+
+        (SweepStep{parameter: value, impacts: result.trajectory().clone(), orbit_type: Some(orbit_type)}, result.final_impact())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_is_carried_between_steps() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let axis = ChartAxis::new(ParameterName::Offset, 0.0, 0.01, 2);
+
+        // A short transient, so that where each step starts still shows in what it records
+        let sweep = ParameterSweep::new(parameters, axis, 5, 10).sweep(SweepDirection::Up, (0.0, 0.0), &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(sweep.steps().len(), 2);
+        assert_eq!(sweep.steps()[0].impacts().len(), 10);
+
+        // The second step starts from the last impact of the first
+        let first = sweep.steps()[0].impacts().last().unwrap();
+
+        let mapper = ImpactMap::new(parameters.with_value(ParameterName::Offset, 0.01).unwrap());
+
+        let record = |point: (Phase, Velocity)| -> Vec<(Phase, Velocity)> {
+            let mut control = IterationControl::new().with_sampling(TrajectorySampling::new(6, 1, 10));
+
+            let result = mapper.iterate_with_control(&mut IterationContext::new(), &mut control, mapper.impact_from_point(point.0, point.1), 15);
+
+            result.trajectory().iter().map(|impact| (impact.phase(), impact.velocity())).collect()
+        };
+
+        let second: Vec<(Phase, Velocity)> = sweep.steps()[1].impacts().iter().map(|impact| (impact.phase(), impact.velocity())).collect();
+
+        assert_eq!(second, record((first.phase(), first.velocity())));
+        assert_ne!(second, record((0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_down_sweep_visits_values_in_reverse() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let axis = ChartAxis::new(ParameterName::Frequency, 2.0, 4.85, 4);
+
+        let hysteresis = ParameterSweep::new(parameters, axis, 1000, 20).hysteresis((0.0, 0.0), &AtomicBool::new(false)).unwrap();
+
+        let up: Vec<f64> = hysteresis.up().steps().iter().map(|step| step.parameter()).collect();
+        let down: Vec<f64> = hysteresis.down().steps().iter().map(|step| step.parameter()).rev().collect();
+
+        assert_eq!(up, down);
+        assert_eq!(hysteresis.down().direction(), SweepDirection::Down);
+
+        // The (1, 1) orbit at the start period-doubles on the way up
+        let jumps = hysteresis.up().jumps();
+
+        assert_eq!(jumps[0].from, OrbitType::Periodic{impacts: 1, periods: 1});
+    }
+
+    #[test]
+    fn test_coexisting_orbits_show_hysteresis() {
+        let parameters = Parameters::new(3.0, 0.0, 0.8, 100).unwrap();
+
+        let axis = ChartAxis::new(ParameterName::Offset, 0.08, 0.14, 10);
+
+        let hysteresis = ParameterSweep::new(parameters, axis, 1000, 40).hysteresis((0.0, 0.0), &AtomicBool::new(false)).unwrap();
+
+        // The up-sweep stays on a (3, 4) orbit where the down-sweep has found a (2, 4) orbit
+        let coexisting = hysteresis.coexisting();
+
+        assert!(coexisting.iter().any(|&(offset, up, down)| (offset - 0.11).abs() < 0.01
+            && up == OrbitType::Periodic{impacts: 3, periods: 4}
+            && down == OrbitType::Periodic{impacts: 2, periods: 4}));
+    }
+
+    #[test]
+    fn test_invalid_values_are_passed_over() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let axis = ChartAxis::new(ParameterName::CoefficientOfRestitution, 0.8, 1.2, 3);
+
+        let sweep = ParameterSweep::new(parameters, axis, 100, 10).sweep(SweepDirection::Up, (0.0, 0.0), &AtomicBool::new(false)).unwrap();
+
+        assert!(sweep.steps()[0].orbit_type().is_some());
+        assert!(sweep.steps()[2].orbit_type().is_none());
+        assert!(sweep.steps()[2].impacts().is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_sweep_is_abandoned() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let axis = ChartAxis::new(ParameterName::Frequency, 2.0, 3.0, 3);
+
+        assert!(ParameterSweep::new(parameters, axis, 100, 10).hysteresis((0.0, 0.0), &AtomicBool::new(true)).is_none());
+    }
+}
@@ -11,7 +11,9 @@ use std::fmt::Display;
 /// which the impact occurs
 /// The `velocity` of the impact, which cannot be negative
 ///
-/// In addition, we also record the actual `time` of the impact.
+/// In addition, we also record when the impact occurs, as the number of whole forcing periods
+/// before it together with its `phase`. Keeping the count of periods separate means that the
+/// phase does not lose precision however long a simulation runs.
 ///
 /// Because the `phase` is periodic and the `velocity` non-negative, the surface on which
 /// impacts are defined is a half-cylinder. Whether a zero-velocity impact
@@ -25,6 +27,10 @@ pub struct SimpleImpact {
 }
 
 impl SimpleImpact{
+	pub fn new(phase: Phase, velocity: Velocity) -> SimpleImpact {
+		SimpleImpact{phase, velocity}
+	}
+
 	pub fn phase(&self) -> Phase {
 		self.phase
 	}
@@ -37,7 +43,8 @@ impl SimpleImpact{
 #[derive(Copy, Clone, Debug)]
 pub struct Impact {
 	simple_impact: SimpleImpact,
-	time: Time
+	periods: i64,
+	period: Time
 }
 
 impl Impact {
@@ -56,10 +63,18 @@ impl ImpactGenerator
 	}
 
 	pub fn generate(&self, impact_time: Time, impact_velocity: Velocity) -> Impact  {
-			Impact{time: impact_time, simple_impact: SimpleImpact {
-				phase: self.converter.time_to_phase(impact_time), 
-				velocity: impact_velocity}
-			}
+		self.generate_after_periods(0, impact_time, impact_velocity)
+	}
+
+	// An impact at `impact_time` measured from the start of the forcing period which begins after `periods`
+	// whole periods
+	pub fn generate_after_periods(&self, periods: i64, impact_time: Time, impact_velocity: Velocity) -> Impact {
+		let (extra_periods, phase) = self.converter.periods_and_phase(impact_time);
+
+		Impact{periods: periods + extra_periods, period: self.converter.period(), simple_impact: SimpleImpact {
+			phase, 
+			velocity: impact_velocity}
+		}
 	}
 }
 
@@ -91,7 +106,7 @@ pub fn impact_comparer(tolerance: SimpleImpact) -> Box<ImpactComparer> {
 	})
 }
 
-fn default_impact_comparer() -> Box<ImpactComparer> 
+pub fn default_impact_comparer() -> Box<ImpactComparer> 
 {
 	let tol = 1e-3;
 	impact_comparer(SimpleImpact{phase: tol, velocity: tol})
@@ -106,8 +121,30 @@ impl Impact{
 		self.simple_impact.velocity
 	}
 
+	/// The simulation time of the impact. For very long runs this is less precise than the phase, so
+	/// prefer `time_in_period` and `time_since` for calculations.
 	pub fn time(&self) -> Time {
-		self.time
+		((self.periods as f64) + self.simple_impact.phase) * self.period
+	}
+
+	/// The number of whole forcing periods before the impact
+	pub fn periods(&self) -> i64 {
+		self.periods
+	}
+
+	/// The time of the impact measured from the start of the forcing period in which it occurs
+	pub fn time_in_period(&self) -> Time {
+		self.simple_impact.phase * self.period
+	}
+
+	/// The time elapsed since an `earlier` impact, computed without going through the simulation time
+	pub fn time_since(&self, earlier: Impact) -> Time {
+		((self.periods - earlier.periods) as f64) * self.period + self.time_in_period() - earlier.time_in_period()
+	}
+
+	/// The same impact moved back to the first forcing period
+	pub fn in_first_period(&self) -> Impact {
+		Impact{periods: 0, ..*self}
 	}
 
 	pub fn dual_impact(&self, coefficient_of_restitution: Coefficient) -> Impact {
@@ -116,18 +153,13 @@ impl Impact{
 		// non-zero coefficient of restitution. For the zero-restitution case, all impacts behave like
 		// zero-velocity impacts anyway.
 
-		if coefficient_of_restitution > 0.0 {
-			Impact{
-				simple_impact: SimpleImpact{phase: 1.0 - self.simple_impact.phase, 
-				velocity: self.simple_impact.velocity / coefficient_of_restitution}, 
-				time: -self.time}
-		} else {
-			Impact{
-				simple_impact: SimpleImpact{
-					phase: 1.0 - self.simple_impact.phase, 
-					velocity: 0.0}, 
-					time: -self.time}
-		}
+		// Reversing time takes phase φ after n periods to phase 1 - φ after -(n + 1) periods
+		let velocity = if coefficient_of_restitution > 0.0 {self.simple_impact.velocity / coefficient_of_restitution} else {0.0};
+
+		Impact{
+			simple_impact: SimpleImpact{phase: 1.0 - self.simple_impact.phase, velocity},
+			periods: -self.periods - 1,
+			period: self.period}
 	}
 }
 
@@ -160,6 +192,24 @@ mod tests {
 		check_equal(impact1, impact5, true);
 	}
 
+	#[test]
+	fn test_time_is_kept_as_periods_and_phase() {
+		let converter = PhaseConverter::new(2.0).unwrap();
+
+		let generator = ImpactGenerator::new(converter);
+
+		let impact = generator.generate_after_periods(1_000_000_000, 2.25 * converter.period(), 0.5);
+
+		assert_eq!(impact.periods(), 1_000_000_002);
+		assert!((impact.phase() - 0.25).abs() < 1e-12);
+
+		let later = generator.generate_after_periods(1_000_000_002, 0.75 * converter.period(), 0.5);
+
+		assert!((later.time_since(impact) - 0.5 * converter.period()).abs() < 1e-12);
+		assert_eq!(impact.in_first_period().periods(), 0);
+		assert_eq!(impact.in_first_period().phase(), impact.phase());
+	}
+
 	#[test]
 	fn test_impact_dual() {
 		let converter = PhaseConverter::new(2.0).unwrap();
@@ -179,6 +229,7 @@ mod tests {
 
 				assert_eq!(dual.phase(), 1.0 - impact.phase());
 				assert_eq!(dual.velocity(), self.expected_v);
+				assert!((dual.time() + impact.time()).abs() < 1e-12);
 			}
 		}
 
@@ -1,31 +1,104 @@
 use super::impact::Impact;
 use super::motion::MotionBetweenImpacts as MotionBetweenImpacts;
+use super::motion::StateOfMotion as StateOfMotion;
+use super::model_types::StateError as StateError;
 use super::chatter::ChatterChecker as ChatterChecker;
+use super::chatter::ChatterCount as ChatterCount;
 use super::model_types::Time as Time;
 use super::model_types::Phase as Phase;
 use super::model_types::Velocity as Velocity;
 use super::model_types::Coefficient as Coefficient;
+use super::model_types::Distance as Distance;
 use super::impact::ImpactGenerator as ImpactGenerator;
+use super::impact::default_impact_comparer as default_impact_comparer;
 use super::parameters::Parameters as Parameters;
 use super::forcing_phase::PhaseConverter as PhaseConverter;
+use super::classification::OrbitClassifier as OrbitClassifier;
+use super::classification::OrbitType as OrbitType;
+use super::limiting_cases::RestitutionMode as RestitutionMode;
+use super::limiting_cases::symplectic_error as symplectic_error;
+use super::density::DensityHistogram as DensityHistogram;
 use log::debug;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub struct IterationResult 
 {
 	impacts: Vec<Impact>,
 
-	long_excursions: bool
+	final_impact: Impact,
+
+	long_excursions: bool,
+
+	non_impacting_amplitude: Option<Distance>,
+
+	symplectic_error: Option<f64>,
+
+	cancelled: bool
 }
 
 impl IterationResult {
     pub fn trajectory(&self) -> &Trajectory {&self.impacts}
+    pub fn final_impact(&self) -> Impact {self.final_impact}
     pub fn has_long_excursions(&self) -> bool {self.long_excursions}
+    // Set if the motion left the obstacle for good, to settle on non-impacting harmonic motion of this amplitude
+    pub fn non_impacting_amplitude(&self) -> Option<Distance> {self.non_impacting_amplitude}
+    pub fn was_cancelled(&self) -> bool {self.cancelled}
+    // The largest departure from area preservation over the run, only tracked when r = 1 (see `symplectic_error`)
+    pub fn symplectic_error(&self) -> Option<f64> {self.symplectic_error}
+}
+
+/// Outcome of a simulation started from an arbitrary state of motion rather than from an impact
+pub enum StateIterationResult
+{
+    // The motion reached the obstacle, and the map was iterated from the first impact
+    Impacting(IterationResult),
+
+    // The motion can never reach the obstacle, and its displacement is bounded by `amplitude`
+    NonImpacting {amplitude: Distance},
+
+    // No impact was found within the maximum number of forcing periods, although the motion is not bounded
+    // away from the obstacle
+    NoImpact
+}
+
+/// An impact, or set of impacts, which the map takes to a given impact
+#[derive(Debug, Copy, Clone)]
+pub enum PreImage
+{
+    // A single impact
+    Impact(Impact),
+
+    // When r = 0 impacts at this time map to the same image whatever their velocity
+    AnyVelocity {time: Time},
+
+    // Zero-velocity impacts anywhere in a sticking interval, which all release at the end of the interval
+    Sticking {from: Time, to: Time}
+}
+
+/// Compact outcome of one trajectory in a batch: where it ended up and what kind of orbit it settled on.
+/// The full trajectory is only kept on request.
+pub struct TrajectorySummary
+{
+    final_impact: Impact,
+    orbit_type: OrbitType,
+    long_excursions: bool,
+    trajectory: Option<Trajectory>
+}
+
+impl TrajectorySummary {
+    pub fn final_impact(&self) -> Impact {self.final_impact}
+    pub fn orbit_type(&self) -> OrbitType {self.orbit_type}
+    pub fn has_long_excursions(&self) -> bool {self.long_excursions}
+    pub fn trajectory(&self) -> Option<&Trajectory> {self.trajectory.as_ref()}
 }
 
 pub struct ImpactResult 
 {
 	impact: Impact,
-	found_impact: bool
+	found_impact: bool,
+	// Where the motion was when the search stopped, which is only different from `impact` if no impact was found
+	final_state: StateOfMotion
 }
 
 type Trajectory = Vec<Impact>;
@@ -45,15 +118,149 @@ impl SingularitySetResult {
     }
 }
 
+/// State which belongs to a single trajectory rather than to the map, so that one `ImpactMap` can be
+/// shared (e.g. between threads) by any number of independent iterations.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct IterationContext {
+    chatter_count: ChatterCount
+}
+
+impl IterationContext {
+    pub fn new() -> IterationContext {
+        IterationContext::default()
+    }
+}
+
+/// Which impacts of a trajectory are recorded: the first `skip` impacts are discarded as transient, after
+/// which every `every`-th impact is kept, up to a maximum of `max_points` (zero meaning no limit). The
+/// initial impact counts as the first impact, as does each accumulation impact added for chatter.
+#[derive(Debug, Copy, Clone)]
+pub struct TrajectorySampling {
+    skip: u32,
+    every: u32,
+    max_points: u32,
+    keep: bool
+}
+
+impl TrajectorySampling {
+    pub fn new(skip: u32, every: u32, max_points: u32) -> TrajectorySampling {
+        TrajectorySampling{skip, every: std::cmp::max(1, every), max_points, keep: true}
+    }
+
+    pub fn all() -> TrajectorySampling {
+        TrajectorySampling::new(0, 1, 0)
+    }
+
+    /// Keeps no impacts at all, for runs which are only wanted for their final impact or for what is accumulated
+    /// along the way (e.g. a `DensityHistogram`)
+    pub fn none() -> TrajectorySampling {
+        TrajectorySampling{keep: false, ..TrajectorySampling::all()}
+    }
+
+    fn is_full(&self, trajectory: &[Impact]) -> bool {
+        self.max_points > 0 && trajectory.len() >= self.max_points as usize
+    }
+
+    // Room for everything which could be kept from a run without chatter
+    fn capacity(&self, num_iterations: u32) -> usize {
+        if !self.keep {
+            return 0;
+        }
+
+        let kept = (num_iterations as usize + 1).saturating_sub(self.skip as usize).div_ceil(self.every as usize);
+
+        if self.max_points > 0 {std::cmp::min(kept, self.max_points as usize)} else {kept}
+    }
+
+    fn record(&self, trajectory: &mut Trajectory, index: &mut u64, impact: Impact) {
+        if self.keep && *index >= self.skip as u64 && (*index - self.skip as u64).is_multiple_of(self.every as u64) && !self.is_full(trajectory) {
+            trajectory.push(impact);
+        }
+
+        *index += 1;
+    }
+}
+
+impl Default for TrajectorySampling {
+    fn default() -> TrajectorySampling {
+        TrajectorySampling::all()
+    }
+}
+
+/// Optional hooks for long-running iterations: a callback which is told how many iterations have been
+/// completed every `progress_interval` iterations, and flags which stop the iteration early once any of them is
+/// set. Both may be driven from another thread.
+/// The control also determines which impacts are recorded in the result (see `TrajectorySampling`), and may
+/// add every impact which the run produces to a `DensityHistogram`, whether or not it is recorded. The initial
+/// impact is not added, since the caller already has it: a run resumed from the final impact of an earlier one
+/// then adds each impact just once.
+pub struct IterationControl<'a> {
+    progress: Option<Box<dyn FnMut(u32) + 'a>>,
+    progress_interval: u32,
+    cancellation: Vec<&'a AtomicBool>,
+    sampling: TrajectorySampling,
+    density: Option<&'a mut DensityHistogram>
+}
+
+impl<'a> IterationControl<'a> {
+    pub fn new() -> IterationControl<'a> {
+        IterationControl{progress: None, progress_interval: 0, cancellation: vec![], sampling: TrajectorySampling::all(), density: None}
+    }
+
+    pub fn with_density(self, density: &'a mut DensityHistogram) -> IterationControl<'a> {
+        IterationControl{density: Some(density), ..self}
+    }
+
+    pub fn with_sampling(self, sampling: TrajectorySampling) -> IterationControl<'a> {
+        IterationControl{sampling, ..self}
+    }
+
+    pub fn with_progress<F: FnMut(u32) + 'a>(self, progress_interval: u32, progress: F) -> IterationControl<'a> {
+        IterationControl{progress: Some(Box::new(progress)), progress_interval, ..self}
+    }
+
+    /// Adds a flag to those which stop the iteration
+    pub fn with_cancellation(mut self, cancellation: &'a AtomicBool) -> IterationControl<'a> {
+        self.cancellation.push(cancellation);
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.iter().any(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    fn observe(&mut self, impact: Impact) {
+        if let Some(density) = self.density.as_mut() {
+            density.add(impact);
+        }
+    }
+
+    fn report(&mut self, iterations_done: u32) {
+        if self.progress_interval > 0 && iterations_done.is_multiple_of(self.progress_interval) {
+            if let Some(progress) = self.progress.as_mut() {
+                progress(iterations_done);
+            }
+        }
+    }
+}
+
+impl<'a> Default for IterationControl<'a> {
+    fn default() -> IterationControl<'a> {
+        IterationControl::new()
+    }
+}
+
 pub struct ImpactMap {
 	
 	// Transformation of the impact surface (an infinite half cylinder parametrised by phase and velocity)
-	// which maps impacts to impacts
+	// which maps impacts to impacts. The map itself is immutable - anything which changes along a trajectory
+	// lives in an `IterationContext`.
 		
 	motion: MotionBetweenImpacts,
 	chatter_checker: ChatterChecker,
 	generator: ImpactGenerator,
-    coefficient_of_restitution: Coefficient
+    coefficient_of_restitution: Coefficient,
+    mode: RestitutionMode
 }
 
 impl ImpactMap {
@@ -62,56 +269,293 @@ impl ImpactMap {
 
         ImpactMap{motion: motion, chatter_checker: ChatterChecker::default(parameters), 
         generator: ImpactGenerator::new(parameters.converter()), 
-        coefficient_of_restitution: parameters.coefficient_of_restitution()}
+        coefficient_of_restitution: parameters.coefficient_of_restitution(),
+        mode: RestitutionMode::of(parameters)}
+    }
+
+    pub fn mode(&self) -> RestitutionMode {
+        self.mode
     }
 
     pub fn generate_impact(&self, time: Time, velocity: Velocity) -> Impact {
         self.generator.generate(time, velocity)
     }
 
-    // Apply the map to an impact
+    // Apply the map to an impact. When r = 0 the mass is left at rest whatever the velocity of the impact, so the
+    // impact is replaced by one at rest, which sticks if it is in the sticking region.
     pub fn apply(&self, impact: Impact) -> ImpactResult {
         debug!("Applying impact map to impact {:?}", impact);
 
+        let impact = match self.mode {
+            RestitutionMode::Plastic => self.generator.generate_after_periods(impact.periods(), impact.time_in_period(), 0.0),
+            _ => impact
+        };
+
         let trajectory = self.motion.next_impact(impact);
 
         let state_at_impact = trajectory.last();
 
-        ImpactResult{impact: self.generate_impact(state_at_impact.time(), state_at_impact.velocity()), found_impact: trajectory.found_impact()}
+        ImpactResult{impact: self.generator.generate_after_periods(trajectory.origin(), state_at_impact.time(), state_at_impact.velocity()), found_impact: trajectory.found_impact(), final_state: state_at_impact}
+    }
+
+    // Apply the map `count` times in succession, with no chatter checking, returning `None` if any
+    // application fails to find an impact
+    pub fn apply_repeatedly(&self, impact: Impact, count: u32) -> Option<Impact> {
+        let mut current = impact;
+
+        for _ in 0..count {
+            let result = self.apply(current);
+
+            if !result.found_impact {
+                return None;
+            }
+
+            current = result.impact;
+        }
+
+        Some(current)
     }
 
-    // Iterate the map 
-    pub fn iterate(&mut self, initial_impact: Impact, num_iterations: u32) -> IterationResult {
+    // How close the motion after `impact` comes to the obstacle before the next impact (see `MotionBetweenImpacts`)
+    pub fn clearance(&self, impact: Impact) -> Option<Distance> {
+        self.motion.clearance(impact)
+    }
+
+    // Where the motion after `impact` would come closest to the obstacle about `elapsed` later if the obstacle
+    // were not there, as an impact at the time of the maximum displacement with zero velocity, together with the
+    // displacement itself (see `MotionBetweenImpacts::peak_near`)
+    pub fn peak_after(&self, impact: Impact, elapsed: Time, reach: Time) -> Option<(Impact, Distance)> {
+        let peak = self.motion.peak_near(impact, impact.time_in_period() + elapsed, reach)?;
+
+        Some((self.generator.generate_after_periods(impact.periods(), peak.time(), 0.0), peak.displacement()))
+    }
+
+    // Iterate the map from a fresh context
+    pub fn iterate(&self, initial_impact: Impact, num_iterations: u32) -> IterationResult {
+        self.iterate_with_context(&mut IterationContext::new(), initial_impact, num_iterations)
+    }
+
+    // Iterate the map, carrying trajectory state in (and back out of) the supplied context
+    pub fn iterate_with_context(&self, context: &mut IterationContext, initial_impact: Impact, num_iterations: u32) -> IterationResult {
+        self.iterate_with_control(context, &mut IterationControl::new(), initial_impact, num_iterations)
+    }
+
+    // Iterate the map, reporting progress and stopping early if cancelled. Only the impacts selected by the
+    // control's sampling are recorded, and iteration stops as soon as the maximum number of points is reached.
+    // When r = 1 the symplectic error of every step is also estimated, which costs four further applications of
+    // the map per step.
+    pub fn iterate_with_control(&self, context: &mut IterationContext, control: &mut IterationControl, initial_impact: Impact, num_iterations: u32) -> IterationResult {
         debug!("Iterating from impact {:?}", initial_impact);
 
         let mut long_excursions = false;
 
-        let mut trajectory = vec![initial_impact];
+        let mut non_impacting_amplitude = None;
+
+        let mut cancelled = false;
+
+        let mut largest_symplectic_error = None;
+
+        let sampling = control.sampling;
+
+        let mut trajectory = Vec::with_capacity(sampling.capacity(num_iterations));
 
-        for _ in 0..num_iterations {
-            let next_impact = self.apply(*trajectory.last().unwrap());
+        let mut impact_index = 0u64;
 
-            trajectory.push(next_impact.impact);
+        let mut current_impact = initial_impact;
+
+        sampling.record(&mut trajectory, &mut impact_index, current_impact);
+
+        for iteration in 0..num_iterations {
+            if control.is_cancelled() {
+                debug!("Iteration cancelled after {} iterations", iteration);
+                cancelled = true;
+                break;
+            }
+
+            if sampling.is_full(&trajectory) {
+                break;
+            }
+
+            let next_impact = self.apply(current_impact);
 
             if !next_impact.found_impact {
                 long_excursions = true;
+
+                // The end of a long excursion is not an impact. If the motion from there can never reach the
+                // obstacle there is nothing more to iterate. That needs the forced response to stay clear of the
+                // obstacle, but since nothing damps the free oscillation it also has to be confirmed from the
+                // state itself.
+                if self.parameters().non_impacting_amplitude().is_some() {
+                    let maximum_displacement = self.motion.generator().generate_from_state(next_impact.final_state).maximum_displacement();
+
+                    if maximum_displacement < self.parameters().obstacle_offset() {
+                        debug!("Motion is non-impacting after {} iterations", iteration);
+                        non_impacting_amplitude = Some(maximum_displacement);
+                        break;
+                    }
+                }
+            }
+
+            if self.mode == RestitutionMode::Conservative && next_impact.found_impact {
+                if let Some(error) = symplectic_error(self, current_impact, next_impact.impact) {
+                    largest_symplectic_error = Some(largest_symplectic_error.map_or(error, |largest: f64| largest.max(error)));
+                }
             }
 
+            current_impact = next_impact.impact;
+
+            sampling.record(&mut trajectory, &mut impact_index, current_impact);
+
+            control.observe(current_impact);
+
             // Now check for chatter
-            let chatter_result = self.chatter_checker.check(*trajectory.last().unwrap());
+            let chatter_result = self.chatter_checker.check(&mut context.chatter_count, current_impact);
 
             if chatter_result.is_chatter() {
-                trajectory.push(chatter_result.accumulation_impact());
+                current_impact = chatter_result.accumulation_impact();
+
+                sampling.record(&mut trajectory, &mut impact_index, current_impact);
+
+                control.observe(current_impact);
             }
+
+            control.report(iteration + 1);
         }
 
-        IterationResult{long_excursions: long_excursions, impacts: trajectory}
+        IterationResult{long_excursions: long_excursions, impacts: trajectory, final_impact: current_impact, non_impacting_amplitude, symplectic_error: largest_symplectic_error, cancelled}
+    }
+
+    // An impact at phase `phi` in the first forcing cycle
+    pub fn impact_from_point(&self, phi: Phase, v: Velocity) -> Impact {
+        self.generate_impact(self.converter().time_into_cycle(phi), v)
     }
 
     // Convenient overload
-    pub fn iterate_from_point(&mut self, phi: Phase, v: Velocity, num_iterations: u32) -> IterationResult {
-        let t = self.converter().time_into_cycle(phi);
-        self.iterate(self.generate_impact(t, v), num_iterations)
+    pub fn iterate_from_point(&self, phi: Phase, v: Velocity, num_iterations: u32) -> IterationResult {
+        self.iterate(self.impact_from_point(phi, v), num_iterations)
+    }
+
+    // Integrate forward from an arbitrary state to the first impact, and then iterate the map from there.
+    // A state at the obstacle which is not moving away from it is already an impact.
+    pub fn iterate_from_state(&self, state: StateOfMotion, num_iterations: u32) -> Result<StateIterationResult, StateError> {
+        let offset = self.parameters().obstacle_offset();
+
+        if state.displacement() > offset {
+            return Err(StateError::BeyondObstacle{displacement: state.displacement(), offset});
+        }
+
+        if state.displacement() == offset && state.velocity() >= 0.0 {
+            return Ok(StateIterationResult::Impacting(self.iterate(self.generate_impact(state.time(), state.velocity()), num_iterations)));
+        }
+
+        let maximum_displacement = self.motion.generator().generate_from_state(state).maximum_displacement();
+
+        let free_flight = self.motion.first_impact(state);
+
+        if !free_flight.found_impact() {
+            debug!("No impact reached from state {:?}", state);
+
+            // The forced response has to clear the obstacle as well as the motion from this state, and the bound is
+            // only a guarantee if the search (which is the numerical confirmation) agrees with it
+            if self.parameters().non_impacting_amplitude().is_some() && maximum_displacement < offset {
+                return Ok(StateIterationResult::NonImpacting{amplitude: maximum_displacement});
+            }
+
+            return Ok(StateIterationResult::NoImpact);
+        }
+
+        let state_at_impact = free_flight.last();
+
+        Ok(StateIterationResult::Impacting(self.iterate(self.generator.generate_after_periods(free_flight.origin(), state_at_impact.time(), state_at_impact.velocity()), num_iterations)))
+    }
+
+    // Find every impact which the map takes to `impact`. The motion between impacts is time-reversible, so
+    // running it backwards from `impact` to where it last left the obstacle gives the only ordinary
+    // pre-image. If the motion left the obstacle with zero velocity at the end of a sticking interval,
+    // the whole interval also maps to `impact`. The result is empty if the backward search finds no
+    // crossing within the maximum number of forcing periods.
+    pub fn pre_images(&self, impact: Impact) -> Vec<PreImage> {
+        const VELOCITY_TOLERANCE: f64 = 1e-3;
+
+        let backward = self.motion.previous_impact(impact);
+
+        let departure = backward.last();
+
+        let origin = backward.origin();
+
+        let mut result = vec![];
+
+        // A search which comes back to where it started has found `impact` itself rather than where the motion left
+        // the obstacle
+        if !backward.found_impact() || default_impact_comparer()(self.generator.generate_after_periods(origin, departure.time(), departure.velocity()), impact) {
+            return result;
+        }
+
+        let departs_at_rest = departure.velocity().abs() < VELOCITY_TOLERANCE;
+
+        if self.coefficient_of_restitution > 0.0 {
+            result.push(PreImage::Impact(self.generator.generate_after_periods(origin, departure.time(), -departure.velocity() / self.coefficient_of_restitution)));
+        } else if departs_at_rest {
+            result.push(PreImage::AnyVelocity{time: self.converter().time_after_periods(origin, departure.time())});
+        }
+
+        if departs_at_rest {
+            if let Some(sticking) = self.sticking_pre_image(origin, departure.time(), impact) {
+                result.push(sticking);
+            }
+        }
+
+        result
+    }
+
+    // Motion which leaves the obstacle tangentially is very sensitive to where it leaves, so rather than
+    // relying on the backward search to locate a release exactly, take the nearest release time and
+    // confirm that it really does map to `impact`. The departure time is measured from the start of the period
+    // which begins after `origin` whole periods.
+    fn sticking_pre_image(&self, origin: i64, departure_time: Time, impact: Impact) -> Option<PreImage> {
+        let sticking = self.motion.sticking();
+
+        if sticking.never() || sticking.always() {
+            return None;
+        }
+
+        let converter = self.converter();
+
+        let phase_offset = converter.time_to_phase(departure_time) - sticking.phase_out();
+
+        let release_time = departure_time - converter.time_into_cycle(phase_offset - phase_offset.round());
+
+        let release = self.apply(self.generator.generate_after_periods(origin, release_time, 0.0));
+
+        if release.found_impact && default_impact_comparer()(release.impact, impact) {
+            let sticking_time = converter.time_into_cycle(1.0 - sticking.phase_in() + sticking.phase_out());
+
+            let release_time = converter.time_after_periods(origin, release_time);
+
+            return Some(PreImage::Sticking{from: release_time - sticking_time, to: release_time});
+        }
+
+        None
+    }
+
+    // Iterate from each of a set of initial impacts in parallel
+    pub fn iterate_batch(&self, initial_impacts: &[Impact], num_iterations: u32, keep_trajectories: bool) -> Vec<TrajectorySummary> {
+        let classifier = OrbitClassifier::default(self.parameters());
+
+        initial_impacts.par_iter().map(|&initial_impact| {
+            let result = self.iterate(initial_impact, num_iterations);
+
+            TrajectorySummary{
+                final_impact: result.final_impact(),
+                orbit_type: classifier.classify(&result),
+                long_excursions: result.has_long_excursions(),
+                trajectory: if keep_trajectories {Some(result.impacts)} else {None}
+            }
+        }).collect()
+    }
+
+    pub fn parameters(&self) -> Parameters {
+        self.motion.generator().parameters()
     }
 
     pub fn converter(&self) -> PhaseConverter {
@@ -151,6 +595,299 @@ impl ImpactMap {
 mod tests {
     use super::*;
 
+    fn assert_shareable<T: Send + Sync>() {}
+
+    fn assert_same_trajectory(x: &IterationResult, y: &IterationResult) {
+        assert_eq!(x.trajectory().len(), y.trajectory().len());
+
+        for (a, b) in x.trajectory().iter().zip(y.trajectory().iter()) {
+            assert_eq!(a.time(), b.time());
+            assert_eq!(a.velocity(), b.velocity());
+        }
+    }
+
+    #[test]
+    fn test_map_can_be_shared_between_threads() {
+        assert_shareable::<ImpactMap>();
+
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let expected = mapper.iterate_from_point(0.0, 0.0, 200);
+
+        let results: Vec<IterationResult> = (0..4).into_par_iter().map(|_| mapper.iterate_from_point(0.0, 0.0, 200)).collect();
+
+        for result in results.iter() {
+            assert_same_trajectory(&expected, result);
+        }
+    }
+
+    #[test]
+    fn test_map_does_not_drift_over_long_runs() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let start = mapper.impact_from_point(0.3, 0.5);
+
+        // The same impact after a trillion forcing periods, when the simulation time itself has only a few
+        // significant figures left for the phase
+        let late_start = mapper.generator.generate_after_periods(1_000_000_000_000, start.time_in_period(), start.velocity());
+
+        let image = mapper.apply_repeatedly(start, 5).unwrap();
+        let late_image = mapper.apply_repeatedly(late_start, 5).unwrap();
+
+        assert_eq!(late_image.periods() - image.periods(), 1_000_000_000_000);
+        assert_eq!(late_image.phase(), image.phase());
+        assert_eq!(late_image.velocity(), image.velocity());
+        assert_eq!(late_image.time_since(late_start), image.time_since(start));
+    }
+
+    #[test]
+    fn test_resonant_one_one_orbit() {
+        // At ω = 1 the forcing adds πcos(φ) to the velocity and πsin(φ) to the displacement over each forcing
+        // period, so a (1, 1) orbit has φ = 0 and v(1 + r) = π
+        let parameters = Parameters::new(1.0, 0.5, 0.8, 100).unwrap();
+
+        let result = ImpactMap::new(parameters).iterate_from_point(0.0, 0.0, 1000);
+
+        let last = result.trajectory().last().unwrap();
+
+        assert!(last.phase().min(1.0 - last.phase()) < 1e-6);
+        assert!((last.velocity() - std::f64::consts::PI / 1.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_chatter_count_does_not_leak_between_runs() {
+        // These parameters chatter twice within the first 40 iterations
+        let parameters = Parameters::new(4.0, -0.1, 0.5, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let first = mapper.iterate_from_point(0.0, 0.0, 40);
+        let second = mapper.iterate_from_point(0.0, 0.0, 40);
+
+        assert!(first.trajectory().len() > 41);
+
+        assert_same_trajectory(&first, &second);
+    }
+
+    #[test]
+    fn test_progress_is_reported_at_intervals() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let mut reports = vec![];
+
+        let result = {
+            let mut control = IterationControl::new().with_progress(25, |n| reports.push(n));
+
+            mapper.iterate_with_control(&mut IterationContext::new(), &mut control, mapper.generate_impact(0.0, 0.0), 100)
+        };
+
+        assert!(!result.was_cancelled());
+        assert_eq!(reports, vec![25, 50, 75, 100]);
+    }
+
+    #[test]
+    fn test_cancellation_stops_iteration() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let cancellation = AtomicBool::new(false);
+
+        let result = {
+            let mut control = IterationControl::new()
+                .with_cancellation(&cancellation)
+                .with_progress(10, |n| if n == 30 {cancellation.store(true, Ordering::Relaxed)});
+
+            mapper.iterate_with_control(&mut IterationContext::new(), &mut control, mapper.generate_impact(0.0, 0.0), 1000)
+        };
+
+        assert!(result.was_cancelled());
+        assert_eq!(result.trajectory().len(), 31);
+    }
+
+    #[test]
+    fn test_any_cancellation_flag_stops_iteration() {
+        let mapper = ImpactMap::new(Parameters::new(4.85, -0.1, 0.8, 100).unwrap());
+
+        let unset = AtomicBool::new(false);
+        let set = AtomicBool::new(true);
+
+        let mut control = IterationControl::new().with_cancellation(&unset).with_cancellation(&set);
+
+        let result = mapper.iterate_with_control(&mut IterationContext::new(), &mut control, mapper.generate_impact(0.0, 0.0), 1000);
+
+        assert!(result.was_cancelled());
+        assert_eq!(result.trajectory().len(), 1);
+        assert!(!unset.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_sampling_skips_and_decimates() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let initial_impact = mapper.generate_impact(0.0, 0.0);
+
+        let full = mapper.iterate(initial_impact, 100);
+
+        let sample = |sampling: TrajectorySampling| -> IterationResult {
+            let mut control = IterationControl::new().with_sampling(sampling);
+            mapper.iterate_with_control(&mut IterationContext::new(), &mut control, initial_impact, 100)
+        };
+
+        let decimated = sample(TrajectorySampling::new(20, 3, 0));
+
+        let expected: Vec<&Impact> = full.trajectory().iter().skip(20).step_by(3).collect();
+
+        assert_eq!(decimated.trajectory().len(), expected.len());
+
+        for (impact, expected_impact) in decimated.trajectory().iter().zip(expected.iter()) {
+            assert_eq!(impact.time(), expected_impact.time());
+        }
+
+        assert_eq!(decimated.final_impact().time(), full.final_impact().time());
+
+        let capped = sample(TrajectorySampling::new(20, 3, 5));
+
+        assert_eq!(capped.trajectory().len(), 5);
+        assert_eq!(capped.trajectory()[4].time(), expected[4].time());
+    }
+
+    #[test]
+    fn test_iterate_from_state() {
+        let parameters = Parameters::new(4.85, 0.05, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        match mapper.iterate_from_state(StateOfMotion::new(0.0, 0.0, 0.0), 10).unwrap() {
+            StateIterationResult::Impacting(result) => {
+                assert_eq!(result.trajectory().len(), 11);
+                assert!(result.trajectory()[0].time() > 0.0);
+            },
+            _ => panic!("Expected the motion to reach the obstacle")
+        }
+
+        assert!(matches!(mapper.iterate_from_state(StateOfMotion::new(0.0, 0.2, 0.0), 10),
+            Err(StateError::BeyondObstacle{..})));
+
+        let unreachable = ImpactMap::new(Parameters::new(4.85, 0.1, 0.8, 100).unwrap());
+
+        match unreachable.iterate_from_state(StateOfMotion::new(0.0, 0.0, 0.0), 10).unwrap() {
+            StateIterationResult::NonImpacting{amplitude} => {
+                assert!(amplitude < 0.1);
+                assert_eq!(amplitude, unreachable.motion.generator().generate_from_state(StateOfMotion::new(0.0, 0.0, 0.0)).maximum_displacement());
+            },
+            _ => panic!("Expected non-impacting motion")
+        }
+    }
+
+    #[test]
+    fn test_iteration_continues_after_long_excursion_which_can_reach_obstacle() {
+        // |γ| ≈ 0.044, so the forced response alone stays clear of the obstacle, but the free oscillation which
+        // an impact starts carries the motion back to it
+        let parameters = Parameters::new(4.85, 0.2, 0.5, 5).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let result = mapper.iterate_from_point(0.9, 0.4, 1000);
+
+        assert!(result.has_long_excursions());
+        assert_eq!(result.non_impacting_amplitude(), None);
+        assert_eq!(result.trajectory().len(), 1001);
+
+        let excursion = result.trajectory().windows(2).map(|pair| mapper.apply(pair[0])).find(|next| !next.found_impact).unwrap();
+
+        let amplitude = mapper.motion.generator().generate_from_state(excursion.final_state).maximum_displacement();
+
+        assert!(parameters.non_impacting_amplitude().is_some());
+        assert!(amplitude >= parameters.obstacle_offset());
+    }
+
+    #[test]
+    fn test_search_does_not_return_to_its_starting_impact() {
+        // The slow impacts here leave the obstacle so briefly that the bisection steps back to where the motion
+        // left it. Taking that as the next impact once gave a run of impacts with no time of flight and
+        // alternating velocities, and a spurious long excursion after 47 impacts, where the motion in fact chatters.
+        let parameters = Parameters::new(4.85, 0.2, 0.5, 5).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let result = mapper.iterate_from_point(0.8, 0.1, 1000);
+
+        assert!(!result.has_long_excursions());
+        assert!(result.trajectory().iter().all(|impact| impact.velocity() >= 0.0));
+    }
+
+    #[test]
+    fn test_pre_image_of_image_is_original() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let comparer = default_impact_comparer();
+
+        for i in 0..5 {
+            let impact = mapper.impact_from_point(0.1 + 0.2 * i as f64, 0.3 + 0.1 * i as f64);
+
+            let image = mapper.apply(impact).impact;
+
+            let pre_images = mapper.pre_images(image);
+
+            assert!(pre_images.iter().any(|pre_image| match pre_image {
+                PreImage::Impact(candidate) => comparer(*candidate, impact),
+                _ => false
+            }), "{:?} not among pre-images {:?} of {:?}", impact, pre_images, image);
+        }
+    }
+
+    #[test]
+    fn test_sticking_pre_images() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        // A zero-velocity impact at phase 0 sticks, and is then released at the end of the sticking region
+        let image = mapper.apply(mapper.impact_from_point(0.0, 0.0)).impact;
+
+        let sticking = mapper.pre_images(image).into_iter().find_map(|pre_image| match pre_image {
+            PreImage::Sticking{from, to} => Some((from, to)),
+            _ => None
+        });
+
+        let (from, to) = sticking.expect("Expected a sticking pre-image");
+
+        assert!(from < 0.0 && 0.0 < to);
+    }
+
+    #[test]
+    fn test_batch_matches_individual_runs() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let initial_impacts: Vec<Impact> = (0..8).map(|i| mapper.generate_impact(0.1 * i as f64, 0.2 * i as f64)).collect();
+
+        let summaries = mapper.iterate_batch(&initial_impacts, 50, true);
+
+        assert_eq!(summaries.len(), initial_impacts.len());
+
+        for (summary, &initial_impact) in summaries.iter().zip(initial_impacts.iter()) {
+            let expected = mapper.iterate(initial_impact, 50);
+
+            assert_same_trajectory(&expected, &IterationResult{impacts: summary.trajectory().unwrap().clone(), final_impact: summary.final_impact(), long_excursions: summary.has_long_excursions(), non_impacting_amplitude: None, symplectic_error: None, cancelled: false});
+            assert_eq!(summary.final_impact().time(), expected.trajectory().last().unwrap().time());
+        }
+
+        assert!(mapper.iterate_batch(&initial_impacts, 50, false).iter().all(|summary| summary.trajectory().is_none()));
+    }
+
     // #[test]
     // fn test_apply_always_returns() {
     //     let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
@@ -0,0 +1,196 @@
+//
+// The two limiting values of the coefficient of restitution, for which the impact map has special structure
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::linearisation::Jacobian as Jacobian;
+use super::model_types::Phase as Phase;
+use super::parameters::Parameters as Parameters;
+use std::fmt;
+
+/// How the impact map is computed, which the map selects for itself from the coefficient of restitution
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RestitutionMode {
+    /// 0 < r < 1
+    General,
+
+    /// r = 0: every impact leaves the mass at rest on the obstacle, so the velocity of an impact has no effect on
+    /// what follows and the map reduces to a map of the circle of phases
+    Plastic,
+
+    /// r = 1: no energy is lost at impacts, and the map preserves the area form v dv dφ
+    Conservative
+}
+
+impl RestitutionMode {
+    pub fn of(parameters: Parameters) -> RestitutionMode {
+        let r = parameters.coefficient_of_restitution();
+
+        if r == 0.0 {
+            RestitutionMode::Plastic
+        } else if r == 1.0 {
+            RestitutionMode::Conservative
+        } else {
+            RestitutionMode::General
+        }
+    }
+}
+
+impl fmt::Display for RestitutionMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RestitutionMode::General => write!(f, "general"),
+            RestitutionMode::Plastic => write!(f, "plastic"),
+            RestitutionMode::Conservative => write!(f, "conservative")
+        }
+    }
+}
+
+/// The impact map for r = 0, as a map of the phase at which the mass comes to rest on the obstacle to the phase
+/// of the next impact. If the mass comes to rest in the sticking region it stays there until it is released.
+pub struct CircleMap {
+    mapper: ImpactMap
+}
+
+impl CircleMap {
+    /// Returns `None` unless r = 0
+    pub fn new(parameters: Parameters) -> Option<CircleMap> {
+        match RestitutionMode::of(parameters) {
+            RestitutionMode::Plastic => Some(CircleMap{mapper: ImpactMap::new(parameters)}),
+            _ => None
+        }
+    }
+
+    /// The next impact after the mass comes to rest at `phase`, or `None` if there is no impact within the maximum
+    /// number of forcing periods
+    pub fn apply(&self, phase: Phase) -> Option<Impact> {
+        self.mapper.apply_repeatedly(self.mapper.impact_from_point(phase, 0.0), 1)
+    }
+
+    /// Iterates from `phase`, returning the phases visited (starting with `phase` itself). The sequence stops early
+    /// if an impact is not found.
+    pub fn iterate(&self, phase: Phase, num_iterations: u32) -> Vec<Phase> {
+        let mut phases = Vec::with_capacity(num_iterations as usize + 1);
+
+        let mut current = self.mapper.converter().time_to_phase(self.mapper.converter().time_into_cycle(phase));
+
+        phases.push(current);
+
+        for _ in 0..num_iterations {
+            match self.apply(current) {
+                Some(impact) => current = impact.phase(),
+                None => break
+            }
+
+            phases.push(current);
+        }
+
+        phases
+    }
+
+    /// The graph of the circle map at `num_points` equally spaced phases, with `None` where there is no image
+    pub fn graph(&self, num_points: u32) -> Vec<(Phase, Option<Phase>)> {
+        let num_points = std::cmp::max(1, num_points);
+
+        (0..num_points).map(|i| {
+            let phase = i as f64 / num_points as f64;
+
+            (phase, self.apply(phase).map(|impact| impact.phase()))
+        }).collect()
+    }
+}
+
+/// How far one application of the map, from `impact` to `image`, is from scaling the form v dv dφ by r², as the
+/// exact map does. The Jacobian is estimated by differences, so the error includes the error of the estimate.
+/// Returns `None` for r = 0, for impacts too slow to be treated as transverse, or if the Jacobian cannot be
+/// estimated.
+pub fn symplectic_error(mapper: &ImpactMap, impact: Impact, image: Impact) -> Option<f64> {
+    const VELOCITY_TOLERANCE: f64 = 1e-3;
+
+    let r = mapper.parameters().coefficient_of_restitution();
+
+    if r == 0.0 || impact.velocity() < VELOCITY_TOLERANCE || image.velocity() < VELOCITY_TOLERANCE {
+        return None;
+    }
+
+    let jacobian = Jacobian::of_return_map(mapper, impact, 1)?;
+
+    Some((jacobian.determinant() * image.velocity() / (r * r * impact.velocity()) - 1.0).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_is_selected_from_parameters() {
+        assert_eq!(RestitutionMode::of(Parameters::new(2.0, 0.0, 0.0, 100).unwrap()), RestitutionMode::Plastic);
+        assert_eq!(RestitutionMode::of(Parameters::new(2.0, 0.0, 0.8, 100).unwrap()), RestitutionMode::General);
+        assert_eq!(RestitutionMode::of(Parameters::new(2.0, 0.0, 1.0, 100).unwrap()), RestitutionMode::Conservative);
+
+        assert!(CircleMap::new(Parameters::new(2.0, 0.0, 0.8, 100).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_impact_velocity_is_forgotten_when_plastic() {
+        let parameters = Parameters::new(2.8, 0.0, 0.0, 100).unwrap();
+
+        let mapper = ImpactMap::new(parameters);
+
+        let circle_map = CircleMap::new(parameters).unwrap();
+
+        for i in 0..10 {
+            let phase = 0.1 * i as f64;
+
+            let expected = circle_map.apply(phase).unwrap();
+
+            let image = mapper.apply_repeatedly(mapper.impact_from_point(phase, 0.7), 1).unwrap();
+
+            assert_eq!(image.phase(), expected.phase());
+            assert_eq!(image.velocity(), expected.velocity());
+        }
+    }
+
+    #[test]
+    fn test_circle_map_matches_iteration() {
+        let parameters = Parameters::new(2.8, 0.0, 0.0, 100).unwrap();
+
+        let phases = CircleMap::new(parameters).unwrap().iterate(0.3, 50);
+
+        let result = ImpactMap::new(parameters).iterate_from_point(0.3, 0.0, 50);
+
+        assert_eq!(phases.len(), 51);
+        assert_eq!(result.trajectory().len(), 51);
+
+        for (phase, impact) in phases.iter().zip(result.trajectory().iter()) {
+            assert_eq!(*phase, impact.phase());
+        }
+    }
+
+    #[test]
+    fn test_conservative_map_preserves_area() {
+        let parameters = Parameters::new(4.85, -0.1, 1.0, 100).unwrap();
+
+        let result = ImpactMap::new(parameters).iterate_from_point(0.3, 0.5, 200);
+
+        let error = result.symplectic_error().expect("Expected the symplectic error to be tracked");
+
+        assert!(error < 1e-4, "Symplectic error {}", error);
+
+        // Dissipative maps are not tracked
+        let dissipative = ImpactMap::new(Parameters::new(4.85, -0.1, 0.8, 100).unwrap()).iterate_from_point(0.3, 0.5, 200);
+
+        assert!(dissipative.symplectic_error().is_none());
+    }
+
+    #[test]
+    fn test_area_scales_by_square_of_restitution() {
+        let mapper = ImpactMap::new(Parameters::new(4.85, -0.1, 0.8, 100).unwrap());
+
+        let impact = mapper.impact_from_point(0.3, 0.5);
+
+        let image = mapper.apply_repeatedly(impact, 1).unwrap();
+
+        assert!(symplectic_error(&mapper, impact, image).unwrap() < 1e-4);
+    }
+}
@@ -0,0 +1,191 @@
+//
+// Linearisation of the impact map about an impact, for analysing the stability of periodic orbits
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::model_types::Phase as Phase;
+use super::model_types::Velocity as Velocity;
+
+/// The difference between two phases, allowing for periodicity, so that the result lies in [-0.5, 0.5)
+pub fn phase_difference(to: Phase, from: Phase) -> Phase {
+    let difference = to - from;
+
+    difference - (difference + 0.5).floor()
+}
+
+/// Distance between two impacts on the impact surface, treating phase as periodic
+pub fn impact_distance(x: Impact, y: Impact) -> f64 {
+    phase_difference(x.phase(), y.phase()).hypot(x.velocity() - y.velocity())
+}
+
+/// The eigenvalues of a real 2x2 matrix
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Eigenvalues {
+    /// Ordered so that the first has the larger modulus
+    Real(f64, f64),
+    ComplexPair {real: f64, imaginary: f64}
+}
+
+impl Eigenvalues {
+    /// The largest modulus of the eigenvalues, which determines the stability of a fixed point
+    pub fn spectral_radius(&self) -> f64 {
+        match *self {
+            Eigenvalues::Real(first, _) => first.abs(),
+            Eigenvalues::ComplexPair{real, imaginary} => real.hypot(imaginary)
+        }
+    }
+}
+
+/// The Jacobian of some number of iterations of the impact map, with respect to the (phase, velocity)
+/// coordinates of the impact surface. Rows are (phase, velocity) of the image, columns (phase, velocity) of
+/// the original impact.
+#[derive(Debug, Copy, Clone)]
+pub struct Jacobian {
+    entries: [[f64; 2]; 2]
+}
+
+impl Jacobian {
+    pub fn new(entries: [[f64; 2]; 2]) -> Jacobian {
+        Jacobian{entries}
+    }
+
+    /// Estimates the Jacobian of `count` iterations of `mapper` at `impact` by central differences.
+    /// Returns `None` if the map fails to find an impact from any of the perturbed points.
+    pub fn of_return_map(mapper: &ImpactMap, impact: Impact, count: u32) -> Option<Jacobian> {
+        const STEP: f64 = 1e-6;
+
+        let velocity_step = STEP * impact.velocity().abs().max(1.0);
+
+        let image = |phase: Phase, velocity: Velocity| mapper.apply_repeatedly(mapper.impact_from_point(phase, velocity), count);
+
+        let phase_plus = image(impact.phase() + STEP, impact.velocity())?;
+        let phase_minus = image(impact.phase() - STEP, impact.velocity())?;
+        let velocity_plus = image(impact.phase(), impact.velocity() + velocity_step)?;
+        let velocity_minus = image(impact.phase(), impact.velocity() - velocity_step)?;
+
+        Some(Jacobian::new([
+            [phase_difference(phase_plus.phase(), phase_minus.phase()) / (2.0 * STEP),
+                phase_difference(velocity_plus.phase(), velocity_minus.phase()) / (2.0 * velocity_step)],
+            [(phase_plus.velocity() - phase_minus.velocity()) / (2.0 * STEP),
+                (velocity_plus.velocity() - velocity_minus.velocity()) / (2.0 * velocity_step)]
+        ]))
+    }
+
+    pub fn entry(&self, row: usize, column: usize) -> f64 {
+        self.entries[row][column]
+    }
+
+    pub fn trace(&self) -> f64 {
+        self.entries[0][0] + self.entries[1][1]
+    }
+
+    pub fn determinant(&self) -> f64 {
+        self.entries[0][0] * self.entries[1][1] - self.entries[0][1] * self.entries[1][0]
+    }
+
+    pub fn eigenvalues(&self) -> Eigenvalues {
+        let half_trace = 0.5 * self.trace();
+
+        let discriminant = half_trace * half_trace - self.determinant();
+
+        if discriminant < 0.0 {
+            Eigenvalues::ComplexPair{real: half_trace, imaginary: (-discriminant).sqrt()}
+        } else {
+            let root = discriminant.sqrt();
+
+            let (larger, smaller) = if half_trace >= 0.0 {(half_trace + root, half_trace - root)} else {(half_trace - root, half_trace + root)};
+
+            Eigenvalues::Real(larger, smaller)
+        }
+    }
+
+    /// A unit eigenvector, as (phase, velocity) components, for a real `eigenvalue` of the matrix
+    pub fn eigenvector(&self, eigenvalue: f64) -> (Phase, Velocity) {
+        let [[a, b], [c, d]] = self.entries;
+
+        // Either row of (J - λI)v = 0 determines v; use whichever is better conditioned
+        let from_first_row = (b, eigenvalue - a);
+        let from_second_row = (eigenvalue - d, c);
+
+        let norm = |v: (f64, f64)| v.0.hypot(v.1);
+
+        let vector = if norm(from_first_row) >= norm(from_second_row) {from_first_row} else {from_second_row};
+
+        let length = norm(vector);
+
+        if length > 0.0 {
+            (vector.0 / length, vector.1 / length)
+        } else {
+            // J = λI, so every direction is an eigenvector
+            (1.0, 0.0)
+        }
+    }
+
+    /// Solves J x = `vector` for x, unless J is singular
+    pub fn solve(&self, vector: (f64, f64)) -> Option<(f64, f64)> {
+        let determinant = self.determinant();
+
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+
+        Some(((self.entries[1][1] * vector.0 - self.entries[0][1] * vector.1) / determinant,
+            (self.entries[0][0] * vector.1 - self.entries[1][0] * vector.0) / determinant))
+    }
+
+    pub fn apply(&self, vector: (f64, f64)) -> (f64, f64) {
+        (self.entries[0][0] * vector.0 + self.entries[0][1] * vector.1,
+            self.entries[1][0] * vector.0 + self.entries[1][1] * vector.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parameters::Parameters;
+    use float_eq::assert_float_eq;
+
+    #[test]
+    fn test_phase_difference_is_periodic() {
+        assert_float_eq!(phase_difference(0.05, 0.95), 0.1, abs <= 1e-12);
+        assert_float_eq!(phase_difference(0.95, 0.05), -0.1, abs <= 1e-12);
+        assert_float_eq!(phase_difference(0.3, 0.1), 0.2, abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_eigen_decomposition() {
+        let jacobian = Jacobian::new([[2.0, 1.0], [0.0, 0.5]]);
+
+        assert_eq!(jacobian.eigenvalues(), Eigenvalues::Real(2.0, 0.5));
+
+        for eigenvalue in [2.0, 0.5] {
+            let vector = jacobian.eigenvector(eigenvalue);
+            let image = jacobian.apply(vector);
+
+            assert_float_eq!(image.0, eigenvalue * vector.0, abs <= 1e-12);
+            assert_float_eq!(image.1, eigenvalue * vector.1, abs <= 1e-12);
+        }
+
+        assert_eq!(Jacobian::new([[0.0, -1.0], [1.0, 0.0]]).eigenvalues(), Eigenvalues::ComplexPair{real: 0.0, imaginary: 1.0});
+    }
+
+    #[test]
+    fn test_jacobian_predicts_nearby_images() {
+        let mapper = ImpactMap::new(Parameters::new(2.8, 0.1, 0.8, 100).unwrap());
+
+        let impact = mapper.impact_from_point(0.3, 0.5);
+
+        let jacobian = Jacobian::of_return_map(&mapper, impact, 2).unwrap();
+
+        let image = mapper.apply_repeatedly(impact, 2).unwrap();
+
+        let perturbation = (1e-4, -2e-4);
+
+        let perturbed_image = mapper.apply_repeatedly(mapper.impact_from_point(impact.phase() + perturbation.0, impact.velocity() + perturbation.1), 2).unwrap();
+
+        let predicted = jacobian.apply(perturbation);
+
+        assert_float_eq!(phase_difference(perturbed_image.phase(), image.phase()), predicted.0, abs <= 1e-6);
+        assert_float_eq!(perturbed_image.velocity() - image.velocity(), predicted.1, abs <= 1e-6);
+    }
+}
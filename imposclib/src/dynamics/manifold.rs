@@ -0,0 +1,388 @@
+//
+// Stable and unstable manifolds of saddle periodic orbits of the impact map
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::impact_map::PreImage as PreImage;
+use super::linearisation::Jacobian as Jacobian;
+use super::linearisation::Eigenvalues as Eigenvalues;
+use super::linearisation::phase_difference as phase_difference;
+use super::linearisation::impact_distance as impact_distance;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ManifoldKind {
+    Stable,
+    Unstable
+}
+
+/// Which half of a manifold to grow, relative to an eigenvector oriented so that its velocity component is
+/// non-negative
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ManifoldBranch {
+    Positive,
+    Negative
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ManifoldError {
+    NoReturnToOrbit,
+    NotASaddle {eigenvalues: Eigenvalues},
+    NotInvertible
+}
+
+impl fmt::Display for ManifoldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ManifoldError::NoReturnToOrbit => write!(f, "The impact map could not be linearised about the orbit"),
+            ManifoldError::NotASaddle{ref eigenvalues} => write!(f, "An orbit with eigenvalues {:?} is not a saddle", eigenvalues),
+            ManifoldError::NotInvertible => write!(f, "Stable manifolds require the map to be invertible, which it is not for zero coefficient of restitution")
+        }
+    }
+}
+
+/// Controls the adaptive growth of a manifold. Distances are measured on the impact surface, with phase
+/// treated as periodic.
+#[derive(Debug, Copy, Clone)]
+pub struct ManifoldSettings {
+    /// Distance from the orbit at which the manifold is seeded along the eigendirection
+    pub seed_distance: f64,
+    /// Points are inserted until successive points are no further apart than this
+    pub maximum_separation: f64,
+    /// Points are inserted until the curve turns by no more than this many radians at each point
+    pub maximum_angle: f64,
+    /// Smallest step, as a fraction of a segment of the curve already computed, used when inserting points.
+    /// If the curve is still too stretched at this resolution it is taken to be discontinuous there.
+    pub minimum_step: f64,
+    pub maximum_points: usize,
+    pub maximum_arc_length: f64
+}
+
+impl Default for ManifoldSettings {
+    fn default() -> ManifoldSettings {
+        ManifoldSettings{
+            seed_distance: 1e-4,
+            maximum_separation: 0.01,
+            maximum_angle: 0.3,
+            minimum_step: 1e-6,
+            maximum_points: 2000,
+            maximum_arc_length: 5.0
+        }
+    }
+}
+
+/// A branch of a manifold as an ordered polyline of impacts, starting at the orbit. Where the manifold
+/// crosses the singularity set the map is discontinuous, and so is the curve: `discontinuities` lists the
+/// indices of points which are not joined to their predecessor.
+#[derive(Debug, Clone)]
+pub struct Manifold {
+    kind: ManifoldKind,
+    eigenvalue: f64,
+    points: Vec<Impact>,
+    discontinuities: Vec<usize>,
+    arc_length: f64
+}
+
+impl Manifold {
+    pub fn kind(&self) -> ManifoldKind {
+        self.kind
+    }
+
+    /// The eigenvalue of the orbit's linearisation associated with the manifold
+    pub fn eigenvalue(&self) -> f64 {
+        self.eigenvalue
+    }
+
+    pub fn points(&self) -> &[Impact] {
+        &self.points
+    }
+
+    pub fn discontinuities(&self) -> &[usize] {
+        &self.discontinuities
+    }
+
+    /// Total length of the joined segments
+    pub fn arc_length(&self) -> f64 {
+        self.arc_length
+    }
+
+    fn is_joined(&self, index: usize) -> bool {
+        index > 0 && !self.discontinuities.contains(&index)
+    }
+}
+
+// A position on the curve, as a segment index and a fraction of the way along that segment
+#[derive(Debug, Copy, Clone)]
+struct CurvePosition {
+    segment: usize,
+    fraction: f64
+}
+
+pub struct ManifoldGenerator<'a> {
+    mapper: &'a ImpactMap,
+    settings: ManifoldSettings
+}
+
+impl<'a> ManifoldGenerator<'a> {
+    pub fn new(mapper: &'a ImpactMap, settings: ManifoldSettings) -> ManifoldGenerator<'a> {
+        ManifoldGenerator{mapper, settings}
+    }
+
+    /// Grows one branch of a manifold of the periodic orbit through `orbit_impact`, which has
+    /// `impacts_per_cycle` impacts in each cycle.
+    ///
+    /// The manifold is seeded with a short segment along the eigendirection, which approximates one
+    /// fundamental domain. Each new point is the image (or, for the stable manifold, the pre-image) of a
+    /// point on the curve already computed, which is refined until the new points are close enough together
+    /// and the curve bends smoothly enough. If the eigenvalue is negative the map alternates between
+    /// branches, so it is applied twice at each step.
+    pub fn generate(&self, orbit_impact: Impact, impacts_per_cycle: u32, kind: ManifoldKind, branch: ManifoldBranch) -> Result<Manifold, ManifoldError> {
+        if kind == ManifoldKind::Stable && self.mapper.parameters().coefficient_of_restitution() == 0.0 {
+            return Err(ManifoldError::NotInvertible);
+        }
+
+        let jacobian = Jacobian::of_return_map(self.mapper, orbit_impact, impacts_per_cycle).ok_or(ManifoldError::NoReturnToOrbit)?;
+
+        let eigenvalue = match jacobian.eigenvalues() {
+            Eigenvalues::Real(unstable, stable) if unstable.abs() > 1.0 && stable.abs() < 1.0 => match kind {
+                ManifoldKind::Unstable => unstable,
+                ManifoldKind::Stable => stable
+            },
+            eigenvalues => return Err(ManifoldError::NotASaddle{eigenvalues})
+        };
+
+        let count = if eigenvalue < 0.0 {2 * impacts_per_cycle} else {impacts_per_cycle};
+
+        let mut direction = jacobian.eigenvector(eigenvalue);
+
+        if (direction.1 < 0.0) != (branch == ManifoldBranch::Negative) {
+            direction = (-direction.0, -direction.1);
+        }
+
+        let origin = self.mapper.impact_from_point(orbit_impact.phase(), orbit_impact.velocity());
+
+        let seed = self.offset(origin, self.settings.seed_distance * direction.0, self.settings.seed_distance * direction.1);
+
+        let mut manifold = Manifold{kind, eigenvalue, points: vec![origin, seed], discontinuities: vec![], arc_length: self.settings.seed_distance};
+
+        let first = self.map(seed, kind, count).ok_or(ManifoldError::NoReturnToOrbit)?;
+
+        manifold.arc_length += impact_distance(seed, first);
+        manifold.points.push(first);
+
+        self.grow(&mut manifold, kind, count);
+
+        Ok(manifold)
+    }
+
+    fn grow(&self, manifold: &mut Manifold, kind: ManifoldKind, count: u32) {
+        // The seed segment (from the second point to the third) is mapped onto the rest of the curve
+        let mut position = CurvePosition{segment: 1, fraction: 0.0};
+
+        let mut step = 0.1;
+
+        while manifold.points.len() < self.settings.maximum_points && manifold.arc_length < self.settings.maximum_arc_length {
+            let next = match self.advance(manifold, position, step) {
+                Some(next) => next,
+                None => break
+            };
+
+            // Anything mapped from across a gap in the curve must land across a gap too
+            let crossed_gap = next.segment > position.segment && (position.segment + 1..=next.segment).any(|index| !manifold.is_joined(index));
+
+            let next = if crossed_gap {CurvePosition{segment: (position.segment + 1..=next.segment).find(|&index| !manifold.is_joined(index)).unwrap(), fraction: 0.0}} else {next};
+
+            let image = self.map(self.interpolate(manifold, next), kind, count);
+
+            let last = *manifold.points.last().unwrap();
+
+            let acceptable = image.is_some_and(|image| {
+                impact_distance(last, image) <= self.settings.maximum_separation && self.turning_angle(manifold, image) <= self.settings.maximum_angle
+            });
+
+            if !acceptable && !crossed_gap && step > self.settings.minimum_step {
+                step *= 0.5;
+                continue;
+            }
+
+            position = next;
+
+            if let Some(image) = image {
+                if acceptable {
+                    manifold.arc_length += impact_distance(last, image);
+                } else if impact_distance(last, image) > self.settings.maximum_separation {
+                    manifold.discontinuities.push(manifold.points.len());
+                }
+
+                manifold.points.push(image);
+
+                if acceptable && impact_distance(last, image) < 0.5 * self.settings.maximum_separation {
+                    step = (2.0 * step).min(1.0);
+                }
+            } else if !manifold.discontinuities.contains(&manifold.points.len()) {
+                // No image at all: the next point found will not be joined to this one
+                manifold.discontinuities.push(manifold.points.len());
+            }
+
+            if crossed_gap {
+                step = self.settings.minimum_step.max(0.1 * step);
+            }
+        }
+    }
+
+    // Moves `step` segments along the curve, or returns `None` if that would run off the end, i.e. onto
+    // the part of the curve which is still being computed
+    fn advance(&self, manifold: &Manifold, position: CurvePosition, step: f64) -> Option<CurvePosition> {
+        let mut next = CurvePosition{fraction: position.fraction + step, ..position};
+
+        while next.fraction >= 1.0 {
+            next.fraction -= 1.0;
+            next.segment += 1;
+        }
+
+        if next.segment + 1 >= manifold.points.len() {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    fn interpolate(&self, manifold: &Manifold, position: CurvePosition) -> Impact {
+        let start = manifold.points[position.segment];
+        let end = manifold.points[position.segment + 1];
+
+        self.offset(start,
+            position.fraction * phase_difference(end.phase(), start.phase()),
+            position.fraction * (end.velocity() - start.velocity()))
+    }
+
+    fn offset(&self, impact: Impact, phase: f64, velocity: f64) -> Impact {
+        self.mapper.impact_from_point((impact.phase() + phase).rem_euclid(1.0), impact.velocity() + velocity)
+    }
+
+    // The angle through which the curve turns at its current end if `candidate` is appended
+    fn turning_angle(&self, manifold: &Manifold, candidate: Impact) -> f64 {
+        let len = manifold.points.len();
+
+        if !manifold.is_joined(len - 1) {
+            return 0.0;
+        }
+
+        let previous = manifold.points[len - 2];
+        let last = manifold.points[len - 1];
+
+        let incoming = (phase_difference(last.phase(), previous.phase()), last.velocity() - previous.velocity());
+        let outgoing = (phase_difference(candidate.phase(), last.phase()), candidate.velocity() - last.velocity());
+
+        let cross = incoming.0 * outgoing.1 - incoming.1 * outgoing.0;
+        let dot = incoming.0 * outgoing.0 + incoming.1 * outgoing.1;
+
+        cross.atan2(dot).abs()
+    }
+
+    // Forward iterates for the unstable manifold, backward for the stable manifold
+    fn map(&self, impact: Impact, kind: ManifoldKind, count: u32) -> Option<Impact> {
+        match kind {
+            ManifoldKind::Unstable => self.mapper.apply_repeatedly(impact, count),
+            ManifoldKind::Stable => {
+                let mut current = impact;
+
+                for _ in 0..count {
+                    current = self.mapper.pre_images(current).into_iter().find_map(|pre_image| match pre_image {
+                        PreImage::Impact(impact) => Some(impact),
+                        _ => None
+                    })?;
+                }
+
+                Some(current)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parameters::Parameters;
+    use super::super::periodic_orbit::OrbitSolver;
+
+    // The (1, 1) orbit for these parameters has eigenvalues of about -2.96 and -0.22
+    fn saddle() -> (ImpactMap, Impact) {
+        let mapper = ImpactMap::new(Parameters::new(2.8, 0.0, 0.8, 100).unwrap());
+
+        let orbit = OrbitSolver::default(&mapper).solve(mapper.impact_from_point(0.487, 0.336), 1, 1).unwrap();
+
+        let orbit_impact = orbit.points()[0];
+
+        (mapper, orbit_impact)
+    }
+
+    // Distance from a point to the nearest joined segment of a manifold
+    fn distance_to_curve(manifold: &Manifold, point: Impact) -> f64 {
+        let points = manifold.points();
+
+        (1..points.len()).filter(|&i| manifold.is_joined(i)).map(|i| {
+            let start = (0.0, points[i - 1].velocity());
+            let end = (phase_difference(points[i].phase(), points[i - 1].phase()), points[i].velocity());
+            let target = (phase_difference(point.phase(), points[i - 1].phase()), point.velocity());
+
+            let segment = (end.0 - start.0, end.1 - start.1);
+            let length_squared = segment.0 * segment.0 + segment.1 * segment.1;
+            let t = if length_squared > 0.0 {(((target.0 - start.0) * segment.0 + (target.1 - start.1) * segment.1) / length_squared).clamp(0.0, 1.0)} else {0.0};
+
+            (target.0 - start.0 - t * segment.0).hypot(target.1 - start.1 - t * segment.1)
+        }).fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn test_unstable_manifold_is_invariant() {
+        let (mapper, orbit) = saddle();
+
+        let settings = ManifoldSettings{maximum_points: 300, maximum_arc_length: 1.0, ..ManifoldSettings::default()};
+
+        let manifold = ManifoldGenerator::new(&mapper, settings).generate(orbit, 1, ManifoldKind::Unstable, ManifoldBranch::Positive).unwrap();
+
+        assert!(manifold.eigenvalue() < -1.0);
+        assert!(manifold.points().len() > 20);
+
+        // Successive joined points are close together
+        for i in (1..manifold.points().len()).filter(|&i| manifold.is_joined(i)) {
+            assert!(impact_distance(manifold.points()[i - 1], manifold.points()[i]) <= settings.maximum_separation);
+        }
+
+        // Images of points near the start of the curve lie further along it
+        for i in [3, 6, 9] {
+            let image = mapper.apply_repeatedly(manifold.points()[i], 2).unwrap();
+
+            assert!(distance_to_curve(&manifold, image) < 1e-3, "Image {:?} of point {} is off the manifold", image, i);
+        }
+    }
+
+    #[test]
+    fn test_stable_manifold_converges_on_orbit() {
+        let (mapper, orbit) = saddle();
+
+        let settings = ManifoldSettings{maximum_points: 100, maximum_arc_length: 0.2, ..ManifoldSettings::default()};
+
+        let manifold = ManifoldGenerator::new(&mapper, settings).generate(orbit, 1, ManifoldKind::Stable, ManifoldBranch::Negative).unwrap();
+
+        assert!(manifold.eigenvalue().abs() < 1.0);
+
+        let start = manifold.points()[5];
+
+        let image = mapper.apply_repeatedly(start, 4).unwrap();
+
+        assert!(impact_distance(image, orbit) < 0.1 * impact_distance(start, orbit));
+    }
+
+    #[test]
+    fn test_stable_orbit_has_no_manifolds() {
+        let mapper = ImpactMap::new(Parameters::new(2.0, 0.0, 0.8, 100).unwrap());
+
+        let orbit = mapper.iterate_from_point(0.2, 0.5, 200).final_impact();
+
+        let result = ManifoldGenerator::new(&mapper, ManifoldSettings::default()).generate(orbit, 1, ManifoldKind::Unstable, ManifoldBranch::Positive);
+
+        assert!(matches!(result, Err(ManifoldError::NotASaddle{..})));
+    }
+}
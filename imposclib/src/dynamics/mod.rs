@@ -3,6 +3,23 @@ pub mod impact;
 pub mod model_types;
 pub mod parameters;
 mod sticking;
-mod motion;
+pub mod motion;
 mod chatter;
-pub mod impact_map;
\ No newline at end of file
+pub mod impact_map;
+pub mod classification;
+pub mod linearisation;
+pub mod manifold;
+pub mod periodic_orbit;
+pub mod continuation;
+pub mod grazing;
+pub mod orbit_chart;
+pub mod hysteresis;
+pub mod schedule;
+pub mod limiting_cases;
+pub mod density;
+pub mod dimension;
+pub mod recurrence;
+pub mod symbolic;
+pub mod rotation;
+pub mod transient;
+pub mod basin;
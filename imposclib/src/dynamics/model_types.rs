@@ -11,7 +11,6 @@ pub type Coefficient = f64;
 pub enum ParameterError {
     ZeroForcingFrequency,
     NegativeForcingFrequency {frequency: Frequency },
-    ResonantForcingFrequency {frequency: Frequency },
     LargeCoefficientOfRestitution {coefficient: Coefficient},
     NegativeCoefficientOfRestitution {coefficient: Coefficient},
     ZeroMaximumPeriods
@@ -23,10 +22,23 @@ impl fmt::Display for ParameterError {
         match *self {
             ParameterError::ZeroForcingFrequency => write!(f, "Forcing frequency cannot be zero"),
             ParameterError::NegativeForcingFrequency{ref frequency} => write!(f, "The model cannot handle negative forcing frequencies {:?}", frequency),
-            ParameterError::ResonantForcingFrequency{ref frequency} => write!(f, "A forcing frequency of {:?} is a resonant case with unbounded solutions", frequency),
             ParameterError::LargeCoefficientOfRestitution{ref coefficient} => write!(f, "A coefficient of restitution of {:?} > 1 will generate unbounded solutions", coefficient),
             ParameterError::NegativeCoefficientOfRestitution{ref coefficient} => write!(f, "A coefficient of restitution of {:?} < 0> will generate unphysical solutions", coefficient),
             ParameterError::ZeroMaximumPeriods => write!(f, "Maximum number of forcing periods to detect impact must be > 0")
         }
     }
+}
+
+// Errors in an initial state of motion, as opposed to in the parameters of the system
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum StateError {
+    BeyondObstacle {displacement: Distance, offset: Distance}
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::BeyondObstacle{ref displacement, ref offset} => write!(f, "An initial displacement of {:?} is beyond the obstacle at {:?}", displacement, offset)
+        }
+    }
 }
\ No newline at end of file
@@ -20,6 +20,10 @@ pub struct StateOfMotion {
 }
 
 impl StateOfMotion {
+    pub fn new(time: Time, displacement: Distance, velocity: Velocity) -> StateOfMotion {
+        StateOfMotion{time, displacement, velocity}
+    }
+
     pub fn time(&self) -> Time {
         self.time
     }
@@ -49,33 +53,57 @@ impl LongExcursionChecker {
         LongExcursionChecker{period: converter.period(), from_time: from_time, maximum_periods: maximum_periods}
     }
 
+    // Works in either direction of time, so that backward searches are also bounded
     pub fn check(&self, time: Time) -> bool {
-        time - self.from_time > (self.maximum_periods as f64) * self.period
+        (time - self.from_time).abs() > (self.maximum_periods as f64) * self.period
     }
 }
 
+/// The motion from one impact to the next. Times are measured from the start of the forcing period of the impact
+/// (see `NextImpactResult`), which keeps them small, so that the trigonometric terms stay accurate however many
+/// periods a run has lasted.
+///
+/// The motion x'' + x = cos(ωt) through a state (t₀, x₀, v₀) is written as the free oscillation through that state
+/// plus the response to the forcing from rest at t₀, which is
+///
+/// x_f = cos(ωt₀)C(λ) - sin(ωt₀)S(λ), with λ = t - t₀, C(λ) = (cos ωλ - cos λ)/(1 - ω²), S(λ) = (sin ωλ - ω sin λ)/(1 - ω²)
+///
+/// Unlike the usual split into γcos(ωt) and a free oscillation, whose amplitudes both grow without bound as ω → 1,
+/// C and S can be evaluated without cancellation close to resonance, and at ω = 1 they become the secular terms
+/// (λ sin λ)/2 and (sin λ - λ cos λ)/2.
 #[derive(Debug)]
 pub struct MotionAtTime {	
 	// Coefficients for time evolution of the system from one impact to the next 
 	parameters: Parameters,
 	impact_time: Time,
-	cos_coefficient: Coefficient,
-	sin_coefficient: Coefficient,
+	initial_displacement: Distance,
+	initial_velocity: Velocity,
+	cos_forcing: Coefficient,
+	sin_forcing: Coefficient,
 	long_excursion_checker: LongExcursionChecker
 }
 
 impl MotionAtTime {
     fn new(parameters: Parameters, impact: Impact) -> MotionAtTime {
-        let cos_coefficient = parameters.obstacle_offset() - parameters.gamma() * (parameters.forcing_frequency()*impact.time()).cos();
-        
-        let sin_coefficient = -(parameters.coefficient_of_restitution() * impact.velocity()) + parameters.forcing_frequency() * parameters.gamma() * (parameters.forcing_frequency()*impact.time()).sin();
+        // Immediately after an impact the mass is at the obstacle, moving away from it
+        MotionAtTime::from_state(parameters, StateOfMotion{
+            time: impact.time(),
+            displacement: parameters.obstacle_offset(),
+            velocity: -(parameters.coefficient_of_restitution() * impact.velocity())})
+    }
 
-        return MotionAtTime{
-            parameters: parameters, 
-            impact_time: impact.time(), 
-            cos_coefficient: cos_coefficient, 
-            sin_coefficient: sin_coefficient, 
-            long_excursion_checker: LongExcursionChecker::new(parameters.maximum_periods(), parameters.converter(), impact.time())}
+    // Motion through an arbitrary state, with no assumption that the state is at the obstacle
+    fn from_state(parameters: Parameters, state: StateOfMotion) -> MotionAtTime {
+        let forcing_angle = parameters.forcing_frequency() * state.time;
+
+        MotionAtTime{
+            parameters, 
+            impact_time: state.time, 
+            initial_displacement: state.displacement,
+            initial_velocity: state.velocity,
+            cos_forcing: forcing_angle.cos(),
+            sin_forcing: forcing_angle.sin(),
+            long_excursion_checker: LongExcursionChecker::new(parameters.maximum_periods(), parameters.converter(), state.time)}
     }
 
     pub fn state(&self, time: Time) -> StateOfMotion {
@@ -84,11 +112,50 @@ impl MotionAtTime {
         let cos_lambda = lambda.cos();
         let sin_lambda = lambda.sin();
 
+        let (c, s, c_derivative) = self.forced_response(lambda);
+
+        let s_derivative = self.parameters.forcing_frequency() * c;
+
         StateOfMotion{time: time,
-            displacement: self.cos_coefficient * cos_lambda + self.sin_coefficient * sin_lambda + 
-                self.parameters.gamma() * (time * self.parameters.forcing_frequency()).cos(),
-            velocity: self.sin_coefficient * cos_lambda - self.cos_coefficient * sin_lambda - 
-                self.parameters.forcing_frequency() * self.parameters.gamma() * (time * self.parameters.forcing_frequency()).sin() }
+            displacement: self.initial_displacement * cos_lambda + self.initial_velocity * sin_lambda +
+                self.cos_forcing * c - self.sin_forcing * s,
+            velocity: self.initial_velocity * cos_lambda - self.initial_displacement * sin_lambda +
+                self.cos_forcing * c_derivative - self.sin_forcing * s_derivative }
+    }
+
+    // C(λ), S(λ) and C'(λ). The differences of sines and cosines are rewritten as products, leaving the factor
+    // (1 - ω) to cancel against a sinc function, so that there is no division by a small number.
+    fn forced_response(&self, lambda: Time) -> (Coefficient, Coefficient, Coefficient) {
+        let frequency = self.parameters.forcing_frequency();
+
+        let mean_angle = 0.5 * (1.0 + frequency) * lambda;
+        let half_difference = 0.5 * (1.0 - frequency) * lambda;
+
+        let sinc = if half_difference == 0.0 {1.0} else {half_difference.sin() / half_difference};
+
+        let beat = lambda * sinc / (1.0 + frequency);
+
+        let c = mean_angle.sin() * beat;
+        let s = (lambda.sin() / (1.0 + frequency)) - mean_angle.cos() * beat;
+        let c_derivative = ((frequency * lambda).sin() / (1.0 + frequency)) + mean_angle.cos() * beat;
+
+        (c, s, c_derivative)
+    }
+
+    // Least upper bound on the displacement if the motion were never interrupted by an impact: the amplitude of
+    // the free oscillation plus that of the forced response γcos(ωt). (It is only attained if ω is irrational.)
+    // At resonance the motion grows without bound.
+    pub fn maximum_displacement(&self) -> Distance {
+        let gamma = self.parameters.gamma();
+
+        if gamma.is_infinite() {
+            return Distance::INFINITY;
+        }
+
+        let cos_coefficient = self.initial_displacement - gamma * self.cos_forcing;
+        let sin_coefficient = self.initial_velocity + self.parameters.forcing_frequency() * gamma * self.sin_forcing;
+
+        cos_coefficient.hypot(sin_coefficient) + gamma.abs()
     }
 
     pub fn constrained_state(&self, time: Time) -> StateOfMotion {
@@ -110,6 +177,10 @@ impl MotionGenerator {
         MotionAtTime::new(self.parameters, impact)
     }
 
+    pub fn generate_from_state(&self, state: StateOfMotion) -> MotionAtTime {
+        MotionAtTime::from_state(self.parameters, state)
+    }
+
     pub fn parameters(&self) -> Parameters {
         self.parameters
     }
@@ -160,37 +231,95 @@ impl MotionBetweenImpacts {
 
     pub fn next_impact(&self, impact: Impact) -> NextImpactResult {
 
-        let mut result = NextImpactResult::new(&self, impact);
+        let result = NextImpactResult::new(&self, impact);
+
+        // NextImpactResult accounts for sticking in the initial impact
+        let initial_state = result.last();
+
+        let motion_model = self.motion_generator.generate(
+            self.impact_generator.generate(initial_state.time, initial_state.velocity)
+        );
+
+        self.search(result, motion_model, 1.0)
+    }
+
+    // The first impact reached from a state in free flight (i.e. strictly short of the obstacle)
+    pub fn first_impact(&self, state: StateOfMotion) -> NextImpactResult {
+        let converter = self.motion_generator.parameters().converter();
+
+        let (origin, phase) = converter.periods_and_phase(state.time);
+
+        let state = StateOfMotion{time: converter.time_into_cycle(phase), ..state};
+
+        let result = NextImpactResult{motion: vec![state], found_impact: false, origin};
+
+        let motion_model = self.motion_generator.generate_from_state(state);
+
+        self.search(result, motion_model, 1.0)
+    }
+
+    // Runs the motion arriving at an impact backwards in time to the previous time it left the obstacle. The
+    // states in the result are in reverse order of time, so the last one is where the motion left the obstacle.
+    pub fn previous_impact(&self, impact: Impact) -> NextImpactResult {
+        let arrival = StateOfMotion{time: impact.time_in_period(), displacement: self.offset, velocity: impact.velocity()};
+
+        let result = NextImpactResult{motion: vec![arrival], found_impact: false, origin: impact.periods()};
+
+        let motion_model = self.motion_generator.generate_from_state(arrival);
+
+        self.search(result, motion_model, -1.0)
+    }
+
+    // Searches from the last state in `result` for the next time the motion reaches the obstacle, going forwards
+    // in time if `direction` is positive and backwards if it is negative
+    fn search(&self, mut result: NextImpactResult, motion_model: MotionAtTime, direction: f64) -> NextImpactResult {
 
         result.found_impact = true;
 
-        let mut step_size = self.search.initial_step_size;
+        let mut step_size = direction.signum() * self.search.initial_step_size;
 
-        // NextImpactResult accounts for sticking in the initial impact
-        let initial_state = result.motion.last().unwrap();
+        let start_time = result.last().time;
 
-        let mut current_time = initial_state.time;
+        let mut current_time = start_time;
 
-        let motion_model = self.motion_generator.generate(
-            self.impact_generator.generate(current_time, initial_state.velocity)
-        );
+        let mut previous_velocity = motion_model.state(start_time).velocity;
 
         while step_size.abs() > self.search.minimum_step_size && result.found_impact {
             current_time += step_size;
 
-            let current_state = motion_model.state(current_time);
+            let mut current_state = motion_model.state(current_time);
+
+            // While marching, an excursion beyond the obstacle which starts and ends between two steps would be
+            // missed, so look for the maximum of the displacement in between whenever the velocity changes sign.
+            // If it is beyond the obstacle, the bisection continues from there.
+            let passed_maximum = previous_velocity * direction > 0.0 && current_state.velocity * direction <= 0.0;
+
+            previous_velocity = current_state.velocity;
 
-            // Update step size - this is the bisection search algorithm
-            if current_state.displacement < self.offset {
+            if passed_maximum && step_size * direction > 0.0 && step_size.abs() == self.search.initial_step_size && current_state.displacement < self.offset {
+                let previous_time = current_time - step_size;
+
+                if let Some(peak) = self.peak_between(&motion_model, previous_time, current_time, direction).filter(|peak| peak.displacement >= self.offset) {
+                    step_size = peak.time - previous_time;
+                    current_time = peak.time;
+                    current_state = peak;
+                }
+            }
+
+            // Update step size - this is the bisection search algorithm. The bisection can return to the start of
+            // the search, where the motion is at the obstacle, but that is where it leaves it rather than an impact.
+            // Taking it as the next impact would give no time of flight and reverse the velocity. Rounding in the
+            // steps means it may only come back to within a step of the start.
+            if current_state.displacement < self.offset || (current_time - start_time).abs() < self.search.minimum_step_size {
                 // only record the state if it is physical
                 // (i.e. non-penetrating)
                 result.grow(current_state);
 
-                if step_size < 0.0 {
+                if step_size * direction < 0.0 {
                     step_size *= -0.5;
                 }
             } else if current_state.displacement > self.offset {
-                if step_size > 0.0 {
+                if step_size * direction > 0.0 {
                     step_size *= -0.5;
                 }
             } else {
@@ -202,10 +331,140 @@ impl MotionBetweenImpacts {
                 result.found_impact = false;
             }
         }
+
+        if result.found_impact {
+            self.refine(&mut result, &motion_model);
+        }
         
         result
     }
 
+    // The bisection only brackets the impact to within the minimum step size, i.e. an error of about 1e-6 in the
+    // time of every impact. That is fine for plotting trajectories, but not for anything which differences the
+    // map: the Jacobian, the shooting solver for periodic orbits and the continuation and grazing code built on
+    // it all need the impact to rounding error. So polish the last state with at most a few Newton steps, each
+    // of which costs one evaluation of the motion. The refinement is abandoned if it strays outside the
+    // bracket, as it can near grazing, where the velocity at the obstacle vanishes.
+    fn refine(&self, result: &mut NextImpactResult, motion_model: &MotionAtTime) {
+        const MAXIMUM_STEPS: u32 = 4;
+
+        let bracketed = result.last();
+
+        let mut state = bracketed;
+
+        for _ in 0..MAXIMUM_STEPS {
+            if state.velocity == 0.0 {
+                return;
+            }
+
+            let time = state.time - (state.displacement - self.offset) / state.velocity;
+
+            if (time - bracketed.time).abs() > 2.0 * self.search.minimum_step_size {
+                return;
+            }
+
+            state = motion_model.state(time);
+        }
+
+        if let Some(last) = result.motion.last_mut() {
+            *last = state.constrain(self.offset);
+        }
+    }
+
+    // How close the motion after an impact comes to the obstacle before it next reaches it, i.e. the smallest
+    // distance below the obstacle of any local maximum of the displacement in between. Returns `None` if the
+    // motion has no such maximum, or reaches no further impact.
+    pub fn clearance(&self, impact: Impact) -> Option<Distance> {
+        let next = self.next_impact(impact);
+
+        if !next.found_impact() {
+            return None;
+        }
+
+        let start = NextImpactResult::new(self, impact).last();
+
+        let motion_model = self.motion_generator.generate(self.impact_generator.generate(start.time, start.velocity));
+
+        let end_time = next.last().time;
+
+        let mut clearance: Option<Distance> = None;
+
+        let mut time = start.time;
+
+        while time < end_time {
+            let step_end = (time + self.search.initial_step_size).min(end_time);
+
+            if let Some(peak) = self.peak_between(&motion_model, time, step_end, 1.0) {
+                let gap = self.offset - peak.displacement;
+
+                clearance = Some(clearance.map_or(gap, |current| current.min(gap)));
+            }
+
+            time = step_end;
+        }
+
+        clearance
+    }
+
+    // The local maximum of the displacement nearest to `time` in the motion after `impact`, as if the obstacle
+    // were not there, looking no further than `reach` either side. Times are measured as for `next_impact`.
+    // Unlike the maxima checked while searching for impacts, this one is polished with secant steps on the
+    // velocity, which is close to linear in time near a maximum.
+    pub fn peak_near(&self, impact: Impact, time: Time, reach: Time) -> Option<StateOfMotion> {
+        const MAXIMUM_STEPS: u32 = 8;
+
+        let start = NextImpactResult::new(self, impact).last();
+
+        let motion_model = self.motion_generator.generate(self.impact_generator.generate(start.time, start.velocity));
+
+        let bracketed = self.peak_between(&motion_model, time - reach, time + reach, 1.0)?;
+
+        let (mut earlier, mut later) = (bracketed.time - self.search.minimum_step_size, bracketed.time + self.search.minimum_step_size);
+
+        let (mut earlier_velocity, mut later_velocity) = (motion_model.state(earlier).velocity, motion_model.state(later).velocity);
+
+        for _ in 0..MAXIMUM_STEPS {
+            if later_velocity == earlier_velocity {
+                break;
+            }
+
+            let next = later - later_velocity * (later - earlier) / (later_velocity - earlier_velocity);
+
+            earlier = later;
+            earlier_velocity = later_velocity;
+
+            later = next;
+            later_velocity = motion_model.state(later).velocity;
+        }
+
+        Some(motion_model.state(later))
+    }
+
+    // The local maximum of the displacement between two times, if there is one, i.e. if the velocity (taken in
+    // the direction of the search) changes from positive to non-positive
+    fn peak_between(&self, motion_model: &MotionAtTime, from: Time, to: Time, direction: f64) -> Option<StateOfMotion> {
+        let rising = |time: Time| motion_model.state(time).velocity * direction > 0.0;
+
+        if !rising(from) || rising(to) {
+            return None;
+        }
+
+        let (mut before, mut after) = (from, to);
+
+        while (after - before).abs() > self.search.minimum_step_size {
+            let middle = 0.5 * (before + after);
+
+            // At very large times the spacing of floating point numbers can exceed the minimum step
+            if middle == before || middle == after {
+                break;
+            }
+
+            if rising(middle) {before = middle} else {after = middle}
+        }
+
+        Some(motion_model.state(0.5 * (before + after)))
+    }
+
     pub fn generator(&self) -> MotionGenerator {
         self.motion_generator
     }
@@ -215,19 +474,24 @@ impl MotionBetweenImpacts {
     }
 }
 
+/// The states of the motion between impacts. Their times are measured from the start of the forcing period which
+/// begins after `origin` whole periods, i.e. the period in which the motion started.
 pub struct NextImpactResult {
 	motion: Vec<StateOfMotion>,
-	found_impact: bool
+	found_impact: bool,
+	origin: i64
 }
 
 impl NextImpactResult {
     fn new(motion: &MotionBetweenImpacts, impact: Impact) -> NextImpactResult {
 
         let mut trajectory: Vec<StateOfMotion> = vec![];
+
+        let local_impact = impact.in_first_period();
         
-        trajectory.push(StateOfMotion {time: impact.time(), displacement: motion.offset, velocity: impact.velocity()});
+        trajectory.push(StateOfMotion {time: local_impact.time(), displacement: motion.offset, velocity: impact.velocity()});
         
-        let release_impact = motion.sticking.check_impact(impact);
+        let release_impact = motion.sticking.check_impact(local_impact);
         
         if release_impact.new_impact() {
             trajectory.push(StateOfMotion{
@@ -236,7 +500,11 @@ impl NextImpactResult {
                 velocity: release_impact.impact().velocity()})
         }
 
-        NextImpactResult{motion: trajectory, found_impact: false}
+        NextImpactResult{motion: trajectory, found_impact: false, origin: impact.periods()}
+    }
+
+    pub fn origin(&self) -> i64 {
+        self.origin
     }
 
     pub fn grow(&mut self, state: StateOfMotion) -> () {
@@ -256,6 +524,7 @@ impl NextImpactResult {
 mod tests {
     use super::*;
     use super::super::impact::ImpactGenerator;
+    use float_eq::assert_float_eq;
 
     #[test]
     fn test_motion_at_time() {
@@ -273,6 +542,59 @@ mod tests {
         assert_eq!(state.velocity, -0.8);
     }
 
+    #[test]
+    fn test_forced_response_away_from_resonance() {
+        let parameters = Parameters::new(2.8, 0.0, 0.8, 100).unwrap();
+
+        let start = StateOfMotion::new(0.7, -0.3, 0.4);
+
+        let motion = MotionGenerator::new(parameters).generate_from_state(start);
+
+        // The textbook form, which is fine this far from resonance
+        let gamma = parameters.gamma();
+        let a = start.displacement() - gamma * (2.8 * start.time()).cos();
+        let b = start.velocity() + 2.8 * gamma * (2.8 * start.time()).sin();
+
+        for &time in [0.7, 1.3, 2.9, 10.0].iter() {
+            let lambda = time - start.time();
+            let state = motion.state(time);
+
+            assert_float_eq!(state.displacement(), a * lambda.cos() + b * lambda.sin() + gamma * (2.8 * time).cos(), abs <= 1e-12);
+            assert_float_eq!(state.velocity(), b * lambda.cos() - a * lambda.sin() - 2.8 * gamma * (2.8 * time).sin(), abs <= 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_resonant_motion() {
+        let parameters = Parameters::new(1.0, 0.0, 0.8, 100).unwrap();
+
+        // From rest at the centre the motion is the secular solution x = (t sin t)/2
+        let motion = MotionGenerator::new(parameters).generate_from_state(StateOfMotion::new(0.0, 0.0, 0.0));
+
+        for &time in [0.5, 3.0, 40.0].iter() {
+            let state = motion.state(time);
+
+            assert_float_eq!(state.displacement(), 0.5 * time * time.sin(), abs <= 1e-12);
+            assert_float_eq!(state.velocity(), 0.5 * (time.sin() + time * time.cos()), abs <= 1e-12);
+        }
+
+        assert!(motion.maximum_displacement().is_infinite());
+
+        // Close to resonance the motion approaches the resonant motion, rather than being swamped by rounding
+        let start = StateOfMotion::new(0.3, 0.1, -0.2);
+
+        let resonant = MotionGenerator::new(parameters).generate_from_state(start).state(25.0);
+
+        for &frequency in [1.0 - 1e-9, 1.0 + 1e-9].iter() {
+            let nearby = Parameters::new(frequency, 0.0, 0.8, 100).unwrap();
+
+            let state = MotionGenerator::new(nearby).generate_from_state(start).state(25.0);
+
+            assert_float_eq!(state.displacement(), resonant.displacement(), abs <= 1e-6);
+            assert_float_eq!(state.velocity(), resonant.velocity(), abs <= 1e-6);
+        }
+    }
+
     #[test]
     fn test_long_excursions() {
         let maximum_periods = 100u32;
@@ -284,6 +606,104 @@ mod tests {
 
         assert!(!checker.check(good_time));
         assert!(checker.check(bad_time));
+        assert!(!checker.check(-good_time));
+        assert!(checker.check(-bad_time));
+    }
+
+    #[test]
+    fn test_motion_from_state() {
+        let parameters = Parameters::new(2.8, 0.0, 0.8, 100).unwrap();
+
+        let motion_generator = MotionGenerator::new(parameters);
+
+        let initial_state = StateOfMotion::new(0.7, -0.3, 0.2);
+
+        let state = motion_generator.generate_from_state(initial_state).state(initial_state.time());
+
+        assert_float_eq!(state.displacement(), initial_state.displacement(), abs <= 1e-12);
+        assert_float_eq!(state.velocity(), initial_state.velocity(), abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_first_impact_from_rest() {
+        // From rest at x = 0 the displacement can't exceed 2|γ| ≈ 0.089
+        let parameters = Parameters::new(4.85, 0.05, 0.8, 100).unwrap();
+
+        let motion = MotionBetweenImpacts::new(parameters);
+
+        let result = motion.first_impact(StateOfMotion::new(0.0, 0.0, 0.0));
+
+        assert!(result.found_impact());
+        assert_float_eq!(result.last().displacement(), 0.05, abs <= 1e-5);
+        assert!(result.last().velocity() > 0.0);
+
+        let unreachable = MotionBetweenImpacts::new(Parameters::new(4.85, 0.1, 0.8, 100).unwrap());
+
+        assert!(!unreachable.first_impact(StateOfMotion::new(0.0, 0.0, 0.0)).found_impact());
+    }
+
+    #[test]
+    fn test_impacts_are_located_beyond_bisection_tolerance() {
+        let parameters = Parameters::new(2.8, 0.1, 0.8, 100).unwrap();
+
+        let motion = MotionBetweenImpacts::new(parameters);
+
+        let impact = ImpactGenerator::new(parameters.converter()).generate(0.3, 0.5);
+
+        let result = motion.next_impact(impact);
+
+        assert!(result.found_impact());
+
+        // The motion itself, rather than the recorded state, which is constrained to lie on the obstacle
+        let arrival = motion.motion(impact).state(result.last().time());
+
+        assert_float_eq!(arrival.displacement(), 0.1, abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_short_excursion_is_not_missed() {
+        // Just below the obstacle and rising slowly, at a time when the forcing has no effect on the acceleration,
+        // the mass rises about 3e-4 above the obstacle and falls back below it within a single search step
+        let parameters = Parameters::new(2.8, 0.5, 0.8, 100).unwrap();
+
+        let motion = MotionBetweenImpacts::new(parameters);
+
+        let start = StateOfMotion::new(0.5 * std::f64::consts::PI / 2.8, 0.5 - 1e-4, 0.02);
+
+        let model = motion.generator().generate_from_state(start);
+
+        assert!(model.state(start.time() + 0.1).displacement() < 0.5);
+
+        let result = motion.first_impact(start);
+
+        assert!(result.found_impact());
+        assert!(result.last().time() < start.time() + 0.1);
+        assert_float_eq!(result.last().displacement(), 0.5, abs <= 1e-9);
+    }
+
+    #[test]
+    fn test_previous_impact_reverses_next_impact() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let motion = MotionBetweenImpacts::new(parameters);
+
+        let impact_generator = ImpactGenerator::new(parameters.converter());
+
+        let start = impact_generator.generate(0.3, 0.5);
+
+        let forward = motion.next_impact(start);
+
+        let arrival = impact_generator.generate_after_periods(forward.origin(), forward.last().time(), forward.last().velocity());
+
+        let backward = motion.previous_impact(arrival);
+
+        // Times in each result are measured from the start of the period of the impact it started from
+        let departure_time = parameters.converter().time_after_periods(backward.origin(), backward.last().time());
+
+        let backward = backward.last();
+
+        assert_float_eq!(departure_time, start.time(), abs <= 1e-5);
+        assert_float_eq!(backward.velocity(), -0.8 * start.velocity(), abs <= 1e-4);
     }
 
     #[test]
@@ -298,4 +718,4 @@ mod tests {
 
         assert!(impact_result.found_impact);
     }
-}
\ No newline at end of file
+}
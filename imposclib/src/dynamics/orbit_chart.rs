@@ -0,0 +1,231 @@
+//
+// Charts of the type of orbit reached over a grid of two of the system parameters
+//
+use super::classification::OrbitClassifier as OrbitClassifier;
+use super::classification::OrbitType as OrbitType;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::model_types::Phase as Phase;
+use super::model_types::Velocity as Velocity;
+use super::parameters::Parameters as Parameters;
+use super::parameters::ParameterName as ParameterName;
+use rayon::prelude::*;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The label of a cell in an orbit chart. This is the orbit type without the amplitude of non-impacting motion,
+/// which would otherwise make every non-impacting cell different.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ChartLabel {
+    Periodic {impacts: u32, periods: u32},
+    Chatter,
+    NonImpacting,
+    LongExcursions,
+    Chaotic,
+    /// The parameters of the cell are not valid, e.g. a coefficient of restitution greater than one
+    Invalid
+}
+
+impl From<OrbitType> for ChartLabel {
+    fn from(orbit_type: OrbitType) -> ChartLabel {
+        match orbit_type {
+            OrbitType::Periodic{impacts, periods} => ChartLabel::Periodic{impacts, periods},
+            OrbitType::Chatter => ChartLabel::Chatter,
+            OrbitType::NonImpacting{..} => ChartLabel::NonImpacting,
+            OrbitType::LongExcursions => ChartLabel::LongExcursions,
+            OrbitType::Aperiodic => ChartLabel::Chaotic
+        }
+    }
+}
+
+impl fmt::Display for ChartLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChartLabel::Periodic{impacts, periods} => write!(f, "({}, {})", impacts, periods),
+            ChartLabel::Chatter => write!(f, "chatter"),
+            ChartLabel::NonImpacting => write!(f, "non-impacting"),
+            ChartLabel::LongExcursions => write!(f, "long excursions"),
+            ChartLabel::Chaotic => write!(f, "chaotic"),
+            ChartLabel::Invalid => write!(f, "invalid")
+        }
+    }
+}
+
+/// Evenly spaced values of one of the parameters, including both ends of the range
+#[derive(Debug, Copy, Clone)]
+pub struct ChartAxis {
+    parameter: ParameterName,
+    minimum: f64,
+    maximum: f64,
+    steps: u32
+}
+
+impl ChartAxis {
+    pub fn new(parameter: ParameterName, minimum: f64, maximum: f64, steps: u32) -> ChartAxis {
+        ChartAxis{parameter, minimum, maximum, steps}
+    }
+
+    pub fn parameter(&self) -> ParameterName {
+        self.parameter
+    }
+
+    pub fn values(&self) -> Vec<f64> {
+        evenly_spaced(self.minimum, self.maximum, self.steps)
+    }
+}
+
+/// `steps` evenly spaced values from `minimum` to `maximum` inclusive, or just `minimum` if there is only one step
+pub fn evenly_spaced(minimum: f64, maximum: f64, steps: u32) -> Vec<f64> {
+    match steps {
+        0 => vec![],
+        1 => vec![minimum],
+        steps => (0..steps).map(|i| minimum + (maximum - minimum) * (i as f64) / ((steps - 1) as f64)).collect()
+    }
+}
+
+/// Replaces each label by an index into a legend of the distinct labels, in the order in which they are first found
+pub fn index_labels(labels: &[ChartLabel]) -> (Vec<usize>, Vec<ChartLabel>) {
+    let mut legend: Vec<ChartLabel> = vec![];
+
+    let cells = labels.iter().map(|label| {
+        legend.iter().position(|known| known == label).unwrap_or_else(|| {
+            legend.push(*label);
+            legend.len() - 1
+        })
+    }).collect();
+
+    (cells, legend)
+}
+
+/// Orbit types over a grid of parameter values. Cells are held by row, so that the cell for the i-th value on the
+/// horizontal axis and the j-th on the vertical one is at j * columns + i, and each holds an index into the legend.
+#[derive(Debug, Clone)]
+pub struct OrbitChart {
+    horizontal: Vec<f64>,
+    vertical: Vec<f64>,
+    cells: Vec<usize>,
+    legend: Vec<ChartLabel>
+}
+
+impl OrbitChart {
+    pub fn horizontal(&self) -> &[f64] {
+        &self.horizontal
+    }
+
+    pub fn vertical(&self) -> &[f64] {
+        &self.vertical
+    }
+
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+
+    /// The distinct labels in the chart, in the order in which they are first found scanning the cells
+    pub fn legend(&self) -> &[ChartLabel] {
+        &self.legend
+    }
+
+    #[cfg(test)]
+    pub fn label(&self, column: usize, row: usize) -> ChartLabel {
+        self.legend[self.cells[row * self.horizontal.len() + column]]
+    }
+}
+
+/// Builds orbit charts by iterating the map from the same initial impact for every cell of the grid, in parallel,
+/// and classifying where the trajectory ends up. Parameters not on either axis are taken from `parameters`.
+pub struct OrbitChartGenerator {
+    parameters: Parameters,
+    horizontal: ChartAxis,
+    vertical: ChartAxis,
+    initial_point: (Phase, Velocity),
+    num_iterations: u32
+}
+
+impl OrbitChartGenerator {
+    pub fn new(parameters: Parameters, horizontal: ChartAxis, vertical: ChartAxis, initial_point: (Phase, Velocity), num_iterations: u32) -> OrbitChartGenerator {
+        OrbitChartGenerator{parameters, horizontal, vertical, initial_point, num_iterations}
+    }
+
+    /// The chart, or `None` if `cancellation` is set before every cell has been started
+    pub fn generate(&self, cancellation: &AtomicBool) -> Option<OrbitChart> {
+        let horizontal = self.horizontal.values();
+        let vertical = self.vertical.values();
+
+        let labels: Vec<ChartLabel> = (0..horizontal.len() * vertical.len()).into_par_iter()
+            .map(|cell| if cancellation.load(Ordering::Relaxed) {
+                None
+            } else {
+                Some(self.classify(horizontal[cell % horizontal.len()], vertical[cell / horizontal.len()]))
+            })
+            .collect::<Option<Vec<ChartLabel>>>()?;
+
+        let (cells, legend) = index_labels(&labels);
+
+        Some(OrbitChart{horizontal, vertical, cells, legend})
+    }
+
+    fn classify(&self, horizontal: f64, vertical: f64) -> ChartLabel {
+        let parameters = self.parameters.with_value(self.horizontal.parameter(), horizontal)
+            .and_then(|parameters| parameters.with_value(self.vertical.parameter(), vertical));
+
+        match parameters {
+            Ok(parameters) => {
+                let result = ImpactMap::new(parameters).iterate_from_point(self.initial_point.0, self.initial_point.1, self.num_iterations);
+
+                ChartLabel::from(OrbitClassifier::default(parameters).classify(&result))
+            },
+            Err(_) => ChartLabel::Invalid
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_axis_values() {
+        let axis = ChartAxis::new(ParameterName::Offset, -0.2, 0.2, 5);
+
+        let values = axis.values();
+
+        assert_eq!(values.len(), 5);
+        assert_eq!(values[0], -0.2);
+        assert_eq!(values[4], 0.2);
+        assert!((values[2]).abs() < 1e-15);
+
+        assert_eq!(ChartAxis::new(ParameterName::Offset, 0.1, 0.2, 1).values(), vec![0.1]);
+    }
+
+    #[test]
+    fn test_chart_matches_classification_of_each_cell() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let horizontal = ChartAxis::new(ParameterName::Frequency, 2.0, 4.85, 2);
+        let vertical = ChartAxis::new(ParameterName::Offset, -0.1, 0.0, 2);
+
+        let chart = OrbitChartGenerator::new(parameters, horizontal, vertical, (0.0, 0.0), 2000).generate(&AtomicBool::new(false)).unwrap();
+
+        assert_eq!(chart.cells().len(), 4);
+        assert_eq!(chart.label(0, 1), ChartLabel::Periodic{impacts: 1, periods: 1});
+        assert_eq!(chart.label(1, 0), ChartLabel::Periodic{impacts: 1, periods: 2});
+
+        // Every label in the legend is used, and only once
+        for (index, label) in chart.legend().iter().enumerate() {
+            assert!(chart.cells().contains(&index));
+            assert_eq!(chart.legend().iter().filter(|other| *other == label).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_invalid_cells() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let horizontal = ChartAxis::new(ParameterName::Frequency, 2.0, 2.0, 1);
+        let vertical = ChartAxis::new(ParameterName::CoefficientOfRestitution, 0.8, 1.2, 2);
+
+        let chart = OrbitChartGenerator::new(parameters, horizontal, vertical, (0.0, 0.0), 100).generate(&AtomicBool::new(false)).unwrap();
+
+        assert_eq!(chart.label(0, 1), ChartLabel::Invalid);
+        assert_eq!(format!("{}", ChartLabel::Invalid), "invalid");
+    }
+}
@@ -4,6 +4,34 @@ use super::model_types::Coefficient as Coefficient;
 use super::model_types::ParameterError as ParameterError;
 use super::forcing_phase::PhaseConverter as PhaseConverter;
 
+/// The system parameters which can be varied, e.g. in a continuation or a sweep
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ParameterName {
+    Frequency,
+    Offset,
+    CoefficientOfRestitution
+}
+
+impl ParameterName {
+    /// Looks up a parameter by the name it is given in the inputs to the library
+    pub fn from_name(name: &str) -> Option<ParameterName> {
+        match name {
+            "frequency" => Some(ParameterName::Frequency),
+            "offset" => Some(ParameterName::Offset),
+            "r" => Some(ParameterName::CoefficientOfRestitution),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ParameterName::Frequency => "frequency",
+            ParameterName::Offset => "offset",
+            ParameterName::CoefficientOfRestitution => "r"
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Parameters {
 	forcing_frequency: Frequency,
@@ -24,10 +52,6 @@ impl Parameters {
             error_list.push(converter_result.unwrap_err());
         }
 
-        if frequency == 1.0 {
-            error_list.push(ParameterError::ResonantForcingFrequency{frequency: frequency});
-        }
-
         if 1.0 < r {
             error_list.push(ParameterError::LargeCoefficientOfRestitution{coefficient: r});
         }
@@ -59,6 +83,7 @@ impl Parameters {
         self.obstacle_offset
     }
 
+    // The amplitude 1/(1 - ω²) of the forced harmonic response, which is infinite in the resonant case ω = 1
     pub fn gamma(&self) -> Coefficient {
         self.gamma
     }
@@ -67,9 +92,34 @@ impl Parameters {
         self.maximum_periods
     }
 
+    // The amplitude |γ| of the forced harmonic response x = γcos(ωt), if that response never reaches the
+    // obstacle (in which case it is a non-impacting periodic orbit)
+    pub fn non_impacting_amplitude(&self) -> Option<Distance> {
+        if self.gamma.abs() < self.obstacle_offset {Some(self.gamma.abs())} else {None}
+    }
+
     pub fn converter(&self) -> PhaseConverter {
         self.converter
     }
+
+    pub fn value(&self, name: ParameterName) -> f64 {
+        match name {
+            ParameterName::Frequency => self.forcing_frequency,
+            ParameterName::Offset => self.obstacle_offset,
+            ParameterName::CoefficientOfRestitution => self.coefficient_of_restitution
+        }
+    }
+
+    /// The same parameters, except for `name`, which takes the new `value`
+    pub fn with_value(&self, name: ParameterName, value: f64) -> Result<Parameters, Vec<ParameterError>> {
+        let (frequency, offset, r) = match name {
+            ParameterName::Frequency => (value, self.obstacle_offset, self.coefficient_of_restitution),
+            ParameterName::Offset => (self.forcing_frequency, value, self.coefficient_of_restitution),
+            ParameterName::CoefficientOfRestitution => (self.forcing_frequency, self.obstacle_offset, value)
+        };
+
+        Parameters::new(frequency, offset, r, self.maximum_periods)
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -94,7 +144,8 @@ mod tests {
             ParameterErrorTest{forcing_frequency: 2.8, coefficient_of_restitution: 0.8, obstacle_offset: -0.1, maximum_periods: 100, expected_errors: 0},
             ParameterErrorTest{forcing_frequency: 2.8, coefficient_of_restitution: -0.5, obstacle_offset: 0.1, maximum_periods: 100, expected_errors: 1},
             ParameterErrorTest{forcing_frequency: 0.0, coefficient_of_restitution: 2.3, obstacle_offset: 0.1, maximum_periods: 100, expected_errors: 2},
-            ParameterErrorTest{forcing_frequency: 1.0, coefficient_of_restitution: 1.2, obstacle_offset: -0.1, maximum_periods: 0, expected_errors: 3},
+            ParameterErrorTest{forcing_frequency: 1.0, coefficient_of_restitution: 1.2, obstacle_offset: -0.1, maximum_periods: 0, expected_errors: 2},
+            ParameterErrorTest{forcing_frequency: 1.0, coefficient_of_restitution: 0.8, obstacle_offset: 0.1, maximum_periods: 100, expected_errors: 0},
         ];
 
         for data in parameter_error_tests.iter() {
@@ -121,4 +172,25 @@ mod tests {
         assert_eq!(parameters.maximum_periods(), 100);
         assert_eq!(parameters.gamma(), -0.125)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_non_impacting_amplitude() {
+        assert_eq!(Parameters::new(3.0, 0.2, 0.8, 100).unwrap().non_impacting_amplitude(), Some(0.125));
+        assert_eq!(Parameters::new(3.0, 0.1, 0.8, 100).unwrap().non_impacting_amplitude(), None);
+        assert_eq!(Parameters::new(3.0, -0.2, 0.8, 100).unwrap().non_impacting_amplitude(), None);
+        assert_eq!(Parameters::new(1.0, 0.2, 0.8, 100).unwrap().non_impacting_amplitude(), None);
+    }
+
+    #[test]
+    fn test_varying_a_parameter() {
+        let parameters = Parameters::new(3.0, 0.0, 0.8, 100).unwrap();
+
+        let varied = parameters.with_value(ParameterName::from_name("offset").unwrap(), 0.1).unwrap();
+
+        assert_eq!(varied.value(ParameterName::Offset), 0.1);
+        assert_eq!(varied.value(ParameterName::Frequency), 3.0);
+        assert!(parameters.with_value(ParameterName::CoefficientOfRestitution, 1.2).is_err());
+        assert_eq!(ParameterName::from_name("phi"), None);
+        assert_eq!(ParameterName::from_name(ParameterName::CoefficientOfRestitution.name()), Some(ParameterName::CoefficientOfRestitution));
+    }
+}
@@ -0,0 +1,271 @@
+//
+// Numerical solution for (m, n) periodic orbits of the impact map by Newton shooting
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::linearisation::Jacobian as Jacobian;
+use super::linearisation::Eigenvalues as Eigenvalues;
+use super::linearisation::impact_distance as impact_distance;
+use super::model_types::Time as Time;
+use super::model_types::Velocity as Velocity;
+use std::fmt;
+
+/// How a Newton solve went: the number of iterations taken and the size of the final residual
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ShootingDiagnostics {
+    pub iterations: u32,
+    pub residual: f64
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ShootingError {
+    ZeroImpactsOrPeriods,
+    NoImpact {iterations: u32},
+    SingularJacobian {iterations: u32},
+    NotConverged {diagnostics: ShootingDiagnostics},
+    NoCandidateInTrajectory
+}
+
+impl fmt::Display for ShootingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShootingError::ZeroImpactsOrPeriods => write!(f, "A periodic orbit must have at least one impact and one forcing period"),
+            ShootingError::NoImpact{ref iterations} => write!(f, "The impact map failed to find an impact after {} Newton iterations", iterations),
+            ShootingError::SingularJacobian{ref iterations} => write!(f, "The Newton step was undefined after {} iterations", iterations),
+            ShootingError::NotConverged{ref diagnostics} => write!(f, "No convergence after {} Newton iterations, with residual {:e}", diagnostics.iterations, diagnostics.residual),
+            ShootingError::NoCandidateInTrajectory => write!(f, "The trajectory does not come close to repeating with the requested period")
+        }
+    }
+}
+
+/// An (m, n) periodic orbit: `impacts` impacts every `periods` forcing periods
+#[derive(Debug, Clone)]
+pub struct PeriodicOrbit {
+    impacts: u32,
+    periods: u32,
+    points: Vec<Impact>,
+    multipliers: Eigenvalues,
+    diagnostics: ShootingDiagnostics
+}
+
+impl PeriodicOrbit {
+    pub fn impacts(&self) -> u32 {
+        self.impacts
+    }
+
+    pub fn periods(&self) -> u32 {
+        self.periods
+    }
+
+    /// The successive impacts of one cycle of the orbit
+    pub fn points(&self) -> &[Impact] {
+        &self.points
+    }
+
+    /// Eigenvalues of the linearisation of one cycle of the orbit, which are the same whichever point of the
+    /// orbit it is taken about
+    pub fn multipliers(&self) -> Eigenvalues {
+        self.multipliers
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.multipliers.spectral_radius() < 1.0
+    }
+
+    pub fn diagnostics(&self) -> ShootingDiagnostics {
+        self.diagnostics
+    }
+}
+
+/// Solves F^m(p) = p for an impact p, subject to the m impacts taking exactly n forcing periods.
+///
+/// The unknowns are the time and velocity of the first impact, and the residual is the difference between
+/// those and the time (less n periods) and velocity of the m-th impact. Working with times rather than phases
+/// is what imposes the constraint on the elapsed time. Steps are damped if they fail to reduce the residual,
+/// so unstable orbits can be found as readily as stable ones, given a guess close enough.
+pub struct OrbitSolver<'a> {
+    mapper: &'a ImpactMap,
+    tolerance: f64,
+    maximum_iterations: u32
+}
+
+impl<'a> OrbitSolver<'a> {
+    pub fn new(mapper: &'a ImpactMap, tolerance: f64, maximum_iterations: u32) -> OrbitSolver<'a> {
+        OrbitSolver{mapper, tolerance, maximum_iterations}
+    }
+
+    pub fn default(mapper: &'a ImpactMap) -> OrbitSolver<'a> {
+        OrbitSolver::new(mapper, 1e-10, 50)
+    }
+
+    pub fn solve(&self, guess: Impact, impacts: u32, periods: u32) -> Result<PeriodicOrbit, ShootingError> {
+        const MINIMUM_DAMPING: f64 = 1.0 / 1024.0;
+
+        if impacts == 0 || periods == 0 {
+            return Err(ShootingError::ZeroImpactsOrPeriods);
+        }
+
+        let period = self.mapper.converter().period();
+
+        let mut state = (self.mapper.converter().time_into_cycle(guess.phase()), guess.velocity());
+
+        let mut residual = self.residual(state, impacts, periods).ok_or(ShootingError::NoImpact{iterations: 0})?;
+
+        for iteration in 0..self.maximum_iterations {
+            if norm(residual) < self.tolerance {
+                return self.orbit(state, impacts, periods, ShootingDiagnostics{iterations: iteration, residual: norm(residual)});
+            }
+
+            let jacobian = Jacobian::of_return_map(self.mapper, self.mapper.generate_impact(state.0, state.1), impacts)
+                .ok_or(ShootingError::NoImpact{iterations: iteration})?;
+
+            // In (time, velocity) coordinates, less the identity
+            let shifted = Jacobian::new([
+                [jacobian.entry(0, 0) - 1.0, period * jacobian.entry(0, 1)],
+                [jacobian.entry(1, 0) / period, jacobian.entry(1, 1) - 1.0]]);
+
+            let step = shifted.solve(residual).ok_or(ShootingError::SingularJacobian{iterations: iteration})?;
+
+            let mut damping = 1.0;
+
+            loop {
+                let trial = (state.0 - damping * step.0, state.1 - damping * step.1);
+
+                if let Some(trial_residual) = self.residual(trial, impacts, periods) {
+                    if norm(trial_residual) < norm(residual) || damping <= MINIMUM_DAMPING {
+                        state = trial;
+                        residual = trial_residual;
+                        break;
+                    }
+                } else if damping <= MINIMUM_DAMPING {
+                    return Err(ShootingError::NoImpact{iterations: iteration});
+                }
+
+                damping *= 0.5;
+            }
+        }
+
+        if norm(residual) < self.tolerance {
+            return self.orbit(state, impacts, periods, ShootingDiagnostics{iterations: self.maximum_iterations, residual: norm(residual)});
+        }
+
+        Err(ShootingError::NotConverged{diagnostics: ShootingDiagnostics{iterations: self.maximum_iterations, residual: norm(residual)}})
+    }
+
+    /// Solves for an orbit starting from the impact in `trajectory` which comes closest to recurring after
+    /// `impacts` impacts and `periods` periods. Chaotic trajectories repeatedly pass close to the unstable
+    /// orbits embedded in them, so this is how those orbits can be found.
+    pub fn solve_from_trajectory(&self, trajectory: &[Impact], impacts: u32, periods: u32) -> Result<PeriodicOrbit, ShootingError> {
+        if impacts == 0 || periods == 0 {
+            return Err(ShootingError::ZeroImpactsOrPeriods);
+        }
+
+        let converter = self.mapper.converter();
+
+        let m = impacts as usize;
+
+        let guess = (0..trajectory.len().saturating_sub(m))
+            .filter(|&i| (trajectory[i + m].time_since(trajectory[i]) / converter.period()).round() as i32 == periods as i32)
+            .min_by(|&i, &j| impact_distance(trajectory[i], trajectory[i + m]).total_cmp(&impact_distance(trajectory[j], trajectory[j + m])))
+            .ok_or(ShootingError::NoCandidateInTrajectory)?;
+
+        self.solve(trajectory[guess], impacts, periods)
+    }
+
+    // (t_m - t_0 - nT, v_m - v_0) after m impacts from an impact at time t_0 with velocity v_0
+    fn residual(&self, state: (Time, Velocity), impacts: u32, periods: u32) -> Option<(Time, Velocity)> {
+        // A step past grazing can reach impacts with negative velocity, which are unphysical
+        if state.1 <= 0.0 {
+            return None;
+        }
+
+        let image = self.mapper.apply_repeatedly(self.mapper.generate_impact(state.0, state.1), impacts)?;
+
+        Some((image.time() - state.0 - (periods as f64) * self.mapper.converter().period(), image.velocity() - state.1))
+    }
+
+    fn orbit(&self, state: (Time, Velocity), impacts: u32, periods: u32, diagnostics: ShootingDiagnostics) -> Result<PeriodicOrbit, ShootingError> {
+        let no_impact = ShootingError::NoImpact{iterations: diagnostics.iterations};
+
+        let first = self.mapper.impact_from_point(self.mapper.converter().time_to_phase(state.0), state.1);
+
+        let mut points = vec![first];
+
+        for _ in 1..impacts {
+            let next = self.mapper.apply_repeatedly(*points.last().unwrap(), 1).ok_or(no_impact)?;
+            points.push(next);
+        }
+
+        let multipliers = Jacobian::of_return_map(self.mapper, first, impacts).ok_or(no_impact)?.eigenvalues();
+
+        Ok(PeriodicOrbit{impacts, periods, points, multipliers, diagnostics})
+    }
+}
+
+fn norm(vector: (f64, f64)) -> f64 {
+    vector.0.hypot(vector.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parameters::Parameters;
+    use float_eq::assert_float_eq;
+
+    #[test]
+    fn test_finds_stable_orbit() {
+        let mapper = ImpactMap::new(Parameters::new(4.85, -0.1, 0.8, 100).unwrap());
+
+        let orbit = OrbitSolver::default(&mapper).solve(mapper.impact_from_point(0.47, 0.59), 1, 2).unwrap();
+
+        assert!(orbit.is_stable());
+        assert!(orbit.diagnostics().residual < 1e-10);
+
+        // The attractor found by iterating
+        let attractor = mapper.iterate_from_point(0.2, 0.5, 200).final_impact();
+
+        assert_float_eq!(orbit.points()[0].phase(), attractor.phase(), abs <= 1e-5);
+        assert_float_eq!(orbit.points()[0].velocity(), attractor.velocity(), abs <= 1e-5);
+
+        // For a single impact per cycle the determinant is r^2
+        if let Eigenvalues::ComplexPair{real, imaginary} = orbit.multipliers() {
+            assert_float_eq!(real * real + imaginary * imaginary, 0.64, abs <= 1e-5);
+        } else if let Eigenvalues::Real(first, second) = orbit.multipliers() {
+            assert_float_eq!(first * second, 0.64, abs <= 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_finds_unstable_orbit_in_chaotic_trajectory() {
+        let mapper = ImpactMap::new(Parameters::new(2.8, 0.0, 0.8, 100).unwrap());
+
+        let trajectory = mapper.iterate_from_point(0.0, 0.0, 2000);
+
+        let orbit = OrbitSolver::default(&mapper).solve_from_trajectory(trajectory.trajectory(), 1, 1).unwrap();
+
+        assert!(!orbit.is_stable());
+
+        let image = mapper.apply_repeatedly(orbit.points()[0], 1).unwrap();
+
+        assert_float_eq!(image.time() - orbit.points()[0].time(), mapper.converter().period(), abs <= 1e-9);
+    }
+
+    #[test]
+    fn test_orbit_with_several_impacts() {
+        let mapper = ImpactMap::new(Parameters::new(2.8, 0.1, 0.8, 100).unwrap());
+
+        let trajectory = mapper.iterate_from_point(0.0, 0.0, 500);
+
+        let orbit = OrbitSolver::default(&mapper).solve_from_trajectory(trajectory.trajectory(), 4, 4).unwrap();
+
+        assert_eq!(orbit.points().len(), 4);
+        assert!(orbit.is_stable());
+    }
+
+    #[test]
+    fn test_rejects_empty_orbit() {
+        let mapper = ImpactMap::new(Parameters::new(2.8, 0.1, 0.8, 100).unwrap());
+
+        assert_eq!(OrbitSolver::default(&mapper).solve(mapper.impact_from_point(0.0, 1.0), 0, 1).unwrap_err(), ShootingError::ZeroImpactsOrPeriods);
+    }
+}
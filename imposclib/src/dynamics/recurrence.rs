@@ -0,0 +1,217 @@
+//
+// Recurrence plots and recurrence quantification of impact sequences
+//
+use super::impact::Impact as Impact;
+use super::impact::SimpleImpact as SimpleImpact;
+use super::impact::impact_comparer as impact_comparer;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Summary statistics of a recurrence plot, leaving out the main diagonal (where every impact trivially recurs).
+/// Lines are only counted if they are at least two points long.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct RecurrenceQuantification {
+    /// Proportion of pairs of distinct impacts which recur
+    pub recurrence_rate: f64,
+
+    /// Proportion of recurrent pairs which lie on diagonal lines, i.e. where the trajectory shadows an earlier
+    /// stretch of itself
+    pub determinism: f64,
+
+    /// Proportion of recurrent pairs which lie on vertical lines, i.e. where the trajectory is trapped near one
+    /// state for several impacts
+    pub laminarity: f64,
+
+    /// Length of the longest diagonal line
+    pub longest_diagonal_line: usize
+}
+
+/// Which impacts of a trajectory recur, i.e. are equal within tolerance to some other impact of the trajectory.
+/// The matrix is symmetric and each row is held as a bitset, one bit per column, as periodic and chattering
+/// trajectories recur so often that the matrix is nearly dense.
+#[derive(Debug, Clone)]
+pub struct RecurrencePlot {
+    size: usize,
+    rows: Vec<Vec<u64>>
+}
+
+impl RecurrencePlot {
+    const MINIMUM_LINE_LENGTH: usize = 2;
+
+    const BITS: usize = 64;
+
+    /// Impacts are compared with the tolerances of `impact_comparer`: absolute in phase (allowing for
+    /// periodicity) and relative in velocity. Rows are computed in parallel. Returns `None` if `cancellation` is
+    /// set before every row has been started.
+    pub fn new(trajectory: &[Impact], tolerance: SimpleImpact, cancellation: &AtomicBool) -> Option<RecurrencePlot> {
+        let size = trajectory.len();
+
+        let words = size.div_ceil(RecurrencePlot::BITS);
+
+        let rows = (0..size).into_par_iter().map_init(|| impact_comparer(tolerance), |comparer, i| {
+            if cancellation.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let mut row = vec![0; words];
+
+            for j in (0..size).filter(|&j| j != i && comparer(trajectory[i], trajectory[j])) {
+                row[j / RecurrencePlot::BITS] |= 1 << (j % RecurrencePlot::BITS);
+            }
+
+            Some(row)
+        }).collect::<Option<Vec<Vec<u64>>>>()?;
+
+        Some(RecurrencePlot{size, rows})
+    }
+
+    /// The number of impacts in the trajectory
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_recurrent(&self, i: usize, j: usize) -> bool {
+        self.rows[i][j / RecurrencePlot::BITS] & (1 << (j % RecurrencePlot::BITS)) != 0
+    }
+
+    /// Every recurrent pair (i, j) with i < j, in order
+    pub fn pairs(&self) -> Vec<(usize, usize)> {
+        self.upper_pairs().collect()
+    }
+
+    /// The recurrent columns of row `i`, in increasing order
+    pub fn columns(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        self.rows[i].iter().enumerate().flat_map(|(index, &word)| {
+            (0..RecurrencePlot::BITS).filter(move |bit| word & (1 << bit) != 0).map(move |bit| index * RecurrencePlot::BITS + bit)
+        })
+    }
+
+    /// Measures are gathered row by row, without listing the recurrent pairs
+    pub fn quantification(&self) -> RecurrenceQuantification {
+        let size = self.size();
+
+        let recurrent: usize = self.rows.iter().flatten().map(|word| word.count_ones() as usize).sum();
+
+        if recurrent == 0 {
+            return RecurrenceQuantification{recurrence_rate: 0.0, determinism: 0.0, laminarity: 0.0, longest_diagonal_line: 0};
+        }
+
+        // By symmetry, diagonal lines are counted above the main diagonal only
+        let mut on_diagonal_lines = 0;
+        let mut longest_diagonal_line = 0;
+
+        for (i, j) in self.upper_pairs() {
+            let starts_line = i == 0 || !self.is_recurrent(i - 1, j - 1);
+
+            if starts_line {
+                let length = (0..).take_while(|&k| j + k < size && self.is_recurrent(i + k, j + k)).count();
+
+                if length >= RecurrencePlot::MINIMUM_LINE_LENGTH {
+                    on_diagonal_lines += 2 * length;
+                }
+
+                longest_diagonal_line = std::cmp::max(longest_diagonal_line, length);
+            }
+        }
+
+        let on_vertical_lines: usize = (0..size).map(|i| RecurrencePlot::points_on_runs(self.columns(i))).sum();
+
+        RecurrenceQuantification{
+            recurrence_rate: recurrent as f64 / (size * (size - 1)) as f64,
+            determinism: on_diagonal_lines as f64 / recurrent as f64,
+            laminarity: on_vertical_lines as f64 / recurrent as f64,
+            longest_diagonal_line
+        }
+    }
+
+    fn upper_pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.size).flat_map(move |i| self.columns(i).filter(move |&j| j > i).map(move |j| (i, j)))
+    }
+
+    // The number of columns, in increasing order, which are on runs of consecutive columns of at least the minimum
+    // length
+    fn points_on_runs(columns: impl Iterator<Item = usize>) -> usize {
+        let mut total = 0;
+        let mut run = 0;
+        let mut previous: Option<usize> = None;
+
+        for column in columns {
+            if previous.is_some_and(|previous| previous + 1 == column) {
+                run += 1;
+            } else {
+                if run >= RecurrencePlot::MINIMUM_LINE_LENGTH {
+                    total += run;
+                }
+
+                run = 1;
+            }
+
+            previous = Some(column);
+        }
+
+        if run >= RecurrencePlot::MINIMUM_LINE_LENGTH {
+            total += run;
+        }
+
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::impact_map::ImpactMap as ImpactMap;
+    use super::super::parameters::Parameters as Parameters;
+
+    fn tolerance() -> SimpleImpact {
+        SimpleImpact::new(1e-2, 1e-2)
+    }
+
+    fn trajectory(frequency: f64, offset: f64, r: f64) -> Vec<Impact> {
+        let result = ImpactMap::new(Parameters::new(frequency, offset, r, 100).unwrap()).iterate_from_point(0.0, 0.0, 1500);
+
+        result.trajectory()[1000..].to_vec()
+    }
+
+    #[test]
+    fn test_runs_of_consecutive_columns() {
+        assert_eq!(RecurrencePlot::points_on_runs([1, 2, 3, 5, 7, 8].iter().copied()), 5);
+        assert_eq!(RecurrencePlot::points_on_runs([1, 3, 5].iter().copied()), 0);
+        assert_eq!(RecurrencePlot::points_on_runs(std::iter::empty()), 0);
+    }
+
+    #[test]
+    fn test_periodic_orbit_always_recurs() {
+        // A (2, 4) orbit, so every other impact recurs
+        let plot = RecurrencePlot::new(&trajectory(3.0, 0.11, 0.8), tolerance(), &AtomicBool::new(false)).unwrap();
+
+        let quantification = plot.quantification();
+
+        assert!(plot.is_recurrent(0, 2));
+        assert!(!plot.is_recurrent(0, 1));
+        // Only the corner of the plot is not on a line
+        assert!(quantification.determinism > 0.99);
+        assert_eq!(quantification.laminarity, 0.0);
+        assert!(quantification.recurrence_rate > 0.4);
+        assert_eq!(plot.pairs().len() * 2, (0..plot.size()).map(|i| plot.columns(i).count()).sum::<usize>());
+        assert!(plot.pairs().iter().all(|&(i, j)| i < j && plot.is_recurrent(j, i)));
+    }
+
+    #[test]
+    fn test_cancelled_plot_is_not_built() {
+        assert!(RecurrencePlot::new(&trajectory(3.0, 0.11, 0.8), tolerance(), &AtomicBool::new(true)).is_none());
+    }
+
+    #[test]
+    fn test_chaos_recurs_less_regularly() {
+        let running = AtomicBool::new(false);
+
+        let periodic = RecurrencePlot::new(&trajectory(3.0, 0.11, 0.8), tolerance(), &running).unwrap().quantification();
+
+        let chaotic = RecurrencePlot::new(&trajectory(2.8, 0.0, 0.8), tolerance(), &running).unwrap().quantification();
+
+        assert!(chaotic.recurrence_rate < 0.1 * periodic.recurrence_rate);
+        assert!(chaotic.determinism < periodic.determinism);
+        assert!(chaotic.longest_diagonal_line < periodic.longest_diagonal_line);
+    }
+}
@@ -0,0 +1,252 @@
+//
+// Rotation numbers of impact sequences, and locking of impacts to the forcing
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::impact_map::IterationContext as IterationContext;
+use super::impact_map::IterationControl as IterationControl;
+use super::impact_map::TrajectorySampling as TrajectorySampling;
+use super::model_types::Phase as Phase;
+use super::model_types::Velocity as Velocity;
+use super::orbit_chart::ChartAxis as ChartAxis;
+use super::parameters::Parameters as Parameters;
+use rayon::prelude::*;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The mean number of forcing cycles between consecutive impacts, from the unwrapped phases of the first and last
+/// impacts (i.e. whole periods elapsed plus the change of phase). Returns `None` for fewer than two impacts. Every
+/// impact between the first and the last must be in `trajectory`, so it cannot be recorded with `every` > 1.
+pub fn rotation_number(trajectory: &[Impact]) -> Option<f64> {
+    if trajectory.len() < 2 {
+        return None;
+    }
+
+    Some(cycles_between(trajectory[0], trajectory[trajectory.len() - 1]) / (trajectory.len() - 1) as f64)
+}
+
+fn cycles_between(earlier: Impact, later: Impact) -> f64 {
+    (later.periods() - earlier.periods()) as f64 + (later.phase() - earlier.phase())
+}
+
+/// Impacts locked to the forcing, so that every `impacts` consecutive impacts take exactly `periods` forcing
+/// periods, as on an orbit with `impacts` impacts every `periods` periods. The ratio is not reduced to lowest
+/// terms, since 4/2 (two impacts at different phases in four periods) is not the same locking as 2/1.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Locking {
+    pub periods: u32,
+    pub impacts: u32
+}
+
+impl fmt::Display for Locking {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.periods, self.impacts)
+    }
+}
+
+/// Looks for the smallest number of impacts, up to `max_impacts`, over which the advance of the unwrapped phase is
+/// the same whole number of cycles (to within `tolerance`) everywhere along a trajectory
+#[derive(Debug, Copy, Clone)]
+pub struct LockingDetector {
+    max_impacts: u32,
+    tolerance: Phase
+}
+
+impl Default for LockingDetector {
+    fn default() -> LockingDetector {
+        LockingDetector::new(20, 1e-3)
+    }
+}
+
+impl LockingDetector {
+    pub fn new(max_impacts: u32, tolerance: Phase) -> LockingDetector {
+        LockingDetector{max_impacts, tolerance}
+    }
+
+    pub fn detect(&self, trajectory: &[Impact]) -> Option<Locking> {
+        let rotation = rotation_number(trajectory)?;
+
+        (1..=self.max_impacts).filter(|&impacts| (impacts as usize) < trajectory.len()).find_map(|impacts| {
+            let periods = (rotation * impacts as f64).round();
+
+            let locked = periods >= 0.0 && trajectory.iter().zip(trajectory.iter().skip(impacts as usize))
+                .all(|(&earlier, &later)| (cycles_between(earlier, later) - periods).abs() < self.tolerance);
+
+            if locked {Some(Locking{periods: periods as u32, impacts})} else {None}
+        })
+    }
+}
+
+/// The rotation number of a trajectory once its transient has died away, and whether it is locked. There is no
+/// rotation number if the parameters are not valid or the motion does not keep impacting.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct RotationPoint {
+    pub rotation_number: Option<f64>,
+    pub locking: Option<Locking>
+}
+
+/// Rotation numbers over a grid of two parameters, held by row as in an `OrbitChart`
+#[derive(Debug, Clone)]
+pub struct RotationChart {
+    horizontal: Vec<f64>,
+    vertical: Vec<f64>,
+    cells: Vec<RotationPoint>
+}
+
+impl RotationChart {
+    pub fn horizontal(&self) -> &[f64] {
+        &self.horizontal
+    }
+
+    pub fn vertical(&self) -> &[f64] {
+        &self.vertical
+    }
+
+    pub fn cells(&self) -> &[RotationPoint] {
+        &self.cells
+    }
+
+    #[cfg(test)]
+    pub fn cell(&self, column: usize, row: usize) -> RotationPoint {
+        self.cells[row * self.horizontal.len() + column]
+    }
+}
+
+/// Rotation numbers as parameters vary, each from the same initial impact, computed in parallel. At each set of
+/// parameters the map is iterated for `transient` impacts, which are discarded, and the rotation number is taken
+/// over the next `recorded`. Swept over the forcing frequency this gives a devil's staircase; over the frequency
+/// and a second parameter it shows the Arnold tongues in which the impacts lock to the forcing.
+pub struct RotationSweep {
+    parameters: Parameters,
+    initial_point: (Phase, Velocity),
+    transient: u32,
+    recorded: u32,
+    detector: LockingDetector
+}
+
+impl RotationSweep {
+    pub fn new(parameters: Parameters, initial_point: (Phase, Velocity), transient: u32, recorded: u32, detector: LockingDetector) -> RotationSweep {
+        RotationSweep{parameters, initial_point, transient, recorded, detector}
+    }
+
+    /// The rotation number at each value on `axis`, or `None` if `cancellation` is set before every value has
+    /// been started
+    pub fn staircase(&self, axis: ChartAxis, cancellation: &AtomicBool) -> Option<Vec<(f64, RotationPoint)>> {
+        axis.values().into_par_iter()
+            .map(|value| if cancellation.load(Ordering::Relaxed) {
+                None
+            } else {
+                Some((value, self.point(self.parameters.with_value(axis.parameter(), value).ok())))
+            })
+            .collect()
+    }
+
+    /// The rotation number in each cell of the grid, or `None` if `cancellation` is set before every cell has been
+    /// started
+    pub fn tongues(&self, horizontal: ChartAxis, vertical: ChartAxis, cancellation: &AtomicBool) -> Option<RotationChart> {
+        let horizontal_values = horizontal.values();
+        let vertical_values = vertical.values();
+
+        let cells = (0..horizontal_values.len() * vertical_values.len()).into_par_iter().map(|cell| {
+            if cancellation.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let parameters = self.parameters.with_value(horizontal.parameter(), horizontal_values[cell % horizontal_values.len()])
+                .and_then(|parameters| parameters.with_value(vertical.parameter(), vertical_values[cell / horizontal_values.len()]));
+
+            Some(self.point(parameters.ok()))
+        }).collect::<Option<Vec<RotationPoint>>>()?;
+
+        Some(RotationChart{horizontal: horizontal_values, vertical: vertical_values, cells})
+    }
+
+    fn point(&self, parameters: Option<Parameters>) -> RotationPoint {
+        let no_rotation = RotationPoint{rotation_number: None, locking: None};
+
+        let parameters = match parameters {
+            Some(parameters) => parameters,
+            None => return no_rotation
+        };
+
+        let mapper = ImpactMap::new(parameters);
+
+        let mut control = IterationControl::new().with_sampling(TrajectorySampling::new(self.transient, 1, 0));
+
+        let result = mapper.iterate_with_control(&mut IterationContext::new(), &mut control,
+            mapper.impact_from_point(self.initial_point.0, self.initial_point.1), self.transient + self.recorded);
+
+        // After a long excursion the next "impact" is only where the search gave up
+        if result.has_long_excursions() {
+            return no_rotation;
+        }
+
+        RotationPoint{rotation_number: rotation_number(result.trajectory()), locking: self.detector.detect(result.trajectory())}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parameters::ParameterName as ParameterName;
+
+    fn tail(frequency: f64, offset: f64) -> Vec<Impact> {
+        let result = ImpactMap::new(Parameters::new(frequency, offset, 0.8, 100).unwrap()).iterate_from_point(0.0, 0.0, 1500);
+
+        result.trajectory()[1000..].to_vec()
+    }
+
+    #[test]
+    fn test_rotation_numbers_of_periodic_orbits() {
+        let detector = LockingDetector::default();
+
+        let one_one = tail(2.0, 0.0);
+
+        assert!((rotation_number(&one_one).unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(detector.detect(&one_one), Some(Locking{periods: 1, impacts: 1}));
+
+        let one_two = tail(3.5, 0.0);
+
+        assert!((rotation_number(&one_two).unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(detector.detect(&one_two).unwrap().to_string(), "2/1");
+
+        // The same rotation number as (1, 2), but a different locking
+        let two_four = tail(3.0, 0.11);
+
+        assert!((rotation_number(&two_four).unwrap() - 2.0).abs() < 1e-3);
+        assert_eq!(detector.detect(&two_four), Some(Locking{periods: 4, impacts: 2}));
+
+        assert!(rotation_number(&one_one[..1]).is_none());
+    }
+
+    #[test]
+    fn test_chaos_is_not_locked() {
+        let chaotic = tail(2.8, 0.0);
+
+        assert!(rotation_number(&chaotic).is_some());
+        assert!(LockingDetector::default().detect(&chaotic).is_none());
+    }
+
+    #[test]
+    fn test_staircase_and_tongues() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let sweep = RotationSweep::new(parameters, (0.0, 0.0), 1000, 200, LockingDetector::default());
+
+        let running = AtomicBool::new(false);
+
+        let staircase = sweep.staircase(ChartAxis::new(ParameterName::Frequency, 2.0, 3.5, 2), &running).unwrap();
+
+        assert_eq!(staircase[0].0, 2.0);
+        assert_eq!(staircase[0].1.locking, Some(Locking{periods: 1, impacts: 1}));
+        assert_eq!(staircase[1].1.locking, Some(Locking{periods: 2, impacts: 1}));
+
+        let chart = sweep.tongues(ChartAxis::new(ParameterName::Frequency, 2.0, 3.5, 2), ChartAxis::new(ParameterName::CoefficientOfRestitution, 0.8, 1.2, 2), &running).unwrap();
+
+        assert_eq!(chart.cells().len(), 4);
+        assert_eq!(chart.cell(1, 0), staircase[1].1);
+        assert_eq!(chart.cell(0, 1), RotationPoint{rotation_number: None, locking: None});
+
+        assert!(sweep.staircase(ChartAxis::new(ParameterName::Frequency, 2.0, 3.5, 2), &AtomicBool::new(true)).is_none());
+    }
+}
@@ -0,0 +1,253 @@
+//
+// Runs of the impact map in which one of the parameters drifts with time, as in a ramp test
+//
+use super::impact::Impact as Impact;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::impact_map::IterationContext as IterationContext;
+use super::model_types::Phase as Phase;
+use super::model_types::Time as Time;
+use super::model_types::Velocity as Velocity;
+use super::parameters::Parameters as Parameters;
+use super::parameters::ParameterName as ParameterName;
+use std::fmt;
+
+/// A parameter value which is a piecewise-linear function of time, given by (time, value) breakpoints. Before the
+/// first breakpoint and after the last the value is held constant.
+#[derive(Debug, Clone)]
+pub struct ParameterSchedule {
+    parameter: ParameterName,
+    breakpoints: Vec<(Time, f64)>
+}
+
+impl ParameterSchedule {
+    /// Breakpoints are sorted into time order. A schedule needs at least one breakpoint.
+    pub fn new(parameter: ParameterName, breakpoints: &[(Time, f64)]) -> Option<ParameterSchedule> {
+        if breakpoints.is_empty() || breakpoints.iter().any(|(time, value)| !time.is_finite() || !value.is_finite()) {
+            return None;
+        }
+
+        let mut breakpoints = breakpoints.to_vec();
+
+        breakpoints.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        Some(ParameterSchedule{parameter, breakpoints})
+    }
+
+    /// A linear ramp from `start` to `end` over `duration`, after which the value stays at `end`
+    #[cfg(test)]
+    pub fn ramp(parameter: ParameterName, start: f64, end: f64, duration: Time) -> Option<ParameterSchedule> {
+        ParameterSchedule::new(parameter, &[(0.0, start), (duration, end)])
+    }
+
+    pub fn parameter(&self) -> ParameterName {
+        self.parameter
+    }
+
+    pub fn value(&self, time: Time) -> f64 {
+        let first = self.breakpoints[0];
+        let last = self.breakpoints[self.breakpoints.len() - 1];
+
+        if time <= first.0 {
+            return first.1;
+        }
+
+        if time >= last.0 {
+            return last.1;
+        }
+
+        let after = self.breakpoints.iter().position(|breakpoint| breakpoint.0 > time).unwrap();
+        let (t0, v0) = self.breakpoints[after - 1];
+        let (t1, v1) = self.breakpoints[after];
+
+        v0 + (v1 - v0) * (time - t0) / (t1 - t0)
+    }
+}
+
+/// An impact of a scheduled run, with the time since the start of the run and the value of the scheduled parameter
+/// at that time
+#[derive(Debug, Copy, Clone)]
+pub struct ScheduledImpact {
+    pub time: Time,
+    pub phase: Phase,
+    pub velocity: Velocity,
+    pub parameter: f64
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ScheduledTermination {
+    Completed,
+    /// The motion settled on a non-impacting orbit
+    NonImpacting,
+    /// The schedule reached a value at which the parameters are not valid, e.g. a coefficient of restitution above one
+    InvalidParameters{time: Time, value: f64}
+}
+
+impl fmt::Display for ScheduledTermination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScheduledTermination::Completed => write!(f, "completed"),
+            ScheduledTermination::NonImpacting => write!(f, "non-impacting"),
+            ScheduledTermination::InvalidParameters{value, ..} => write!(f, "invalid parameters (value {})", value)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduledResult {
+    impacts: Vec<ScheduledImpact>,
+    termination: ScheduledTermination
+}
+
+impl ScheduledResult {
+    pub fn impacts(&self) -> &[ScheduledImpact] {
+        &self.impacts
+    }
+
+    pub fn termination(&self) -> ScheduledTermination {
+        self.termination
+    }
+}
+
+/// Iterates the map with the scheduled parameter updated at every impact and held constant until the next, so
+/// that the coefficients of the motion are recomputed from the parameters in force at each impact. Impacts are
+/// carried from one set of parameters to the next by phase rather than by time, which keeps the forcing continuous
+/// when the frequency changes.
+pub struct ScheduledIterator {
+    parameters: Parameters,
+    schedule: ParameterSchedule
+}
+
+impl ScheduledIterator {
+    /// Parameters which are not scheduled are taken from `parameters`
+    pub fn new(parameters: Parameters, schedule: ParameterSchedule) -> ScheduledIterator {
+        ScheduledIterator{parameters, schedule}
+    }
+
+    pub fn iterate_from_point(&self, phi: Phase, v: Velocity, num_iterations: u32) -> ScheduledResult {
+        let mut context = IterationContext::new();
+
+        let mut impacts = Vec::with_capacity(num_iterations as usize + 1);
+
+        let mut elapsed: Time = 0.0;
+
+        let mut point = (phi, v);
+
+        let mut termination = ScheduledTermination::Completed;
+
+        for iteration in 0..=num_iterations {
+            let value = self.schedule.value(elapsed);
+
+            let mapper = match self.parameters.with_value(self.schedule.parameter(), value) {
+                Ok(parameters) => ImpactMap::new(parameters),
+                Err(_) => {
+                    termination = ScheduledTermination::InvalidParameters{time: elapsed, value};
+                    break;
+                }
+            };
+
+            let impact = mapper.impact_from_point(point.0, point.1);
+
+            impacts.push(ScheduledImpact{time: elapsed, phase: impact.phase(), velocity: impact.velocity(), parameter: value});
+
+            if iteration == num_iterations {
+                break;
+            }
+
+            let result = mapper.iterate_with_context(&mut context, impact, 1);
+
+            // Chatter adds the accumulation impact after the impact which triggered it, with the parameters
+            // unchanged in between
+            let trajectory = result.trajectory();
+
+            for later in trajectory.iter().skip(1).take(trajectory.len().saturating_sub(2)) {
+                impacts.push(ScheduledImpact{time: elapsed + later.time_since(impact), phase: later.phase(), velocity: later.velocity(), parameter: value});
+            }
+
+            if result.non_impacting_amplitude().is_some() {
+                termination = ScheduledTermination::NonImpacting;
+                break;
+            }
+
+            let next: Impact = result.final_impact();
+
+            elapsed += next.time_since(impact);
+
+            point = (next.phase(), next.velocity());
+        }
+
+        ScheduledResult{impacts, termination}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_values() {
+        let schedule = ParameterSchedule::new(ParameterName::Frequency, &[(10.0, 3.0), (0.0, 2.0), (20.0, 2.5)]).unwrap();
+
+        assert_eq!(schedule.value(-1.0), 2.0);
+        assert_eq!(schedule.value(5.0), 2.5);
+        assert_eq!(schedule.value(15.0), 2.75);
+        assert_eq!(schedule.value(30.0), 2.5);
+
+        assert!(ParameterSchedule::new(ParameterName::Frequency, &[]).is_none());
+    }
+
+    #[test]
+    fn test_constant_schedule_matches_ordinary_iteration() {
+        let parameters = Parameters::new(4.85, -0.1, 0.8, 100).unwrap();
+
+        let schedule = ParameterSchedule::ramp(ParameterName::Frequency, 4.85, 4.85, 100.0).unwrap();
+
+        let scheduled = ScheduledIterator::new(parameters, schedule).iterate_from_point(0.0, 0.0, 50);
+
+        let ordinary = ImpactMap::new(parameters).iterate_from_point(0.0, 0.0, 50);
+
+        assert_eq!(scheduled.termination(), ScheduledTermination::Completed);
+        assert_eq!(scheduled.impacts().len(), ordinary.trajectory().len());
+
+        for (a, b) in scheduled.impacts().iter().zip(ordinary.trajectory().iter()) {
+            assert!((a.phase - b.phase()).abs() < 1e-9);
+            assert!((a.velocity - b.velocity()).abs() < 1e-9);
+            assert!((a.time - b.time()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_ramp_pairs_impacts_with_parameter_values() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let schedule = ParameterSchedule::ramp(ParameterName::Frequency, 2.0, 3.0, 500.0).unwrap();
+
+        let result = ScheduledIterator::new(parameters, schedule.clone()).iterate_from_point(0.0, 0.0, 400);
+
+        assert_eq!(result.termination(), ScheduledTermination::Completed);
+
+        for pair in result.impacts().windows(2) {
+            assert!(pair[1].time >= pair[0].time);
+            assert!(pair[1].parameter >= pair[0].parameter);
+        }
+
+        for impact in result.impacts() {
+            assert_eq!(impact.parameter, schedule.value(impact.time));
+        }
+
+        assert_eq!(result.impacts().last().unwrap().parameter, 3.0);
+    }
+
+    #[test]
+    fn test_run_stops_at_invalid_parameters() {
+        let parameters = Parameters::new(2.0, 0.0, 0.8, 100).unwrap();
+
+        let schedule = ParameterSchedule::ramp(ParameterName::CoefficientOfRestitution, 0.8, 1.2, 50.0).unwrap();
+
+        let result = ScheduledIterator::new(parameters, schedule).iterate_from_point(0.0, 0.0, 1000);
+
+        match result.termination() {
+            ScheduledTermination::InvalidParameters{value, ..} => assert!(value > 1.0),
+            other => panic!("Unexpected termination {}", other)
+        }
+    }
+}
@@ -104,14 +104,14 @@ impl Sticking {
     pub fn check_impact(&self, impact: Impact) -> ReleaseImpact {
 
     	if impact.velocity() == 0.0 && self.phase_sticks(impact.phase()) && !self.always() {
-    		return ReleaseImpact{new_impact: true, impact: self.generator.generate(self.release_time(impact.time()), 0.0)}
+    		return ReleaseImpact{new_impact: true, impact: self.generator.generate_after_periods(impact.periods(), self.release_time(impact.time_in_period()), 0.0)}
     	} else {
     		return ReleaseImpact{new_impact: false, impact: impact}
     	}
     }
 
-	pub fn generate(&self, impact_time: Time) -> Impact  {
-        self.generator.generate(impact_time, 0.0)
+	pub fn generate(&self, periods: i64, impact_time: Time) -> Impact  {
+        self.generator.generate_after_periods(periods, impact_time, 0.0)
     }
 }
 
@@ -0,0 +1,211 @@
+//
+// Symbolic dynamics of impact sequences, and entropies estimated from them
+//
+use super::impact::Impact as Impact;
+use super::model_types::Phase as Phase;
+use std::collections::BTreeMap;
+
+pub type Symbol = u32;
+
+pub type Word = Vec<Symbol>;
+
+/// How the interval between consecutive impacts is turned into a symbol
+#[derive(Debug, Clone)]
+pub enum SymbolicEncoding {
+    /// The number of forcing periods which elapse between the impacts, counted as the number of starts of a period
+    /// in between. On a periodic orbit the interval between impacts can be a whole number of periods, which rounding
+    /// the interval down (as `PhaseConverter::difference_in_periods` does) would split unpredictably between two
+    /// symbols.
+    PeriodsElapsed,
+
+    /// The arc of the circle of phases in which the later impact falls. The arcs run from each boundary to the
+    /// next, with the last wrapping round to the first, and are numbered from the arc which starts at the
+    /// smallest boundary.
+    PhasePartition(Vec<Phase>)
+}
+
+impl SymbolicEncoding {
+    /// A partition needs at least two distinct boundaries in [0, 1)
+    pub fn phase_partition(boundaries: &[Phase]) -> Option<SymbolicEncoding> {
+        let mut boundaries: Vec<Phase> = boundaries.to_vec();
+
+        if boundaries.iter().any(|boundary| !(0.0..1.0).contains(boundary)) {
+            return None;
+        }
+
+        boundaries.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        boundaries.dedup();
+
+        if boundaries.len() < 2 {
+            return None;
+        }
+
+        Some(SymbolicEncoding::PhasePartition(boundaries))
+    }
+
+    fn symbol(&self, earlier: Impact, later: Impact) -> Symbol {
+        match self {
+            SymbolicEncoding::PeriodsElapsed => (later.periods() - earlier.periods()) as Symbol,
+            SymbolicEncoding::PhasePartition(boundaries) => {
+                let above = boundaries.iter().filter(|&&boundary| boundary <= later.phase()).count();
+
+                (if above == 0 {boundaries.len() - 1} else {above - 1}) as Symbol
+            }
+        }
+    }
+}
+
+/// Block entropies (by Shannon's formula, in nats) and counts of distinct words for word lengths 1, 2, ..., with
+/// the entropies per symbol estimated from them at the longest length.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EntropyEstimate {
+    pub block_entropies: Vec<f64>,
+    pub word_counts: Vec<usize>,
+
+    /// ln N(n) - ln N(n - 1) for the longest word length n, where N counts distinct words
+    pub topological_entropy: f64,
+
+    /// H(n) - H(n - 1) for the longest word length n, where H is the block entropy
+    pub entropy_rate: f64
+}
+
+/// A trajectory as a string of symbols, one for each interval between consecutive impacts
+#[derive(Debug, PartialEq, Clone)]
+pub struct SymbolSequence {
+    symbols: Vec<Symbol>
+}
+
+impl SymbolSequence {
+    /// `trajectory` should hold consecutive impacts, not ones picked out by a `TrajectorySampling` with `every` > 1
+    pub fn encode(trajectory: &[Impact], encoding: &SymbolicEncoding) -> SymbolSequence {
+        SymbolSequence{symbols: trajectory.windows(2).map(|pair| encoding.symbol(pair[0], pair[1])).collect()}
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Every distinct word of `length` symbols, with the number of times it occurs (overlapping occurrences
+    /// included)
+    pub fn words(&self, length: usize) -> BTreeMap<Word, usize> {
+        let mut words = BTreeMap::new();
+
+        if length > 0 {
+            for word in self.symbols.windows(length) {
+                *words.entry(word.to_vec()).or_insert(0) += 1;
+            }
+        }
+
+        words
+    }
+
+    pub fn block_entropy(&self, length: usize) -> f64 {
+        let words = self.words(length);
+
+        let total: usize = words.values().sum();
+
+        words.values().map(|&count| {
+            let probability = count as f64 / total as f64;
+
+            -probability * probability.ln()
+        }).sum()
+    }
+
+    /// Estimates for word lengths up to `max_length`. The estimates are only reliable while the sequence is much
+    /// longer than the number of distinct words. Returns `None` if the sequence is shorter than `max_length`.
+    pub fn entropy(&self, max_length: usize) -> Option<EntropyEstimate> {
+        if max_length == 0 || self.symbols.len() < max_length {
+            return None;
+        }
+
+        let block_entropies: Vec<f64> = (1..=max_length).map(|length| self.block_entropy(length)).collect();
+        let word_counts: Vec<usize> = (1..=max_length).map(|length| self.words(length).len()).collect();
+
+        let (previous_count, previous_entropy) = if max_length > 1 {
+            (word_counts[max_length - 2], block_entropies[max_length - 2])
+        } else {
+            (1, 0.0)
+        };
+
+        Some(EntropyEstimate{
+            topological_entropy: (word_counts[max_length - 1] as f64).ln() - (previous_count as f64).ln(),
+            entropy_rate: block_entropies[max_length - 1] - previous_entropy,
+            block_entropies,
+            word_counts
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::impact_map::ImpactMap as ImpactMap;
+    use super::super::parameters::Parameters as Parameters;
+
+    fn sequence(symbols: &[Symbol]) -> SymbolSequence {
+        SymbolSequence{symbols: symbols.to_vec()}
+    }
+
+    fn encode(frequency: f64, offset: f64, encoding: &SymbolicEncoding) -> SymbolSequence {
+        let parameters = Parameters::new(frequency, offset, 0.8, 100).unwrap();
+
+        let result = ImpactMap::new(parameters).iterate_from_point(0.0, 0.0, 3000);
+
+        SymbolSequence::encode(&result.trajectory()[1000..], encoding)
+    }
+
+    #[test]
+    fn test_words_and_entropies() {
+        let alternating = sequence(&[0, 1, 0, 1, 0, 1, 0, 1]);
+
+        assert_eq!(alternating.words(2).into_iter().collect::<Vec<(Word, usize)>>(), vec![(vec![0, 1], 4), (vec![1, 0], 3)]);
+        assert!((alternating.block_entropy(1) - 2.0f64.ln()).abs() < 1e-12);
+
+        let estimate = alternating.entropy(4).unwrap();
+
+        assert_eq!(estimate.word_counts, vec![2, 2, 2, 2]);
+        assert_eq!(estimate.topological_entropy, 0.0);
+
+        assert!(sequence(&[0, 1]).entropy(3).is_none());
+    }
+
+    #[test]
+    fn test_phase_partition_wraps() {
+        let partition = SymbolicEncoding::phase_partition(&[0.75, 0.25]).unwrap();
+
+        let mapper = ImpactMap::new(Parameters::new(2.0, 0.0, 0.8, 100).unwrap());
+
+        let impacts: Vec<Impact> = [0.0, 0.3, 0.8, 0.1].iter().map(|&phase| mapper.impact_from_point(phase, 1.0)).collect();
+
+        let symbols = SymbolSequence::encode(&impacts, &partition);
+
+        assert_eq!(symbols.symbols(), &[0, 1, 1]);
+
+        assert!(SymbolicEncoding::phase_partition(&[0.5]).is_none());
+        assert!(SymbolicEncoding::phase_partition(&[0.5, 1.5]).is_none());
+    }
+
+    #[test]
+    fn test_periodic_orbit_has_zero_entropy() {
+        // A (1, 2) orbit, with two forcing periods between impacts
+        let symbols = encode(3.5, 0.0, &SymbolicEncoding::PeriodsElapsed);
+
+        assert!(symbols.symbols().iter().all(|&symbol| symbol == 2));
+
+        let estimate = symbols.entropy(6).unwrap();
+
+        assert_eq!(estimate.topological_entropy, 0.0);
+        assert_eq!(estimate.entropy_rate, 0.0);
+    }
+
+    #[test]
+    fn test_chaotic_orbit_has_positive_entropy() {
+        let partition = SymbolicEncoding::phase_partition(&[0.0, 0.5]).unwrap();
+
+        let estimate = encode(2.8, 0.0, &partition).entropy(6).unwrap();
+
+        assert!(estimate.topological_entropy > 0.1, "{:?}", estimate);
+        assert!(estimate.entropy_rate > 0.1, "{:?}", estimate);
+        assert!(estimate.entropy_rate <= estimate.topological_entropy + 1e-9);
+    }
+}
@@ -0,0 +1,215 @@
+//
+// Lengths of the transients from initial impacts to the attractors on which they settle
+//
+use super::classification::OrbitClassifier as OrbitClassifier;
+use super::classification::OrbitType as OrbitType;
+use super::impact::Impact as Impact;
+use super::impact::SimpleImpact as SimpleImpact;
+use super::impact::impact_comparer as impact_comparer;
+use super::impact_map::ImpactMap as ImpactMap;
+use super::model_types::Phase as Phase;
+use super::model_types::Velocity as Velocity;
+use super::orbit_chart::ChartLabel as ChartLabel;
+use super::orbit_chart::index_labels as index_labels;
+use super::parameters::Parameters as Parameters;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Where a trajectory ends up, and after how many impacts it gets there. Lengths count impacts of the trajectory,
+/// including accumulation impacts at the end of chatter, so the initial impact is at 0.
+///
+/// - For a periodic orbit, the length is the index of the first impact after which every impact is within
+///   tolerance of one of the impacts of the final cycle.
+/// - For chatter, it is the index of the first impact at zero velocity to within the velocity tolerance, i.e. the
+///   first accumulation or sticking impact.
+/// - For non-impacting motion, it is the index of the last impact.
+/// - A chaotic attractor has no final cycle to compare with, and long excursions never settle, so these have no
+///   length.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct TransientOutcome {
+    pub label: ChartLabel,
+    pub length: Option<u32>
+}
+
+/// Transient lengths and attractor labels over a grid of initial impacts. Cells are held by row as in an
+/// `OrbitChart`, with phase on the horizontal axis and velocity on the vertical one.
+#[derive(Debug, Clone)]
+pub struct TransientGrid {
+    phases: Vec<Phase>,
+    velocities: Vec<Velocity>,
+    lengths: Vec<Option<u32>>,
+    cells: Vec<usize>,
+    legend: Vec<ChartLabel>
+}
+
+impl TransientGrid {
+    pub fn phases(&self) -> &[Phase] {
+        &self.phases
+    }
+
+    pub fn velocities(&self) -> &[Velocity] {
+        &self.velocities
+    }
+
+    pub fn lengths(&self) -> &[Option<u32>] {
+        &self.lengths
+    }
+
+    /// Indices into the legend, as for `OrbitChart`
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+
+    pub fn legend(&self) -> &[ChartLabel] {
+        &self.legend
+    }
+
+    #[cfg(test)]
+    pub fn length(&self, column: usize, row: usize) -> Option<u32> {
+        self.lengths[row * self.phases.len() + column]
+    }
+
+    #[cfg(test)]
+    pub fn label(&self, column: usize, row: usize) -> ChartLabel {
+        self.legend[self.cells[row * self.phases.len() + column]]
+    }
+}
+
+/// Iterates the map from initial impacts for a fixed number of iterations, classifies where each trajectory ends
+/// up and measures how long it took to get there. Impacts are compared with the tolerances of `impact_comparer`.
+pub struct TransientAnalyser {
+    mapper: ImpactMap,
+    classifier: OrbitClassifier,
+    num_iterations: u32,
+    tolerance: SimpleImpact
+}
+
+impl TransientAnalyser {
+    pub fn new(parameters: Parameters, num_iterations: u32, tolerance: SimpleImpact) -> TransientAnalyser {
+        TransientAnalyser{mapper: ImpactMap::new(parameters), classifier: OrbitClassifier::default(parameters), num_iterations, tolerance}
+    }
+
+    #[cfg(test)]
+    pub fn with_default_tolerance(parameters: Parameters, num_iterations: u32) -> TransientAnalyser {
+        TransientAnalyser::new(parameters, num_iterations, SimpleImpact::new(1e-3, 1e-3))
+    }
+
+    pub fn outcome(&self, initial_impact: Impact) -> TransientOutcome {
+        let result = self.mapper.iterate(initial_impact, self.num_iterations);
+
+        let trajectory = result.trajectory();
+
+        let orbit_type = self.classifier.classify(&result);
+
+        let length = match orbit_type {
+            OrbitType::Periodic{impacts, ..} => Some(self.settling_index(trajectory, impacts as usize)),
+            OrbitType::Chatter => trajectory.iter().skip(1).position(|impact| impact.velocity().abs() < self.tolerance.velocity()).map(|index| index as u32 + 1),
+            OrbitType::NonImpacting{..} => Some(trajectory.len() as u32 - 1),
+            OrbitType::LongExcursions | OrbitType::Aperiodic => None
+        };
+
+        TransientOutcome{label: ChartLabel::from(orbit_type), length}
+    }
+
+    /// The outcome from every combination of `phases` and `velocities`, computed in parallel, or `None` if
+    /// `cancellation` is set before every cell has been started
+    pub fn grid(&self, phases: &[Phase], velocities: &[Velocity], cancellation: &AtomicBool) -> Option<TransientGrid> {
+        let outcomes: Vec<TransientOutcome> = (0..phases.len() * velocities.len()).into_par_iter()
+            .map(|cell| if cancellation.load(Ordering::Relaxed) {
+                None
+            } else {
+                Some(self.outcome(self.mapper.impact_from_point(phases[cell % phases.len()], velocities[cell / phases.len()])))
+            })
+            .collect::<Option<Vec<TransientOutcome>>>()?;
+
+        let labels: Vec<ChartLabel> = outcomes.iter().map(|outcome| outcome.label).collect();
+
+        let (cells, legend) = index_labels(&labels);
+
+        Some(TransientGrid{
+            phases: phases.to_vec(),
+            velocities: velocities.to_vec(),
+            lengths: outcomes.iter().map(|outcome| outcome.length).collect(),
+            cells,
+            legend
+        })
+    }
+
+    // Works back from the end of the trajectory while impacts still match one of the last `impacts` impacts
+    fn settling_index(&self, trajectory: &[Impact], impacts: usize) -> u32 {
+        let comparer = impact_comparer(self.tolerance);
+
+        let cycle = &trajectory[trajectory.len() - impacts..];
+
+        let on_cycle = trajectory.iter().rev()
+            .take_while(|&&impact| cycle.iter().any(|&member| comparer(impact, member)))
+            .count();
+
+        (trajectory.len() - on_cycle) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyser(frequency: f64, offset: f64, r: f64) -> TransientAnalyser {
+        TransientAnalyser::with_default_tolerance(Parameters::new(frequency, offset, r, 100).unwrap(), 2000)
+    }
+
+    #[test]
+    fn test_transient_to_periodic_orbit() {
+        let analyser = analyser(2.0, 0.0, 0.8);
+
+        let outcome = analyser.outcome(analyser.mapper.impact_from_point(0.0, 0.0));
+
+        assert_eq!(outcome.label, ChartLabel::Periodic{impacts: 1, periods: 1});
+
+        let length = outcome.length.unwrap();
+
+        assert!(0 < length && length < 2000);
+
+        // Starting on the orbit there is no transient
+        let on_orbit = analyser.mapper.iterate_from_point(0.0, 0.0, 2000).final_impact();
+
+        assert_eq!(analyser.outcome(on_orbit).length, Some(0));
+    }
+
+    #[test]
+    fn test_chatter_and_chaos() {
+        let chatter = analyser(4.0, -0.1, 0.5);
+
+        let outcome = chatter.outcome(chatter.mapper.impact_from_point(0.0, 0.0));
+
+        assert_eq!(outcome.label, ChartLabel::Chatter);
+        assert!(outcome.length.is_some());
+
+        let chaos = analyser(2.8, 0.0, 0.8);
+
+        assert_eq!(chaos.outcome(chaos.mapper.impact_from_point(0.0, 0.0)), TransientOutcome{label: ChartLabel::Chaotic, length: None});
+    }
+
+    #[test]
+    fn test_grid_matches_outcome_of_each_cell() {
+        let analyser = analyser(3.0, 0.11, 0.8);
+
+        let phases = [0.0, 0.5];
+        let velocities = [0.0, 1.0, 2.0];
+
+        let grid = analyser.grid(&phases, &velocities, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(grid.lengths().len(), 6);
+        assert_eq!(grid.cells().len(), 6);
+
+        for (column, &phase) in phases.iter().enumerate() {
+            for (row, &velocity) in velocities.iter().enumerate() {
+                let outcome = analyser.outcome(analyser.mapper.impact_from_point(phase, velocity));
+
+                assert_eq!(grid.length(column, row), outcome.length);
+                assert_eq!(grid.label(column, row), outcome.label);
+            }
+        }
+
+        assert!(analyser.grid(&phases, &velocities, &AtomicBool::new(true)).is_none());
+    }
+}
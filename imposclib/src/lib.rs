@@ -8,10 +8,15 @@ use pyo3::prelude::*;
 use pyo3::{PyIterProtocol, PyMappingProtocol, PySequenceProtocol};
 use pyo3::types::{PyDict, IntoPyDict};
 
+use numpy::{Element, PyArray1, PyReadonlyArray1};
+
 use std::convert::From;
 
 use std::collections::HashMap;
 
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 mod io {
     pyo3::import_exception!(io, IndexError);
     pyo3::import_exception!(io, ValueError);
@@ -31,12 +36,55 @@ fn imposclib(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<ParameterProperties>()?;
     m.add_class::<PyImpact>()?;
     m.add_class::<IterationInputs>()?;
+    m.add_class::<BatchIterationInputs>()?;
+    m.add_class::<StateIterationInputs>()?;
+    m.add_class::<StateIterationOutputs>()?;
     m.add_class::<IterationOutputs>()?;
+    m.add_class::<CancellationToken>()?;
+    m.add_class::<BatchIterationOutputs>()?;
+    m.add_class::<PreImageOutputs>()?;
+    m.add_class::<ManifoldOutputs>()?;
+    m.add_class::<PeriodicOrbitOutputs>()?;
+    m.add_class::<BranchOutputs>()?;
+    m.add_class::<GrazingOutputs>()?;
+    m.add_class::<NormalFormOutputs>()?;
+    m.add_class::<OrbitChartOutputs>()?;
+    m.add_class::<SweepOutputs>()?;
+    m.add_class::<HysteresisOutputs>()?;
+    m.add_class::<ScheduledOutputs>()?;
+    m.add_class::<CircleMapOutputs>()?;
+    m.add_class::<DensityOutputs>()?;
+    m.add_class::<DimensionOutputs>()?;
+    m.add_class::<RecurrenceOutputs>()?;
+    m.add_class::<SymbolicOutputs>()?;
+    m.add_class::<RotationOutputs>()?;
+    m.add_class::<RotationChartOutputs>()?;
+    m.add_class::<TransientOutputs>()?;
+    m.add_class::<BasinStabilityOutputs>()?;
     
     m.add_function(wrap_pyfunction!(app_info, m)?)?;
     m.add_function(wrap_pyfunction!(symbol_properties, m)?)?;
     m.add_function(wrap_pyfunction!(group_properties, m)?)?;
     m.add_function(wrap_pyfunction!(iterate, m)?)?;
+    m.add_function(wrap_pyfunction!(iterate_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(iterate_from_state, m)?)?;
+    m.add_function(wrap_pyfunction!(pre_images, m)?)?;
+    m.add_function(wrap_pyfunction!(manifold, m)?)?;
+    m.add_function(wrap_pyfunction!(find_orbit, m)?)?;
+    m.add_function(wrap_pyfunction!(continue_orbit, m)?)?;
+    m.add_function(wrap_pyfunction!(grazing, m)?)?;
+    m.add_function(wrap_pyfunction!(orbit_chart, m)?)?;
+    m.add_function(wrap_pyfunction!(hysteresis_sweep, m)?)?;
+    m.add_function(wrap_pyfunction!(iterate_with_schedule, m)?)?;
+    m.add_function(wrap_pyfunction!(circle_map, m)?)?;
+    m.add_function(wrap_pyfunction!(invariant_density, m)?)?;
+    m.add_function(wrap_pyfunction!(fractal_dimension, m)?)?;
+    m.add_function(wrap_pyfunction!(recurrence, m)?)?;
+    m.add_function(wrap_pyfunction!(symbolic_dynamics, m)?)?;
+    m.add_function(wrap_pyfunction!(rotation_staircase, m)?)?;
+    m.add_function(wrap_pyfunction!(rotation_tongues, m)?)?;
+    m.add_function(wrap_pyfunction!(transient_lengths, m)?)?;
+    m.add_function(wrap_pyfunction!(basin_stability, m)?)?;
     m.add_function(wrap_pyfunction!(validate, m)?)?;
 
     Ok(())
@@ -54,7 +102,7 @@ fn symbol_properties() -> ParameterProperties {
 
 #[pyfunction]
 fn group_properties() -> ParameterProperties {
-    ParameterProperties::from(vec![("frequency", "System parameters"), ("offset", "System parameters"), ("r", "System parameters"), ("phi", "Initial impact"), ("v", "Initial impact"), ("max_periods", "Control parameters"), ("num_iterations", "Control parameters"), ("num_points", "Control parameters")])
+    ParameterProperties::from(vec![("frequency", "System parameters"), ("offset", "System parameters"), ("r", "System parameters"), ("phi", "Initial impact"), ("v", "Initial impact"), ("max_periods", "Control parameters"), ("num_iterations", "Control parameters"), ("num_points", "Control parameters"), ("skip_impacts", "Control parameters"), ("keep_every", "Control parameters"), ("max_points", "Control parameters")])
 }
 
 #[pyclass]
@@ -224,11 +272,66 @@ impl From<Vec<(&str, &str)>> for ParameterProperties {
 
 use crate::dynamics::parameters::Parameters as Parameters;
 use crate::dynamics::model_types::ParameterError as ParameterError;
+use crate::dynamics::model_types::StateError as StateError;
 use crate::dynamics::model_types::Phase as Phase;
 use crate::dynamics::model_types::Velocity as Velocity;
-use crate::dynamics::impact::SimpleImpact as SimpleImpact;
+use crate::dynamics::model_types::Time as Time;
+use crate::dynamics::model_types::Distance as Distance;
 use crate::dynamics::impact_map::IterationResult as IterationResult;
 use crate::dynamics::impact_map::ImpactMap as ImpactMap;
+use crate::dynamics::impact_map::IterationContext as IterationContext;
+use crate::dynamics::impact_map::IterationControl as IterationControl;
+use crate::dynamics::impact_map::TrajectorySampling as TrajectorySampling;
+use crate::dynamics::impact_map::TrajectorySummary as TrajectorySummary;
+use crate::dynamics::impact_map::StateIterationResult as StateIterationResult;
+use crate::dynamics::impact_map::PreImage as PreImage;
+use crate::dynamics::manifold::Manifold as Manifold;
+use crate::dynamics::manifold::ManifoldBranch as ManifoldBranch;
+use crate::dynamics::manifold::ManifoldGenerator as ManifoldGenerator;
+use crate::dynamics::manifold::ManifoldKind as ManifoldKind;
+use crate::dynamics::manifold::ManifoldSettings as ManifoldSettings;
+use crate::dynamics::periodic_orbit::OrbitSolver as OrbitSolver;
+use crate::dynamics::periodic_orbit::PeriodicOrbit as PeriodicOrbit;
+use crate::dynamics::periodic_orbit::ShootingError as ShootingError;
+use crate::dynamics::linearisation::Eigenvalues as Eigenvalues;
+use crate::dynamics::continuation::Branch as Branch;
+use crate::dynamics::continuation::Continuation as Continuation;
+use crate::dynamics::continuation::ContinuationSettings as ContinuationSettings;
+use crate::dynamics::parameters::ParameterName as ParameterName;
+use crate::dynamics::grazing::GrazingLocator as GrazingLocator;
+use crate::dynamics::grazing::GrazingPoint as GrazingPoint;
+use crate::dynamics::grazing::NormalForm as NormalForm;
+use crate::dynamics::orbit_chart::ChartAxis as ChartAxis;
+use crate::dynamics::orbit_chart::OrbitChart as OrbitChart;
+use crate::dynamics::orbit_chart::OrbitChartGenerator as OrbitChartGenerator;
+use crate::dynamics::hysteresis::ParameterSweep as ParameterSweep;
+use crate::dynamics::hysteresis::Sweep as Sweep;
+use crate::dynamics::schedule::ParameterSchedule as ParameterSchedule;
+use crate::dynamics::schedule::ScheduledIterator as ScheduledIterator;
+use crate::dynamics::limiting_cases::CircleMap as CircleMap;
+use crate::dynamics::density::DensityHistogram as DensityHistogram;
+use crate::dynamics::dimension::correlation_dimension as correlation_dimension;
+use crate::dynamics::dimension::box_counting_dimension as box_counting_dimension;
+use crate::dynamics::dimension::DimensionEstimate as DimensionEstimate;
+use crate::dynamics::recurrence::RecurrencePlot as RecurrencePlot;
+use crate::dynamics::recurrence::RecurrenceQuantification as RecurrenceQuantification;
+use crate::dynamics::symbolic::SymbolicEncoding as SymbolicEncoding;
+use crate::dynamics::symbolic::SymbolSequence as SymbolSequence;
+use crate::dynamics::symbolic::EntropyEstimate as EntropyEstimate;
+use crate::dynamics::symbolic::Symbol as Symbol;
+use crate::dynamics::symbolic::Word as Word;
+use crate::dynamics::rotation::LockingDetector as LockingDetector;
+use crate::dynamics::rotation::RotationPoint as RotationPoint;
+use crate::dynamics::rotation::RotationSweep as RotationSweep;
+use crate::dynamics::transient::TransientAnalyser as TransientAnalyser;
+use crate::dynamics::orbit_chart::evenly_spaced as evenly_spaced;
+use crate::dynamics::basin::BasinStability as BasinStability;
+use crate::dynamics::basin::BasinStabilityEstimate as BasinStabilityEstimate;
+use crate::dynamics::basin::SamplingRegion as SamplingRegion;
+use crate::dynamics::motion::StateOfMotion as StateOfMotion;
+use crate::dynamics::impact::Impact as Impact;
+use crate::dynamics::impact::SimpleImpact as SimpleImpact;
+use crate::dynamics::classification::OrbitType as OrbitType;
 
 #[pyclass]
 #[derive(Clone, Default, Debug, Copy)]
@@ -239,19 +342,28 @@ pub struct IterationInputs {
     max_periods: u32,
     phi: f64,
     v: f64,
-    num_iterations: u32
+    num_iterations: u32,
+    // Which impacts are returned - see `TrajectorySampling`
+    skip_impacts: u32,
+    keep_every: u32,
+    max_points: u32
 }
 
 #[pymethods]
 impl IterationInputs {
     #[new]
+    #[args(skip_impacts = "0", keep_every = "1", max_points = "0")]
+    #[allow(clippy::too_many_arguments)]
     fn new(frequency: f64,
         offset: f64,
         r: f64,
         max_periods: u32,
         phi: f64,
         v: f64,
-        num_iterations: u32) -> PyResult<Self>
+        num_iterations: u32,
+        skip_impacts: u32,
+        keep_every: u32,
+        max_points: u32) -> PyResult<Self>
     {
         Ok(IterationInputs
         {
@@ -261,7 +373,10 @@ impl IterationInputs {
             max_periods: max_periods,
             phi: phi,
             v: v,
-            num_iterations: num_iterations
+            num_iterations: num_iterations,
+            skip_impacts,
+            keep_every,
+            max_points
         })
     }
 
@@ -292,6 +407,18 @@ impl IterationInputs {
     pub fn num_iterations(&self) -> u32 {
         self.num_iterations
     }
+
+    pub fn skip_impacts(&self) -> u32 {
+        self.skip_impacts
+    }
+
+    pub fn keep_every(&self) -> u32 {
+        self.keep_every
+    }
+
+    pub fn max_points(&self) -> u32 {
+        self.max_points
+    }
 }
 
 impl IterationInputs {
@@ -307,12 +434,176 @@ impl IterationInputs {
         }
     }
 
+    fn sampling(&self) -> TrajectorySampling {
+        TrajectorySampling::new(self.skip_impacts, self.keep_every, self.max_points)
+    }
+
     pub fn iterate(&self)-> Result<IterationResult, Vec<ParameterError>> {
+        self.iterate_with_control(IterationControl::new())
+    }
+
+    pub fn iterate_with_control(&self, control: IterationControl)-> Result<IterationResult, Vec<ParameterError>> {
         debug!("Calling iterate() on {:?}", self);
-        let result = self.mapper()?.iterate_from_point(self.phi, self.v, self.num_iterations);
+        let mapper = self.mapper()?;
+        let initial_impact = mapper.impact_from_point(self.phi, self.v);
+        let mut control = control.with_sampling(self.sampling());
+        let result = mapper.iterate_with_control(&mut IterationContext::new(), &mut control, initial_impact, self.num_iterations);
 
         Ok(result)
     }
+
+    pub fn pre_images(&self) -> Result<Vec<PreImageOutputs>, Vec<ParameterError>> {
+        debug!("Calling pre_images() on {:?}", self);
+        let mapper = self.mapper()?;
+        let converter = mapper.converter();
+        let impact = mapper.impact_from_point(self.phi, self.v);
+
+        Ok(mapper.pre_images(impact).into_iter().map(|pre_image| PreImageOutputs::from_pre_image(pre_image, |time| converter.time_to_phase(time))).collect())
+    }
+}
+
+// Initial conditions given as a general state of motion (t, x, v) rather than as an impact
+#[pyclass]
+#[derive(Clone, Default, Debug, Copy)]
+pub struct StateIterationInputs {
+    frequency: f64,
+    offset: f64,
+    r: f64,
+    max_periods: u32,
+    t: f64,
+    x: f64,
+    v: f64,
+    num_iterations: u32
+}
+
+#[pymethods]
+impl StateIterationInputs {
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    fn new(frequency: f64,
+        offset: f64,
+        r: f64,
+        max_periods: u32,
+        t: f64,
+        x: f64,
+        v: f64,
+        num_iterations: u32) -> PyResult<Self>
+    {
+        Ok(StateIterationInputs{frequency, offset, r, max_periods, t, x, v, num_iterations})
+    }
+
+    pub fn t(&self) -> f64 {
+        self.t
+    }
+
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    pub fn v(&self) -> f64 {
+        self.v
+    }
+}
+
+impl StateIterationInputs {
+    fn mapper(&self) -> Result<ImpactMap, Vec<ParameterError>> {
+        Ok(ImpactMap::new(Parameters::new(self.frequency, self.offset, self.r, self.max_periods)?))
+    }
+
+    pub fn iterate(&self, mapper: &ImpactMap) -> Result<StateIterationResult, StateError> {
+        debug!("Calling iterate() on {:?}", self);
+        mapper.iterate_from_state(StateOfMotion::new(self.t, self.x, self.v), self.num_iterations)
+    }
+}
+
+// Many initial impacts sharing a single set of parameters, built from NumPy arrays of phases and velocities
+#[pyclass]
+#[derive(Clone, Default, Debug)]
+pub struct BatchIterationInputs {
+    frequency: f64,
+    offset: f64,
+    r: f64,
+    max_periods: u32,
+    phis: Column<Phase>,
+    vs: Column<Velocity>,
+    num_iterations: u32
+}
+
+#[pymethods]
+impl BatchIterationInputs {
+    #[new]
+    fn new(frequency: f64,
+        offset: f64,
+        r: f64,
+        max_periods: u32,
+        phi: PyReadonlyArray1<Phase>,
+        v: PyReadonlyArray1<Velocity>,
+        num_iterations: u32) -> PyResult<Self>
+    {
+        if phi.len() != v.len() {
+            use pyo3::exceptions::*;
+            return Err(PyValueError::new_err(format!("Initial phases ({}) and velocities ({}) must have the same length", phi.len(), v.len())));
+        }
+
+        Ok(BatchIterationInputs
+        {
+            frequency,
+            offset,
+            r,
+            max_periods,
+            phis: Column::Values(phi.to_vec()?),
+            vs: Column::Values(v.to_vec()?),
+            num_iterations
+        })
+    }
+
+    pub fn phi<'py>(&mut self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        self.phis.array(py)
+    }
+
+    pub fn v<'py>(&mut self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        self.vs.array(py)
+    }
+}
+
+impl BatchIterationInputs {
+    fn mapper(&self) -> Result<ImpactMap, Vec<ParameterError>> {
+        Ok(ImpactMap::new(Parameters::new(self.frequency, self.offset, self.r, self.max_periods)?))
+    }
+
+    // Read with the GIL held, so that the batch can then be iterated without it
+    fn initial_impacts(&self, mapper: &ImpactMap) -> Vec<Impact> {
+        self.phis.with_values(|phis| self.vs.with_values(|vs| phis.iter().zip(vs.iter())
+            .map(|(&phi, &v)| mapper.impact_from_point(phi, v))
+            .collect()))
+    }
+
+    // The inputs for a single member of the batch
+    fn inputs(&self, idx: usize) -> IterationInputs {
+        IterationInputs{
+            frequency: self.frequency,
+            offset: self.offset,
+            r: self.r,
+            max_periods: self.max_periods,
+            phi: self.phis.with_values(|phis| phis[idx]),
+            v: self.vs.with_values(|vs| vs[idx]),
+            num_iterations: self.num_iterations,
+            ..IterationInputs::default()
+        }
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for BatchIterationInputs {
+    fn __len__(&self) -> PyResult<usize> {
+        Ok(self.phis.len())
+    }
+
+    fn __getitem__(&self, idx: isize) -> PyResult<IterationInputs> {
+        let idx_to_use = make_idx_usable(idx, self.phis.len())?;
+
+        Ok(self.inputs(idx_to_use))
+    }
 }
 
 #[pyclass]
@@ -343,14 +634,6 @@ impl PyImpact {
 	}
 }
 
-impl From<SimpleImpact> for PyImpact {
-    fn from(impact: SimpleImpact) -> PyImpact {
-        PyImpact {
-            phase: impact.phase(),
-            velocity: impact.velocity(),
-        }
-    }
-}
 
 #[pyproto]
 impl PyIterProtocol for PyImpact {
@@ -391,12 +674,64 @@ impl PyIterProtocol for PyPyImpactIter {
     }
 }
 
+// One column of numbers, held in a `Vec` until Python first asks for it. The `Vec` is then handed over to NumPy
+// without copying, and every later request borrows the same array.
+#[derive(Clone, Debug)]
+enum Column<T> {
+    Values(Vec<T>),
+    Array(Py<PyArray1<T>>)
+}
+
+impl<T: Element + Copy> Column<T> {
+    fn array<'py>(&mut self, py: Python<'py>) -> &'py PyArray1<T> {
+        if let Column::Values(values) = self {
+            *self = Column::Array(Py::from(PyArray1::from_vec(py, std::mem::take(values))));
+        }
+
+        match self {
+            Column::Array(array) => array.clone_ref(py).into_ref(py),
+            Column::Values(_) => unreachable!()
+        }
+    }
+
+    // Reads the values wherever they are held, taking the GIL once they belong to NumPy
+    fn with_values<R>(&self, f: impl FnOnce(&[T]) -> R) -> R {
+        match self {
+            Column::Values(values) => f(values),
+            // An array made from a `Vec` is always contiguous
+            Column::Array(array) => Python::with_gil(|py| f(array.as_ref(py).readonly().as_slice().unwrap()))
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.with_values(|values| values.len())
+    }
+}
+
+impl<T> Default for Column<T> {
+    fn default() -> Self {
+        Column::Values(vec![])
+    }
+}
+
+// The trajectory is held column-wise, so that each column can be handed to NumPy as a single contiguous
+// block rather than one `PyImpact` at a time
 #[pyclass]
 #[derive(Clone, Default)]
 pub struct IterationOutputs {
-	impacts: Vec<SimpleImpact>,
+	phases: Column<Phase>,
+
+	velocities: Column<Velocity>,
+
+	times: Column<Time>,
+
+	long_excursions: bool,
 
-	long_excursions: bool
+	non_impacting_amplitude: Option<f64>,
+
+	symplectic_error: Option<f64>,
+
+	cancelled: bool
 }
 
 #[pymethods]
@@ -404,50 +739,159 @@ impl IterationOutputs {
     #[new]
     fn new() -> PyResult<Self>
     {
-        Ok(IterationOutputs
-        {
-            impacts: vec![],
-            long_excursions: false
-        })
+        Ok(IterationOutputs::default())
     }
 
+    pub fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    pub fn long_excursions(&self) -> bool {
+        self.long_excursions
+    }
+
+    // `None` unless a long excursion ended in a state from which the motion can never reach the obstacle
+    pub fn non_impacting_amplitude(&self) -> Option<f64> {
+        self.non_impacting_amplitude
+    }
+
+    // `None` unless r = 1, when it is the largest departure of any step from area preservation
+    pub fn symplectic_error(&self) -> Option<f64> {
+        self.symplectic_error
+    }
+
+    pub fn phases<'py>(&mut self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        self.phases.array(py)
+    }
+
+    pub fn velocities<'py>(&mut self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        self.velocities.array(py)
+    }
+
+    pub fn times<'py>(&mut self, py: Python<'py>) -> &'py PyArray1<Time> {
+        self.times.array(py)
+    }
+}
+
+impl IterationOutputs {
+    fn from_trajectory(trajectory: &[Impact], long_excursions: bool, cancelled: bool) -> IterationOutputs {
+        IterationOutputs {
+            long_excursions,
+            non_impacting_amplitude: None,
+            symplectic_error: None,
+            cancelled,
+            phases: Column::Values(trajectory.iter().map(|impact| impact.phase()).collect()),
+            velocities: Column::Values(trajectory.iter().map(|impact| impact.velocity()).collect()),
+            times: Column::Values(trajectory.iter().map(|impact| impact.time()).collect())
+        }
+    }
 }
 
 impl From<&IterationResult> for IterationOutputs {
     fn from(result: &IterationResult) -> IterationOutputs {
         IterationOutputs {
-            long_excursions: result.has_long_excursions(),
-            impacts: result.trajectory().iter().map(|&impact| impact.get_simple_impact()).collect()
+            non_impacting_amplitude: result.non_impacting_amplitude(),
+            symplectic_error: result.symplectic_error(),
+            ..IterationOutputs::from_trajectory(result.trajectory(), result.has_long_excursions(), result.was_cancelled())
         }
     }
 }
 
-fn make_idx_usable (idx: isize, size: usize) -> usize {
-    if idx < 0 {
-        return make_idx_usable(idx + size as isize, size)
+// One entry per initial impact in a batch. Periodic orbits report their (m, n) through `impacts_per_cycle`
+// and `periods_per_cycle`, which are both zero for any other kind of orbit.
+#[pyclass]
+pub struct BatchIterationOutputs {
+    summaries: Vec<TrajectorySummary>
+}
+
+#[pymethods]
+impl BatchIterationOutputs {
+    pub fn final_phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_iter(py, self.summaries.iter().map(|summary| summary.final_impact().phase()))
+    }
+
+    pub fn final_velocities<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_iter(py, self.summaries.iter().map(|summary| summary.final_impact().velocity()))
+    }
+
+    pub fn final_times<'py>(&self, py: Python<'py>) -> &'py PyArray1<Time> {
+        PyArray1::from_iter(py, self.summaries.iter().map(|summary| summary.final_impact().time()))
+    }
+
+    pub fn long_excursions<'py>(&self, py: Python<'py>) -> &'py PyArray1<bool> {
+        PyArray1::from_iter(py, self.summaries.iter().map(|summary| summary.has_long_excursions()))
+    }
+
+    pub fn orbit_types(&self) -> Vec<String> {
+        self.summaries.iter().map(|summary| summary.orbit_type().to_string()).collect()
+    }
+
+    pub fn impacts_per_cycle<'py>(&self, py: Python<'py>) -> &'py PyArray1<u32> {
+        PyArray1::from_iter(py, self.summaries.iter().map(|summary| match summary.orbit_type() {
+            OrbitType::Periodic{impacts, ..} => impacts,
+            _ => 0
+        }))
+    }
+
+    pub fn periods_per_cycle<'py>(&self, py: Python<'py>) -> &'py PyArray1<u32> {
+        PyArray1::from_iter(py, self.summaries.iter().map(|summary| match summary.orbit_type() {
+            OrbitType::Periodic{periods, ..} => periods,
+            _ => 0
+        }))
     }
 
-    idx as usize
+    // The full trajectory for one member of the batch, if trajectories were kept
+    pub fn trajectory(&self, idx: isize) -> PyResult<Option<IterationOutputs>> {
+        let idx_to_use = make_idx_usable(idx, self.summaries.len())?;
+
+        let summary = &self.summaries[idx_to_use];
+
+        let non_impacting_amplitude = match summary.orbit_type() {
+            OrbitType::NonImpacting{amplitude} => Some(amplitude),
+            _ => None
+        };
+
+        Ok(summary.trajectory().map(|trajectory| IterationOutputs{
+            non_impacting_amplitude,
+            ..IterationOutputs::from_trajectory(trajectory, summary.has_long_excursions(), false)
+        }))
+    }
 }
 
 #[pyproto]
-impl PySequenceProtocol for IterationOutputs {
+impl PySequenceProtocol for BatchIterationOutputs {
     fn __len__(&self) -> PyResult<usize> {
-        Ok(self.impacts.len())
+        Ok(self.summaries.len())
     }
+}
 
-    fn __getitem__(&self, idx: isize) -> PyResult<PyImpact> {
+// Python-style indexing, in which negative indices count back from the end
+fn python_index(idx: isize, size: usize) -> Option<usize> {
+    let idx_to_use = if idx < 0 {idx + size as isize} else {idx};
+
+    if idx_to_use < 0 || idx_to_use as usize >= size {
+        return None;
+    }
 
-        let usable_idx = |idx: isize| -> usize {make_idx_usable(idx, self.impacts.len())};
+    Some(idx_to_use as usize)
+}
 
-        let idx_to_use = usable_idx(idx);
+fn make_idx_usable(idx: isize, size: usize) -> PyResult<usize> {
+    use pyo3::exceptions::*;
 
-        if idx_to_use >= self.impacts.len() {
-            use pyo3::exceptions::*;
-            return Err(PyIndexError::new_err("Invalid index"));
-        }
+    python_index(idx, size).ok_or_else(|| PyIndexError::new_err("Invalid index"))
+}
+
+#[pyproto]
+impl PySequenceProtocol for IterationOutputs {
+    fn __len__(&self) -> PyResult<usize> {
+        Ok(self.phases.len())
+    }
+
+    fn __getitem__(&self, idx: isize) -> PyResult<PyImpact> {
+        let idx_to_use = make_idx_usable(idx, self.phases.len())?;
 
-        Ok(PyImpact::from(self.impacts[idx_to_use]))
+        Ok(PyImpact{phase: self.phases.with_values(|phases| phases[idx_to_use]), velocity: self.velocities.with_values(|velocities| velocities[idx_to_use])})
     }
 }
 
@@ -475,46 +919,1492 @@ impl PyIterProtocol for PyIterationOutputsIter {
 }
 
 
-#[pyfunction]
-fn iterate(inputs: IterationInputs) -> IterationOutputs {
-    let result = inputs.iterate().unwrap();
+/// A flag which can be shared between Python and a running computation, so that the computation can be
+/// abandoned (e.g. when the client which requested it has gone away)
+#[pyclass]
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>
+}
+
+#[pymethods]
+impl CancellationToken {
+    #[new]
+    fn new() -> PyResult<Self>
+    {
+        Ok(CancellationToken::default())
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
 
-    IterationOutputs::from(&result)
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
 }
 
-#[pyfunction]
-fn validate(inputs: IterationInputs) -> IterationInputs {
-    inputs
+// Runs the iteration without holding the GIL, which is only re-acquired to call `progress`. If `progress` raises,
+// the iteration is stopped and the exception is passed back to the caller, leaving `cancellation` untouched.
+#[pyfunction(progress = "None", progress_interval = "1000", cancellation = "None")]
+fn iterate(py: Python, inputs: IterationInputs, progress: Option<PyObject>, progress_interval: u32, cancellation: Option<CancellationToken>) -> PyResult<IterationOutputs> {
+    let token = cancellation.unwrap_or_default();
+
+    let progress_failed = AtomicBool::new(false);
+
+    let mut progress_error: Option<PyErr> = None;
+
+    let result = py.allow_threads(|| {
+        let progress_failed = &progress_failed;
+        let progress_error = &mut progress_error;
+
+        let mut control = IterationControl::new().with_cancellation(&token.flag).with_cancellation(progress_failed);
+
+        if let Some(callback) = progress.as_ref() {
+            control = control.with_progress(progress_interval, move |iterations_done| {
+                if let Err(error) = Python::with_gil(|py| callback.call1(py, (iterations_done,))) {
+                    progress_error.get_or_insert(error);
+                    progress_failed.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+
+        inputs.iterate_with_control(control)
+    }).map_err(parameter_errors_to_py)?;
+
+    match progress_error {
+        Some(error) => Err(error),
+        None => Ok(IterationOutputs::from(&result))
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::convert::TryFrom;
+fn parameter_errors_to_py(errors: Vec<ParameterError>) -> PyErr {
+    use pyo3::exceptions::*;
+    PyValueError::new_err(errors.iter().map(|error| error.to_string()).collect::<Vec<String>>().join("; "))
+}
 
-    #[test]
-    fn can_access_parameter_properties() {
-        let properties = ParameterProperties::from(vec![("frequency", "ω")]);
-        assert_eq!(properties.property("frequency").property, String::from("ω"));
-        assert_eq!(properties.property("period").property, String::from(""));
+// Validates the parameters once and then iterates every member of the batch in parallel, without holding the GIL
+#[pyfunction(keep_trajectories = "false")]
+fn iterate_batch(py: Python, inputs: BatchIterationInputs, keep_trajectories: bool) -> PyResult<BatchIterationOutputs> {
+    let mapper = inputs.mapper().map_err(parameter_errors_to_py)?;
+
+    debug!("Calling iterate() on a batch of {} initial impacts", inputs.phis.len());
+    let initial_impacts = inputs.initial_impacts(&mapper);
+
+    let summaries = py.allow_threads(|| mapper.iterate_batch(&initial_impacts, inputs.num_iterations, keep_trajectories));
+
+    Ok(BatchIterationOutputs{summaries})
+}
+
+// The outcome of a simulation from a general state: `outcome` is one of "impacting", "non-impacting" or
+// "no impact", and only impacting motion has `impacts`
+#[pyclass]
+pub struct StateIterationOutputs {
+    outcome: String,
+    amplitude: Option<f64>,
+    impacts: Option<IterationOutputs>
+}
+
+#[pymethods]
+impl StateIterationOutputs {
+    pub fn outcome(&self) -> String {
+        self.outcome.clone()
     }
 
-    #[test]
-    fn can_run_to_completion() {
-        let inputs = IterationInputs{
-            frequency: 4.85,
-            offset: -0.1,
-            r: 0.8,
-            max_periods: 100,
-            phi: 0.0,
-            v: 0.0,
-            num_iterations: 1000
-        };
-        
-        let result = iterate(inputs);
+    pub fn amplitude(&self) -> Option<f64> {
+        self.amplitude
+    }
 
-        let n = u32::try_from(result.impacts.len()).unwrap();
+    pub fn impacts(&self) -> Option<IterationOutputs> {
+        self.impacts.clone()
+    }
+}
 
-        assert_eq!(inputs.num_iterations+1, n);
+impl From<StateIterationResult> for StateIterationOutputs {
+    fn from(result: StateIterationResult) -> StateIterationOutputs {
+        match result {
+            StateIterationResult::Impacting(result) => StateIterationOutputs{
+                outcome: String::from("impacting"), amplitude: None, impacts: Some(IterationOutputs::from(&result))},
+            StateIterationResult::NonImpacting{amplitude} => StateIterationOutputs{
+                outcome: String::from("non-impacting"), amplitude: Some(amplitude), impacts: None},
+            StateIterationResult::NoImpact => StateIterationOutputs{
+                outcome: String::from("no impact"), amplitude: None, impacts: None}
+        }
+    }
+}
+
+#[pyfunction]
+fn iterate_from_state(py: Python, inputs: StateIterationInputs) -> PyResult<StateIterationOutputs> {
+    use pyo3::exceptions::*;
+
+    let mapper = inputs.mapper().map_err(parameter_errors_to_py)?;
+
+    let result = py.allow_threads(|| inputs.iterate(&mapper)).map_err(|error| PyValueError::new_err(error.to_string()))?;
+
+    Ok(StateIterationOutputs::from(result))
+}
+
+// A pre-image of an impact: either a single impact, an impact with any velocity (when r = 0) or the whole
+// of a sticking interval, given as a range of phases
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct PreImageOutputs {
+    kind: String,
+    phase: Phase,
+    velocity: Option<Velocity>,
+    end_phase: Option<Phase>
+}
+
+#[pymethods]
+impl PreImageOutputs {
+    pub fn kind(&self) -> String {
+        self.kind.clone()
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn velocity(&self) -> Option<Velocity> {
+        self.velocity
+    }
+
+    pub fn end_phase(&self) -> Option<Phase> {
+        self.end_phase
+    }
+}
+
+impl PreImageOutputs {
+    fn from_pre_image(pre_image: PreImage, to_phase: impl Fn(Time) -> Phase) -> PreImageOutputs {
+        match pre_image {
+            PreImage::Impact(impact) => PreImageOutputs{
+                kind: String::from("impact"), phase: impact.phase(), velocity: Some(impact.velocity()), end_phase: None},
+            PreImage::AnyVelocity{time} => PreImageOutputs{
+                kind: String::from("any velocity"), phase: to_phase(time), velocity: None, end_phase: None},
+            PreImage::Sticking{from, to} => PreImageOutputs{
+                kind: String::from("sticking"), phase: to_phase(from), velocity: Some(0.0), end_phase: Some(to_phase(to))}
+        }
+    }
+}
+
+// The pre-images of the impact given by the initial phase and velocity of `inputs`
+#[pyfunction]
+fn pre_images(py: Python, inputs: IterationInputs) -> PyResult<Vec<PreImageOutputs>> {
+    py.allow_threads(|| inputs.pre_images()).map_err(parameter_errors_to_py)
+}
+
+// One branch of a manifold of a saddle orbit, as a polyline which is broken where it crosses the singularity set
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct ManifoldOutputs {
+    eigenvalue: f64,
+    arc_length: f64,
+    phases: Vec<Phase>,
+    velocities: Vec<Velocity>,
+    discontinuities: Vec<usize>
+}
+
+#[pymethods]
+impl ManifoldOutputs {
+    pub fn eigenvalue(&self) -> f64 {
+        self.eigenvalue
+    }
+
+    pub fn arc_length(&self) -> f64 {
+        self.arc_length
+    }
+
+    pub fn phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phases)
+    }
+
+    pub fn velocities<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_slice(py, &self.velocities)
+    }
+
+    // Indices of points which are not joined to the point before
+    pub fn discontinuities(&self) -> Vec<usize> {
+        self.discontinuities.clone()
+    }
+}
+
+impl From<&Manifold> for ManifoldOutputs {
+    fn from(manifold: &Manifold) -> ManifoldOutputs {
+        ManifoldOutputs{
+            eigenvalue: manifold.eigenvalue(),
+            arc_length: manifold.arc_length(),
+            phases: manifold.points().iter().map(|point| point.phase()).collect(),
+            velocities: manifold.points().iter().map(|point| point.velocity()).collect(),
+            discontinuities: manifold.discontinuities().to_vec()
+        }
+    }
+}
+
+// A manifold of the saddle orbit with `impacts_per_cycle` impacts per cycle through the impact given by the
+// initial phase and velocity of `inputs`
+#[pyfunction(stable = "false", negative_branch = "false", max_points = "2000", max_arc_length = "5.0")]
+fn manifold(py: Python, inputs: IterationInputs, impacts_per_cycle: u32, stable: bool, negative_branch: bool, max_points: usize, max_arc_length: f64) -> PyResult<ManifoldOutputs> {
+    use pyo3::exceptions::*;
+
+    let mapper = inputs.mapper().map_err(parameter_errors_to_py)?;
+
+    let kind = if stable {ManifoldKind::Stable} else {ManifoldKind::Unstable};
+    let branch = if negative_branch {ManifoldBranch::Negative} else {ManifoldBranch::Positive};
+    let settings = ManifoldSettings{maximum_points: max_points, maximum_arc_length: max_arc_length, ..ManifoldSettings::default()};
+
+    let result = py.allow_threads(|| {
+        let orbit = mapper.impact_from_point(inputs.phi, inputs.v);
+
+        ManifoldGenerator::new(&mapper, settings).generate(orbit, impacts_per_cycle, kind, branch)
+    });
+
+    result.map(|manifold| ManifoldOutputs::from(&manifold)).map_err(|error| PyValueError::new_err(error.to_string()))
+}
+
+// An (m, n) periodic orbit found by Newton shooting, with its stability multipliers as (real, imaginary) pairs
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct PeriodicOrbitOutputs {
+    impacts: u32,
+    periods: u32,
+    phases: Vec<Phase>,
+    velocities: Vec<Velocity>,
+    times: Vec<Time>,
+    multipliers: Vec<(f64, f64)>,
+    stable: bool,
+    iterations: u32,
+    residual: f64
+}
+
+#[pymethods]
+impl PeriodicOrbitOutputs {
+    pub fn impacts(&self) -> u32 {
+        self.impacts
+    }
+
+    pub fn periods(&self) -> u32 {
+        self.periods
+    }
+
+    pub fn phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phases)
+    }
+
+    pub fn velocities<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_slice(py, &self.velocities)
+    }
+
+    pub fn times<'py>(&self, py: Python<'py>) -> &'py PyArray1<Time> {
+        PyArray1::from_slice(py, &self.times)
+    }
+
+    pub fn multipliers(&self) -> Vec<(f64, f64)> {
+        self.multipliers.clone()
+    }
+
+    pub fn stable(&self) -> bool {
+        self.stable
+    }
+
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    pub fn residual(&self) -> f64 {
+        self.residual
+    }
+}
+
+impl From<&PeriodicOrbit> for PeriodicOrbitOutputs {
+    fn from(orbit: &PeriodicOrbit) -> PeriodicOrbitOutputs {
+        PeriodicOrbitOutputs{
+            impacts: orbit.impacts(),
+            periods: orbit.periods(),
+            phases: orbit.points().iter().map(|point| point.phase()).collect(),
+            velocities: orbit.points().iter().map(|point| point.velocity()).collect(),
+            times: orbit.points().iter().map(|point| point.time()).collect(),
+            multipliers: multipliers_to_pairs(orbit.multipliers()),
+            stable: orbit.is_stable(),
+            iterations: orbit.diagnostics().iterations,
+            residual: orbit.diagnostics().residual
+        }
+    }
+}
+
+// Multipliers as (real, imaginary) pairs
+fn multipliers_to_pairs(multipliers: Eigenvalues) -> Vec<(f64, f64)> {
+    match multipliers {
+        Eigenvalues::Real(first, second) => vec![(first, 0.0), (second, 0.0)],
+        Eigenvalues::ComplexPair{real, imaginary} => vec![(real, imaginary), (real, -imaginary)]
+    }
+}
+
+// Finds an (m, n) orbit, starting either from the initial impact of `inputs` or, if `from_trajectory` is set, from
+// the closest approach to such an orbit of the trajectory which `inputs` generates
+#[pyfunction(from_trajectory = "false")]
+fn find_orbit(py: Python, inputs: IterationInputs, impacts: u32, periods: u32, from_trajectory: bool) -> PyResult<PeriodicOrbitOutputs> {
+    use pyo3::exceptions::*;
+
+    let mapper = inputs.mapper().map_err(parameter_errors_to_py)?;
+
+    let result = py.allow_threads(|| {
+        let solver = OrbitSolver::default(&mapper);
+
+        if from_trajectory {
+            let trajectory = mapper.iterate(mapper.impact_from_point(inputs.phi, inputs.v), inputs.num_iterations);
+
+            solver.solve_from_trajectory(trajectory.trajectory(), impacts, periods)
+        } else {
+            solver.solve(mapper.impact_from_point(inputs.phi, inputs.v), impacts, periods)
+        }
+    });
+
+    result.map(|orbit| PeriodicOrbitOutputs::from(&orbit)).map_err(|error| PyValueError::new_err(error.to_string()))
+}
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct BranchOutputs {
+    parameter: String,
+    parameters: Vec<f64>,
+    phases: Vec<Phase>,
+    velocities: Vec<Velocity>,
+    multipliers: Vec<Vec<(f64, f64)>>,
+    stable: Vec<bool>,
+    events: Vec<(String, f64)>,
+    termination: String
+}
+
+#[pymethods]
+impl BranchOutputs {
+    pub fn parameter(&self) -> String {
+        self.parameter.clone()
+    }
+
+    pub fn parameters<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.parameters)
+    }
+
+    pub fn phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phases)
+    }
+
+    pub fn velocities<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_slice(py, &self.velocities)
+    }
+
+    pub fn multipliers(&self) -> Vec<Vec<(f64, f64)>> {
+        self.multipliers.clone()
+    }
+
+    pub fn stable(&self) -> Vec<bool> {
+        self.stable.clone()
+    }
+
+    pub fn events(&self) -> Vec<(String, f64)> {
+        self.events.clone()
+    }
+
+    pub fn termination(&self) -> String {
+        self.termination.clone()
+    }
+}
+
+impl From<&Branch> for BranchOutputs {
+    fn from(branch: &Branch) -> BranchOutputs {
+        BranchOutputs{
+            parameter: branch.parameter().name().to_string(),
+            parameters: branch.points().iter().map(|point| point.parameter()).collect(),
+            phases: branch.points().iter().map(|point| point.orbit()[0].phase()).collect(),
+            velocities: branch.points().iter().map(|point| point.orbit()[0].velocity()).collect(),
+            multipliers: branch.points().iter().map(|point| multipliers_to_pairs(point.multipliers())).collect(),
+            stable: branch.points().iter().map(|point| point.is_stable()).collect(),
+            events: branch.events().iter().map(|event| (event.kind.to_string(), event.parameter)).collect(),
+            termination: branch.termination().to_string()
+        }
+    }
+}
+
+// Finds an (m, n) orbit from the initial impact of `inputs` and follows it as `parameter` is varied towards `end`
+#[pyfunction(max_points = "1000")]
+fn continue_orbit(py: Python, inputs: IterationInputs, impacts: u32, periods: u32, parameter: &str, end: f64, max_points: usize) -> PyResult<BranchOutputs> {
+    use pyo3::exceptions::*;
+
+    let name = ParameterName::from_name(parameter).ok_or_else(|| PyValueError::new_err(format!("Cannot continue in parameter {}", parameter)))?;
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let mapper = ImpactMap::new(parameters);
+
+    let start = parameters.value(name);
+
+    let branch = py.allow_threads(|| {
+        let orbit = OrbitSolver::default(&mapper).solve(mapper.impact_from_point(inputs.phi, inputs.v), impacts, periods)?;
+
+        let mut settings = ContinuationSettings::new((start.min(end), start.max(end)));
+
+        settings.maximum_points = max_points;
+
+        Ok(Continuation::new(parameters, name, impacts, periods, settings).run(&orbit, end > start))
+    });
+
+    branch.map(|branch| BranchOutputs::from(&branch)).map_err(|error: ShootingError| PyValueError::new_err(error.to_string()))
+}
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct NormalFormOutputs {
+    smooth: Vec<Vec<f64>>,
+    parameter_derivative: (f64, f64),
+    depth_gradient: (f64, f64),
+    depth_parameter_derivative: f64,
+    square_root: (f64, f64),
+    scenario: String,
+    penetrates_for_increasing_parameter: Option<bool>
+}
+
+#[pymethods]
+impl NormalFormOutputs {
+    pub fn smooth(&self) -> Vec<Vec<f64>> {
+        self.smooth.clone()
+    }
+
+    pub fn parameter_derivative(&self) -> (f64, f64) {
+        self.parameter_derivative
+    }
+
+    pub fn depth_gradient(&self) -> (f64, f64) {
+        self.depth_gradient
+    }
+
+    pub fn depth_parameter_derivative(&self) -> f64 {
+        self.depth_parameter_derivative
+    }
+
+    pub fn square_root(&self) -> (f64, f64) {
+        self.square_root
+    }
+
+    pub fn scenario(&self) -> String {
+        self.scenario.clone()
+    }
+
+    pub fn penetrates_for_increasing_parameter(&self) -> Option<bool> {
+        self.penetrates_for_increasing_parameter
+    }
+}
+
+impl From<&NormalForm> for NormalFormOutputs {
+    fn from(normal_form: &NormalForm) -> NormalFormOutputs {
+        let smooth = normal_form.smooth();
+
+        NormalFormOutputs{
+            smooth: (0..2).map(|row| (0..2).map(|column| smooth.entry(row, column)).collect()).collect(),
+            parameter_derivative: normal_form.parameter_derivative(),
+            depth_gradient: normal_form.depth_gradient(),
+            depth_parameter_derivative: normal_form.depth_parameter_derivative(),
+            square_root: normal_form.square_root(),
+            scenario: normal_form.scenario().to_string(),
+            penetrates_for_increasing_parameter: normal_form.penetrates_for_increasing_parameter()
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct GrazingOutputs {
+    parameter: f64,
+    kind: String,
+    phases: Vec<Phase>,
+    velocities: Vec<Velocity>,
+    orbit_parameter: f64,
+    index: usize,
+    margin: f64,
+    slope: f64,
+    normal_form: Option<NormalFormOutputs>
+}
+
+#[pymethods]
+impl GrazingOutputs {
+    pub fn parameter(&self) -> f64 {
+        self.parameter
+    }
+
+    pub fn kind(&self) -> String {
+        self.kind.clone()
+    }
+
+    pub fn phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phases)
+    }
+
+    pub fn velocities<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_slice(py, &self.velocities)
+    }
+
+    pub fn orbit_parameter(&self) -> f64 {
+        self.orbit_parameter
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn margin(&self) -> f64 {
+        self.margin
+    }
+
+    pub fn slope(&self) -> f64 {
+        self.slope
+    }
+
+    pub fn normal_form(&self) -> Option<NormalFormOutputs> {
+        self.normal_form.clone()
+    }
+}
+
+impl GrazingOutputs {
+    fn new(point: &GrazingPoint, normal_form: Option<NormalForm>) -> GrazingOutputs {
+        GrazingOutputs{
+            parameter: point.parameter(),
+            kind: point.kind().to_string(),
+            phases: point.orbit().iter().map(|impact| impact.phase()).collect(),
+            velocities: point.orbit().iter().map(|impact| impact.velocity()).collect(),
+            orbit_parameter: point.orbit_parameter(),
+            index: point.index(),
+            margin: point.margin(),
+            slope: point.slope(),
+            normal_form: normal_form.as_ref().map(NormalFormOutputs::from)
+        }
+    }
+}
+
+// Finds an (m, n) orbit from the initial impact of `inputs` and continues it in `parameter` towards `towards` until
+// it grazes. The normal form is included when it can be derived about the grazing orbit.
+#[pyfunction]
+fn grazing(py: Python, inputs: IterationInputs, impacts: u32, periods: u32, parameter: &str, towards: f64) -> PyResult<GrazingOutputs> {
+    use pyo3::exceptions::*;
+
+    let name = ParameterName::from_name(parameter).ok_or_else(|| PyValueError::new_err(format!("Cannot continue in parameter {}", parameter)))?;
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let mapper = ImpactMap::new(parameters);
+
+    let result = py.allow_threads(|| {
+        let orbit = OrbitSolver::default(&mapper).solve(mapper.impact_from_point(inputs.phi, inputs.v), impacts, periods)
+            .map_err(|error| error.to_string())?;
+
+        let locator = GrazingLocator::new(parameters, name, impacts, periods);
+
+        let point = locator.locate(&orbit, towards).map_err(|error| error.to_string())?;
+
+        let normal_form = locator.normal_form(&point).ok();
+
+        Ok(GrazingOutputs::new(&point, normal_form))
+    });
+
+    result.map_err(|error: String| PyValueError::new_err(error))
+}
+
+// Orbit types over a grid of forcing frequency and one other parameter. `cells` holds a row of indices into
+// `legend` for each value of the other parameter.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct OrbitChartOutputs {
+    parameter: String,
+    frequencies: Vec<f64>,
+    values: Vec<f64>,
+    cells: Vec<Vec<usize>>,
+    legend: Vec<String>
+}
+
+#[pymethods]
+impl OrbitChartOutputs {
+    pub fn parameter(&self) -> String {
+        self.parameter.clone()
+    }
+
+    pub fn frequencies<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.frequencies)
+    }
+
+    pub fn values<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.values)
+    }
+
+    pub fn cells(&self) -> Vec<Vec<usize>> {
+        self.cells.clone()
+    }
+
+    pub fn legend(&self) -> Vec<String> {
+        self.legend.clone()
+    }
+}
+
+impl OrbitChartOutputs {
+    fn new(parameter: &str, chart: &OrbitChart) -> OrbitChartOutputs {
+        let columns = chart.horizontal().len().max(1);
+
+        OrbitChartOutputs{
+            parameter: parameter.to_string(),
+            frequencies: chart.horizontal().to_vec(),
+            values: chart.vertical().to_vec(),
+            cells: chart.cells().chunks(columns).map(|row| row.to_vec()).collect(),
+            legend: chart.legend().iter().map(|label| label.to_string()).collect()
+        }
+    }
+}
+
+// Classifies the orbit reached from the initial impact of `inputs` for each combination of forcing frequency and
+// `parameter` (either "offset" or "r") on a grid. Ranges are given as (minimum, maximum, number of values). Gives
+// None if it is cancelled.
+#[pyfunction(cancellation = "None")]
+fn orbit_chart(py: Python, inputs: IterationInputs, parameter: &str, frequency_range: (f64, f64, u32), parameter_range: (f64, f64, u32),
+    cancellation: Option<CancellationToken>) -> PyResult<Option<OrbitChartOutputs>> {
+    use pyo3::exceptions::*;
+
+    let name = match ParameterName::from_name(parameter) {
+        Some(ParameterName::Frequency) | None => return Err(PyValueError::new_err(format!("Cannot chart forcing frequency against {}", parameter))),
+        Some(name) => name
+    };
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let generator = OrbitChartGenerator::new(
+        parameters,
+        ChartAxis::new(ParameterName::Frequency, frequency_range.0, frequency_range.1, frequency_range.2),
+        ChartAxis::new(name, parameter_range.0, parameter_range.1, parameter_range.2),
+        (inputs.phi, inputs.v),
+        inputs.num_iterations);
+
+    let token = cancellation.unwrap_or_default();
+
+    Ok(py.allow_threads(|| generator.generate(&token.flag)).map(|chart| OrbitChartOutputs::new(parameter, &chart)))
+}
+
+// One branch of a bifurcation diagram. The recorded impacts of every step are flattened, with `parameters` giving
+// the parameter value for each impact, so that the branch can be drawn directly as a scatter plot.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct SweepOutputs {
+    direction: String,
+    parameters: Vec<f64>,
+    phases: Vec<Phase>,
+    velocities: Vec<Velocity>,
+    orbit_types: Vec<(f64, Option<String>)>,
+    jumps: Vec<(f64, f64, String, String)>
+}
+
+#[pymethods]
+impl SweepOutputs {
+    pub fn direction(&self) -> String {
+        self.direction.clone()
+    }
+
+    pub fn parameters<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.parameters)
+    }
+
+    pub fn phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phases)
+    }
+
+    pub fn velocities<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_slice(py, &self.velocities)
+    }
+
+    pub fn orbit_types(&self) -> Vec<(f64, Option<String>)> {
+        self.orbit_types.clone()
+    }
+
+    pub fn jumps(&self) -> Vec<(f64, f64, String, String)> {
+        self.jumps.clone()
+    }
+}
+
+impl From<&Sweep> for SweepOutputs {
+    fn from(sweep: &Sweep) -> SweepOutputs {
+        let impacts = || sweep.steps().iter().flat_map(|step| step.impacts().iter().map(move |impact| (step.parameter(), impact)));
+
+        SweepOutputs{
+            direction: sweep.direction().to_string(),
+            parameters: impacts().map(|(parameter, _)| parameter).collect(),
+            phases: impacts().map(|(_, impact)| impact.phase()).collect(),
+            velocities: impacts().map(|(_, impact)| impact.velocity()).collect(),
+            orbit_types: sweep.steps().iter().map(|step| (step.parameter(), step.orbit_type().map(|orbit_type| orbit_type.to_string()))).collect(),
+            jumps: sweep.jumps().iter().map(|jump| (jump.from_parameter, jump.to_parameter, jump.from.to_string(), jump.to.to_string())).collect()
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct HysteresisOutputs {
+    parameter: String,
+    up: SweepOutputs,
+    down: SweepOutputs,
+    coexisting: Vec<(f64, String, String)>
+}
+
+#[pymethods]
+impl HysteresisOutputs {
+    pub fn parameter(&self) -> String {
+        self.parameter.clone()
+    }
+
+    pub fn up(&self) -> SweepOutputs {
+        self.up.clone()
+    }
+
+    pub fn down(&self) -> SweepOutputs {
+        self.down.clone()
+    }
+
+    pub fn coexisting(&self) -> Vec<(f64, String, String)> {
+        self.coexisting.clone()
+    }
+}
+
+// Sweeps `parameter` up over a range given as (minimum, maximum, number of values) and back down again, starting
+// from the initial impact of `inputs` and carrying the final impact at each value on to the next. Gives None if it is
+// cancelled.
+#[pyfunction(transient = "1000", recorded = "100", cancellation = "None")]
+fn hysteresis_sweep(py: Python, inputs: IterationInputs, parameter: &str, range: (f64, f64, u32), transient: u32, recorded: u32,
+    cancellation: Option<CancellationToken>) -> PyResult<Option<HysteresisOutputs>> {
+    use pyo3::exceptions::*;
+
+    let name = ParameterName::from_name(parameter).ok_or_else(|| PyValueError::new_err(format!("Cannot sweep parameter {}", parameter)))?;
+
+    // There would be nothing to classify at each value
+    if recorded == 0 {
+        return Err(PyValueError::new_err("At least one impact must be recorded at each parameter value"));
+    }
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let sweep = ParameterSweep::new(parameters, ChartAxis::new(name, range.0, range.1, range.2), transient, recorded);
+
+    let token = cancellation.unwrap_or_default();
+
+    let hysteresis = match py.allow_threads(|| sweep.hysteresis((inputs.phi, inputs.v), &token.flag)) {
+        Some(hysteresis) => hysteresis,
+        None => return Ok(None)
+    };
+
+    Ok(Some(HysteresisOutputs{
+        parameter: name.name().to_string(),
+        up: SweepOutputs::from(hysteresis.up()),
+        down: SweepOutputs::from(hysteresis.down()),
+        coexisting: hysteresis.coexisting().iter().map(|(value, up, down)| (*value, up.to_string(), down.to_string())).collect()
+    }))
+}
+
+// Impacts from a run in which one parameter follows a schedule, each paired with the time since the start of the
+// run and the value of the parameter at that time
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct ScheduledOutputs {
+    parameter: String,
+    times: Vec<Time>,
+    phases: Vec<Phase>,
+    velocities: Vec<Velocity>,
+    values: Vec<f64>,
+    termination: String
+}
+
+#[pymethods]
+impl ScheduledOutputs {
+    pub fn parameter(&self) -> String {
+        self.parameter.clone()
+    }
+
+    pub fn times<'py>(&self, py: Python<'py>) -> &'py PyArray1<Time> {
+        PyArray1::from_slice(py, &self.times)
+    }
+
+    pub fn phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phases)
+    }
+
+    pub fn velocities<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_slice(py, &self.velocities)
+    }
+
+    pub fn values<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.values)
+    }
+
+    pub fn termination(&self) -> String {
+        self.termination.clone()
+    }
+}
+
+// Iterates from the initial impact of `inputs` with `parameter` following a piecewise-linear schedule of
+// (time, value) breakpoints. The other parameters are taken from `inputs`.
+#[pyfunction]
+fn iterate_with_schedule(py: Python, inputs: IterationInputs, parameter: &str, schedule: Vec<(Time, f64)>) -> PyResult<ScheduledOutputs> {
+    use pyo3::exceptions::*;
+
+    let name = ParameterName::from_name(parameter).ok_or_else(|| PyValueError::new_err(format!("Cannot schedule parameter {}", parameter)))?;
+
+    let schedule = ParameterSchedule::new(name, &schedule).ok_or_else(|| PyValueError::new_err("A schedule needs at least one breakpoint, with finite times and values"))?;
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let iterator = ScheduledIterator::new(parameters, schedule);
+
+    let result = py.allow_threads(|| iterator.iterate_from_point(inputs.phi, inputs.v, inputs.num_iterations));
+
+    Ok(ScheduledOutputs{
+        parameter: name.name().to_string(),
+        times: result.impacts().iter().map(|impact| impact.time).collect(),
+        phases: result.impacts().iter().map(|impact| impact.phase).collect(),
+        velocities: result.impacts().iter().map(|impact| impact.velocity).collect(),
+        values: result.impacts().iter().map(|impact| impact.parameter).collect(),
+        termination: result.termination().to_string()
+    })
+}
+
+// The circle map to which the impact map reduces when r = 0: its graph, with NaN where a phase has no image, and
+// the phases visited from the initial phase of the inputs
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct CircleMapOutputs {
+    phases: Vec<Phase>,
+    images: Vec<Phase>,
+    orbit: Vec<Phase>
+}
+
+#[pymethods]
+impl CircleMapOutputs {
+    pub fn phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phases)
+    }
+
+    pub fn images<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.images)
+    }
+
+    pub fn orbit<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.orbit)
+    }
+}
+
+#[pyfunction(num_points="200")]
+fn circle_map(py: Python, inputs: IterationInputs, num_points: u32) -> PyResult<CircleMapOutputs> {
+    use pyo3::exceptions::*;
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let circle_map = CircleMap::new(parameters).ok_or_else(|| PyValueError::new_err("The impact map is only a circle map when the coefficient of restitution is zero"))?;
+
+    let (graph, orbit) = py.allow_threads(|| (circle_map.graph(num_points), circle_map.iterate(inputs.phi, inputs.num_iterations)));
+
+    Ok(CircleMapOutputs{
+        phases: graph.iter().map(|(phase, _)| *phase).collect(),
+        images: graph.iter().map(|(_, image)| image.unwrap_or(f64::NAN)).collect(),
+        orbit
+    })
+}
+
+// Estimated invariant density over the impact surface, as a grid with one row per velocity bin, together with the
+// edges of the bins
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct DensityOutputs {
+    phase_edges: Vec<Phase>,
+    velocity_edges: Vec<Velocity>,
+    density: Vec<Vec<f64>>,
+    total: u64
+}
+
+#[pymethods]
+impl DensityOutputs {
+    pub fn phase_edges<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phase_edges)
+    }
+
+    pub fn velocity_edges<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_slice(py, &self.velocity_edges)
+    }
+
+    pub fn density(&self) -> Vec<Vec<f64>> {
+        self.density.clone()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+// Iterates from the initial impact of `inputs`, adding every impact after the first `skip_impacts` to a histogram
+// whose size is fixed by the numbers of bins. `keep_every` and `max_points` are not used. Cancelling stops the
+// iteration early, with the density of the impacts added so far.
+#[pyfunction(phase_bins="100", velocity_bins="100", cancellation="None")]
+fn invariant_density(py: Python, inputs: IterationInputs, phase_bins: usize, velocity_bins: usize, cancellation: Option<CancellationToken>) -> PyResult<DensityOutputs> {
+    let token = cancellation.unwrap_or_default();
+
+    let mapper = inputs.mapper().map_err(parameter_errors_to_py)?;
+
+    let transient = std::cmp::min(inputs.skip_impacts, inputs.num_iterations);
+
+    let mut histogram = DensityHistogram::new(phase_bins, velocity_bins);
+
+    py.allow_threads(|| histogram.accumulate(&mapper, mapper.impact_from_point(inputs.phi, inputs.v), transient, inputs.num_iterations - transient, &token.flag));
+
+    Ok(DensityOutputs{
+        phase_edges: histogram.phase_edges(),
+        velocity_edges: histogram.velocity_edges(),
+        density: histogram.density(),
+        total: histogram.total()
+    })
+}
+
+// A fractal dimension with its error bar, and the log-log plot it was estimated from. The scaling region is given
+// by the indices of its first and last points in the plot.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct DimensionOutputs {
+    estimate: DimensionEstimate
+}
+
+#[pymethods]
+impl DimensionOutputs {
+    pub fn method(&self) -> String {
+        self.estimate.method().to_string()
+    }
+
+    pub fn dimension(&self) -> f64 {
+        self.estimate.dimension()
+    }
+
+    pub fn error(&self) -> f64 {
+        self.estimate.error()
+    }
+
+    pub fn log_scales<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, self.estimate.log_scales())
+    }
+
+    pub fn log_measures<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, self.estimate.log_measures())
+    }
+
+    pub fn scaling_region(&self) -> (usize, usize) {
+        (self.estimate.fit().from, self.estimate.fit().to)
+    }
+
+    pub fn intercept(&self) -> f64 {
+        self.estimate.fit().intercept
+    }
+}
+
+// Estimates the dimension of the impacts recorded from iterating `inputs`, by `method` ("correlation" or
+// "box-counting"). Transient impacts should be left out with `skip_impacts`. Box counting ignores any scales
+// beyond the resolution of the phase.
+#[pyfunction(method="\"correlation\"", num_scales="20")]
+fn fractal_dimension(py: Python, inputs: IterationInputs, method: &str, num_scales: usize) -> PyResult<DimensionOutputs> {
+    use pyo3::exceptions::*;
+
+    let estimator = match method {
+        "correlation" => correlation_dimension,
+        "box-counting" => box_counting_dimension,
+        _ => return Err(PyValueError::new_err(format!("Unknown method {} for estimating dimension", method)))
+    };
+
+    let result = py.allow_threads(|| inputs.iterate()).map_err(parameter_errors_to_py)?;
+
+    let points: Vec<SimpleImpact> = result.trajectory().iter().map(|impact| impact.get_simple_impact()).collect();
+
+    let estimate = py.allow_threads(|| estimator(&points, num_scales))
+        .ok_or_else(|| PyValueError::new_err("Too few distinct scales to estimate a dimension"))?;
+
+    Ok(DimensionOutputs{estimate})
+}
+
+// A recurrence plot as the (row, column) indices of its recurrent pairs above the main diagonal, which is enough
+// to rebuild the whole (symmetric) matrix, with its recurrence quantification measures
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct RecurrenceOutputs {
+    size: usize,
+    rows: Vec<usize>,
+    columns: Vec<usize>,
+    quantification: RecurrenceQuantification
+}
+
+#[pymethods]
+impl RecurrenceOutputs {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn rows<'py>(&self, py: Python<'py>) -> &'py PyArray1<usize> {
+        PyArray1::from_slice(py, &self.rows)
+    }
+
+    pub fn columns<'py>(&self, py: Python<'py>) -> &'py PyArray1<usize> {
+        PyArray1::from_slice(py, &self.columns)
+    }
+
+    pub fn recurrence_rate(&self) -> f64 {
+        self.quantification.recurrence_rate
+    }
+
+    pub fn determinism(&self) -> f64 {
+        self.quantification.determinism
+    }
+
+    pub fn laminarity(&self) -> f64 {
+        self.quantification.laminarity
+    }
+
+    pub fn longest_diagonal_line(&self) -> usize {
+        self.quantification.longest_diagonal_line
+    }
+}
+
+// Recurrence of the impacts recorded from iterating `inputs`, compared with an absolute tolerance in phase and a
+// relative tolerance in velocity. Cancelling stops both the iteration and the comparison of impacts, giving None.
+#[pyfunction(phase_tolerance="1e-3", velocity_tolerance="1e-3", cancellation="None")]
+fn recurrence(py: Python, inputs: IterationInputs, phase_tolerance: f64, velocity_tolerance: f64, cancellation: Option<CancellationToken>) -> PyResult<Option<RecurrenceOutputs>> {
+    let token = cancellation.unwrap_or_default();
+
+    let result = py.allow_threads(|| inputs.iterate_with_control(IterationControl::new().with_cancellation(&token.flag))).map_err(parameter_errors_to_py)?;
+
+    let plot = match py.allow_threads(|| RecurrencePlot::new(result.trajectory(), SimpleImpact::new(phase_tolerance, velocity_tolerance), &token.flag)) {
+        Some(plot) => plot,
+        None => return Ok(None)
+    };
+
+    let pairs = plot.pairs();
+
+    Ok(Some(RecurrenceOutputs{
+        size: plot.size(),
+        rows: pairs.iter().map(|(i, _)| *i).collect(),
+        columns: pairs.iter().map(|(_, j)| *j).collect(),
+        quantification: plot.quantification()
+    }))
+}
+
+// A trajectory as a string of symbols, with the distinct words of the longest length considered and the entropies
+// estimated from words of every length up to it
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct SymbolicOutputs {
+    symbols: Vec<Symbol>,
+    words: Vec<(Word, usize)>,
+    entropy: EntropyEstimate
+}
+
+#[pymethods]
+impl SymbolicOutputs {
+    pub fn symbols<'py>(&self, py: Python<'py>) -> &'py PyArray1<Symbol> {
+        PyArray1::from_slice(py, &self.symbols)
+    }
+
+    pub fn words(&self) -> Vec<(Word, usize)> {
+        self.words.clone()
+    }
+
+    pub fn block_entropies<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.entropy.block_entropies)
+    }
+
+    pub fn word_counts(&self) -> Vec<usize> {
+        self.entropy.word_counts.clone()
+    }
+
+    pub fn topological_entropy(&self) -> f64 {
+        self.entropy.topological_entropy
+    }
+
+    pub fn entropy_rate(&self) -> f64 {
+        self.entropy.entropy_rate
+    }
+}
+
+// Encodes the impacts recorded from iterating `inputs` by the number of forcing periods between impacts or, if
+// `partition` gives the boundaries of arcs of phase, by the arc in which each impact falls. Symbols are taken
+// between consecutive impacts, so `keep_every` is not used.
+#[pyfunction(max_word_length="6", partition="None")]
+fn symbolic_dynamics(py: Python, inputs: IterationInputs, max_word_length: usize, partition: Option<Vec<Phase>>) -> PyResult<SymbolicOutputs> {
+    use pyo3::exceptions::*;
+
+    let encoding = match partition {
+        None => SymbolicEncoding::PeriodsElapsed,
+        Some(boundaries) => SymbolicEncoding::phase_partition(&boundaries)
+            .ok_or_else(|| PyValueError::new_err("A phase partition needs at least two distinct boundaries in [0, 1)"))?
+    };
+
+    let consecutive = IterationInputs{keep_every: 1, ..inputs};
+
+    let result = py.allow_threads(|| consecutive.iterate()).map_err(parameter_errors_to_py)?;
+
+    let sequence = SymbolSequence::encode(result.trajectory(), &encoding);
+
+    let entropy = sequence.entropy(max_word_length)
+        .ok_or_else(|| PyValueError::new_err(format!("Too few impacts for words of length {}", max_word_length)))?;
+
+    Ok(SymbolicOutputs{
+        words: sequence.words(max_word_length).into_iter().collect(),
+        symbols: sequence.symbols().to_vec(),
+        entropy
+    })
+}
+
+// Rotation numbers (forcing periods per impact) as one parameter varies, with NaN where there is none, and the
+// locking (periods, impacts) where the impacts are locked to the forcing
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct RotationOutputs {
+    parameter: String,
+    values: Vec<f64>,
+    rotation_numbers: Vec<f64>,
+    lockings: Vec<Option<(u32, u32)>>
+}
+
+#[pymethods]
+impl RotationOutputs {
+    pub fn parameter(&self) -> String {
+        self.parameter.clone()
+    }
+
+    pub fn values<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.values)
+    }
+
+    pub fn rotation_numbers<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.rotation_numbers)
+    }
+
+    pub fn lockings(&self) -> Vec<Option<(u32, u32)>> {
+        self.lockings.clone()
+    }
+}
+
+fn rotation_number_or_nan(point: &RotationPoint) -> f64 {
+    point.rotation_number.unwrap_or(f64::NAN)
+}
+
+fn locking_as_pair(point: &RotationPoint) -> Option<(u32, u32)> {
+    point.locking.map(|locking| (locking.periods, locking.impacts))
+}
+
+// Rotation numbers from the initial impact of `inputs` as `parameter` varies over a range given as (minimum,
+// maximum, number of values). Swept over "frequency" this is the devil's staircase. The impacts are chosen by
+// `transient` and `recorded`, not by the sampling of `inputs`, since the rotation number needs consecutive impacts.
+// Gives None if it is cancelled.
+#[pyfunction(transient = "1000", recorded = "200", max_impacts = "20", tolerance = "1e-3", cancellation = "None")]
+#[allow(clippy::too_many_arguments)]
+fn rotation_staircase(py: Python, inputs: IterationInputs, parameter: &str, range: (f64, f64, u32), transient: u32, recorded: u32, max_impacts: u32, tolerance: f64,
+    cancellation: Option<CancellationToken>) -> PyResult<Option<RotationOutputs>> {
+    use pyo3::exceptions::*;
+
+    let name = ParameterName::from_name(parameter).ok_or_else(|| PyValueError::new_err(format!("Cannot sweep parameter {}", parameter)))?;
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let sweep = RotationSweep::new(parameters, (inputs.phi, inputs.v), transient, recorded, LockingDetector::new(max_impacts, tolerance));
+
+    let token = cancellation.unwrap_or_default();
+
+    let staircase = match py.allow_threads(|| sweep.staircase(ChartAxis::new(name, range.0, range.1, range.2), &token.flag)) {
+        Some(staircase) => staircase,
+        None => return Ok(None)
+    };
+
+    Ok(Some(RotationOutputs{
+        parameter: name.name().to_string(),
+        values: staircase.iter().map(|(value, _)| *value).collect(),
+        rotation_numbers: staircase.iter().map(|(_, point)| rotation_number_or_nan(point)).collect(),
+        lockings: staircase.iter().map(|(_, point)| locking_as_pair(point)).collect()
+    }))
+}
+
+// Rotation numbers over a grid of forcing frequency and one other parameter, with a row for each value of the
+// other parameter, showing the Arnold tongues where the impacts lock to the forcing
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct RotationChartOutputs {
+    parameter: String,
+    frequencies: Vec<f64>,
+    values: Vec<f64>,
+    rotation_numbers: Vec<Vec<f64>>,
+    lockings: Vec<Vec<Option<(u32, u32)>>>
+}
+
+#[pymethods]
+impl RotationChartOutputs {
+    pub fn parameter(&self) -> String {
+        self.parameter.clone()
+    }
+
+    pub fn frequencies<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.frequencies)
+    }
+
+    pub fn values<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.values)
+    }
+
+    pub fn rotation_numbers(&self) -> Vec<Vec<f64>> {
+        self.rotation_numbers.clone()
+    }
+
+    pub fn lockings(&self) -> Vec<Vec<Option<(u32, u32)>>> {
+        self.lockings.clone()
+    }
+}
+
+// Ranges are given as for `orbit_chart`. Gives None if it is cancelled.
+#[pyfunction(transient = "1000", recorded = "200", max_impacts = "20", tolerance = "1e-3", cancellation = "None")]
+#[allow(clippy::too_many_arguments)]
+fn rotation_tongues(py: Python, inputs: IterationInputs, parameter: &str, frequency_range: (f64, f64, u32), parameter_range: (f64, f64, u32),
+    transient: u32, recorded: u32, max_impacts: u32, tolerance: f64, cancellation: Option<CancellationToken>) -> PyResult<Option<RotationChartOutputs>> {
+    use pyo3::exceptions::*;
+
+    let name = match ParameterName::from_name(parameter) {
+        Some(ParameterName::Frequency) | None => return Err(PyValueError::new_err(format!("Cannot chart forcing frequency against {}", parameter))),
+        Some(name) => name
+    };
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let sweep = RotationSweep::new(parameters, (inputs.phi, inputs.v), transient, recorded, LockingDetector::new(max_impacts, tolerance));
+
+    let token = cancellation.unwrap_or_default();
+
+    let chart = match py.allow_threads(|| sweep.tongues(
+        ChartAxis::new(ParameterName::Frequency, frequency_range.0, frequency_range.1, frequency_range.2),
+        ChartAxis::new(name, parameter_range.0, parameter_range.1, parameter_range.2),
+        &token.flag)) {
+        Some(chart) => chart,
+        None => return Ok(None)
+    };
+
+    let columns = chart.horizontal().len().max(1);
+
+    Ok(Some(RotationChartOutputs{
+        parameter: parameter.to_string(),
+        frequencies: chart.horizontal().to_vec(),
+        values: chart.vertical().to_vec(),
+        rotation_numbers: chart.cells().chunks(columns).map(|row| row.iter().map(rotation_number_or_nan).collect()).collect(),
+        lockings: chart.cells().chunks(columns).map(|row| row.iter().map(locking_as_pair).collect()).collect()
+    }))
+}
+
+// Transient lengths and attractor labels over a grid of initial impacts, with a row for each initial velocity.
+// `lengths` is None where the trajectory has no length of transient (chaos or long excursions), and `cells` holds
+// indices into `legend` as for `OrbitChartOutputs`.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct TransientOutputs {
+    phases: Vec<Phase>,
+    velocities: Vec<Velocity>,
+    lengths: Vec<Vec<Option<u32>>>,
+    cells: Vec<Vec<usize>>,
+    legend: Vec<String>
+}
+
+#[pymethods]
+impl TransientOutputs {
+    pub fn phases<'py>(&self, py: Python<'py>) -> &'py PyArray1<Phase> {
+        PyArray1::from_slice(py, &self.phases)
+    }
+
+    pub fn velocities<'py>(&self, py: Python<'py>) -> &'py PyArray1<Velocity> {
+        PyArray1::from_slice(py, &self.velocities)
+    }
+
+    pub fn lengths(&self) -> Vec<Vec<Option<u32>>> {
+        self.lengths.clone()
+    }
+
+    pub fn cells(&self) -> Vec<Vec<usize>> {
+        self.cells.clone()
+    }
+
+    pub fn legend(&self) -> Vec<String> {
+        self.legend.clone()
+    }
+}
+
+// Iterates from every combination of initial phase and velocity in ranges given as (minimum, maximum, number of
+// values), for the number of iterations in `inputs`, and measures how many impacts it takes to settle. There is no
+// grid if it is cancelled.
+#[pyfunction(phase_tolerance = "1e-3", velocity_tolerance = "1e-3", cancellation = "None")]
+#[allow(clippy::too_many_arguments)]
+fn transient_lengths(py: Python, inputs: IterationInputs, phase_range: (Phase, Phase, u32), velocity_range: (Velocity, Velocity, u32), phase_tolerance: f64, velocity_tolerance: f64,
+    cancellation: Option<CancellationToken>) -> PyResult<Option<TransientOutputs>> {
+    let token = cancellation.unwrap_or_default();
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let analyser = TransientAnalyser::new(parameters, inputs.num_iterations, SimpleImpact::new(phase_tolerance, velocity_tolerance));
+
+    let phases = evenly_spaced(phase_range.0, phase_range.1, phase_range.2);
+    let velocities = evenly_spaced(velocity_range.0, velocity_range.1, velocity_range.2);
+
+    let grid = match py.allow_threads(|| analyser.grid(&phases, &velocities, &token.flag)) {
+        Some(grid) => grid,
+        None => return Ok(None)
+    };
+
+    let columns = phases.len().max(1);
+
+    Ok(Some(TransientOutputs{
+        lengths: grid.lengths().chunks(columns).map(|row| row.to_vec()).collect(),
+        cells: grid.cells().chunks(columns).map(|row| row.to_vec()).collect(),
+        legend: grid.legend().iter().map(|label| label.to_string()).collect(),
+        phases: grid.phases().to_vec(),
+        velocities: grid.velocities().to_vec()
+    }))
+}
+
+// The fraction of sampled initial conditions which end on each attractor, with the bounds of its 95% confidence
+// interval, in the order in which the attractors were first reached
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct BasinStabilityOutputs {
+    estimate: BasinStabilityEstimate
+}
+
+#[pymethods]
+impl BasinStabilityOutputs {
+    pub fn labels(&self) -> Vec<String> {
+        self.estimate.fractions().iter().map(|fraction| fraction.label.to_string()).collect()
+    }
+
+    pub fn counts(&self) -> Vec<u64> {
+        self.estimate.fractions().iter().map(|fraction| fraction.count).collect()
+    }
+
+    pub fn fractions<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.estimate.fractions().iter().map(|fraction| fraction.fraction).collect::<Vec<f64>>())
+    }
+
+    pub fn lower<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.estimate.fractions().iter().map(|fraction| fraction.lower).collect::<Vec<f64>>())
+    }
+
+    pub fn upper<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.estimate.fractions().iter().map(|fraction| fraction.upper).collect::<Vec<f64>>())
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.estimate.samples()
+    }
+
+    pub fn converged(&self) -> bool {
+        self.estimate.converged()
+    }
+}
+
+// Samples initial impacts with phase and velocity in `phase_range` and `velocity_range` or, if `displacement_range`
+// is given instead, states of motion with displacement and velocity in `displacement_range` and `velocity_range`.
+// Each is iterated for the number of iterations in `inputs` until every confidence interval is within `precision`.
+// Cancelling stops sampling after the current batch, leaving an estimate which has not converged.
+#[pyfunction(phase_range = "None", displacement_range = "None", seed = "0", precision = "0.01", batch_size = "100", max_samples = "10000", cancellation = "None")]
+#[allow(clippy::too_many_arguments)]
+fn basin_stability(py: Python, inputs: IterationInputs, velocity_range: (Velocity, Velocity), phase_range: Option<(Phase, Phase)>, displacement_range: Option<(Distance, Distance)>,
+    seed: u64, precision: f64, batch_size: u64, max_samples: u64, cancellation: Option<CancellationToken>) -> PyResult<BasinStabilityOutputs> {
+    use pyo3::exceptions::*;
+
+    let token = cancellation.unwrap_or_default();
+
+    let region = match (phase_range, displacement_range) {
+        (Some(phases), None) => SamplingRegion::impact_surface(phases, velocity_range)
+            .ok_or_else(|| PyValueError::new_err("Ranges must be in increasing order, with impact velocities not negative"))?,
+        (None, Some(displacements)) => SamplingRegion::states_of_motion(displacements, velocity_range)
+            .ok_or_else(|| PyValueError::new_err("Ranges must be in increasing order"))?,
+        _ => return Err(PyValueError::new_err("Either a phase range or a displacement range must be given, but not both"))
+    };
+
+    let parameters = inputs.get_parameters().map_err(parameter_errors_to_py)?;
+
+    let basin = BasinStability::new(parameters, region, inputs.num_iterations, seed);
+
+    let estimate = py.allow_threads(|| basin.estimate(precision, batch_size, max_samples, &token.flag));
+
+    Ok(BasinStabilityOutputs{estimate})
+}
+
+#[pyfunction]
+fn validate(inputs: IterationInputs) -> IterationInputs {
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn can_access_parameter_properties() {
+        let properties = ParameterProperties::from(vec![("frequency", "ω")]);
+        assert_eq!(properties.property("frequency").property, String::from("ω"));
+        assert_eq!(properties.property("period").property, String::from(""));
+    }
+
+    #[test]
+    fn can_run_to_completion() {
+        let inputs = IterationInputs{
+            frequency: 4.85,
+            offset: -0.1,
+            r: 0.8,
+            max_periods: 100,
+            phi: 0.0,
+            v: 0.0,
+            num_iterations: 1000,
+            ..IterationInputs::default()
+        };
+        
+        let result = inputs.iterate().unwrap();
+
+        let n = u32::try_from(result.trajectory().len()).unwrap();
+
+        assert_eq!(inputs.num_iterations+1, n);
+    }
+
+    #[test]
+    fn inputs_control_which_impacts_are_returned() {
+        let inputs = IterationInputs{
+            frequency: 4.85,
+            offset: -0.1,
+            r: 0.8,
+            max_periods: 100,
+            phi: 0.0,
+            v: 0.0,
+            num_iterations: 1000,
+            skip_impacts: 100,
+            keep_every: 10,
+            max_points: 50
+        };
+
+        let result = inputs.iterate().unwrap();
+
+        assert_eq!(result.trajectory().len(), 50);
+    }
+
+    #[test]
+    fn indices_count_back_from_the_end() {
+        assert_eq!(python_index(1, 3), Some(1));
+        assert_eq!(python_index(-1, 3), Some(2));
+        assert_eq!(python_index(3, 3), None);
+        assert_eq!(python_index(-4, 3), None);
+        assert_eq!(python_index(-1, 0), None);
+    }
+
+    #[test]
+    fn pre_images_are_reported_as_phases() {
+        let inputs = IterationInputs{frequency: 2.8, offset: 0.1, r: 0.8, max_periods: 100, phi: 0.3, v: 0.5, num_iterations: 1, ..IterationInputs::default()};
+
+        let image = inputs.iterate().unwrap().trajectory()[1];
+
+        let pre_images = IterationInputs{phi: image.phase(), v: image.velocity(), ..inputs}.pre_images().unwrap();
+
+        assert!(pre_images.iter().any(|pre_image| pre_image.kind() == "impact" && (pre_image.phase() - 0.3).abs() < 1e-3));
     }
 }